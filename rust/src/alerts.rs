@@ -0,0 +1,598 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::Result;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    BoxFuture, Client, InstanceStatusKind, SendChatRequest, ServerResourcesResponse,
+    SystemResourcesPeriod, poll_every,
+};
+
+const ANOMALY_MIN_SAMPLES: usize = 10;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Cpu,
+    /// RAM usage in percent of `max_ram`.
+    RamPercent,
+    ServerFps,
+    Players,
+}
+
+impl Metric {
+    fn samples(&self, resources: &ServerResourcesResponse) -> Vec<(SystemTime, f64)> {
+        match self {
+            Metric::Cpu => resources.cpu_samples(),
+            Metric::RamPercent => resources.ram_percent_samples(),
+            Metric::ServerFps => resources.server_fps_samples(),
+            Metric::Players => resources.player_samples(),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Metric::Cpu => write!(f, "cpu"),
+            Metric::RamPercent => write!(f, "ram %"),
+            Metric::ServerFps => write!(f, "server fps"),
+            Metric::Players => write!(f, "players"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    Above(f64),
+    Below(f64),
+}
+
+impl Condition {
+    pub fn matches(&self, value: f64) -> bool {
+        match self {
+            Condition::Above(threshold) => value > *threshold,
+            Condition::Below(threshold) => value < *threshold,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Above(threshold) => write!(f, "above {threshold}"),
+            Condition::Below(threshold) => write!(f, "below {threshold}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RuleKind {
+    Threshold {
+        condition: Condition,
+        sustained: Duration,
+        guards: Vec<(Metric, Condition)>,
+    },
+    Anomaly {
+        deviations: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    name: String,
+    metric: Metric,
+    kind: RuleKind,
+}
+
+impl AlertRule {
+    /// Fires when `metric` matches `condition`, by default on the latest sample.
+    pub fn threshold(name: impl Into<String>, metric: Metric, condition: Condition) -> Self {
+        Self {
+            name: name.into(),
+            metric,
+            kind: RuleKind::Threshold {
+                condition,
+                sustained: Duration::ZERO,
+                guards: Vec::new(),
+            },
+        }
+    }
+
+    /// Fires when the latest sample of `metric` is more than `deviations`
+    /// standard deviations away from the mean of the earlier samples.
+    pub fn anomaly(name: impl Into<String>, metric: Metric, deviations: f64) -> Self {
+        Self {
+            name: name.into(),
+            metric,
+            kind: RuleKind::Anomaly { deviations },
+        }
+    }
+
+    /// Requires the condition to hold for every sample within `duration`.
+    pub fn sustained(mut self, duration: Duration) -> Self {
+        if let RuleKind::Threshold { sustained, .. } = &mut self.kind {
+            *sustained = duration;
+        }
+        self
+    }
+
+    /// Only fires while `metric` also matches `condition`, e.g. players above 10.
+    /// Like the rule itself, the guard has to hold for the sustained duration.
+    pub fn when(mut self, metric: Metric, condition: Condition) -> Self {
+        if let RuleKind::Threshold { guards, .. } = &mut self.kind {
+            guards.push((metric, condition));
+        }
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the offending value when the rule fires.
+    pub fn evaluate(&self, resources: &ServerResourcesResponse) -> Option<f64> {
+        let samples = self.metric.samples(resources);
+        let (latest_time, latest) = *samples.last()?;
+
+        match &self.kind {
+            RuleKind::Threshold {
+                condition,
+                sustained,
+                guards,
+            } => {
+                let since = latest_time.checked_sub(*sustained)?;
+                // Not enough history to tell whether it held the whole time.
+                if samples.first().is_none_or(|(time, _)| *time > since) {
+                    return None;
+                }
+
+                let window = samples.iter().filter(|(time, _)| *time >= since);
+                if !window.clone().all(|(_, value)| condition.matches(*value)) {
+                    return None;
+                }
+
+                let guarded = guards.iter().all(|(metric, condition)| {
+                    let samples = metric.samples(resources);
+                    // A series without samples in the window falls back to its
+                    // latest value.
+                    let start = samples
+                        .iter()
+                        .position(|(time, _)| *time >= since)
+                        .unwrap_or(samples.len().saturating_sub(1));
+                    let window = &samples[start..];
+
+                    !window.is_empty() && window.iter().all(|(_, value)| condition.matches(*value))
+                });
+                guarded.then_some(latest)
+            }
+            RuleKind::Anomaly { deviations } => {
+                let history = &samples[..samples.len() - 1];
+                if history.len() < ANOMALY_MIN_SAMPLES {
+                    return None;
+                }
+
+                let count = history.len() as f64;
+                let mean = history.iter().map(|(_, value)| value).sum::<f64>() / count;
+                let variance = history
+                    .iter()
+                    .map(|(_, value)| (value - mean).powi(2))
+                    .sum::<f64>()
+                    / count;
+                let deviation = variance.sqrt();
+
+                (deviation > 0.0 && (latest - mean).abs() > deviations * deviation)
+                    .then_some(latest)
+            }
+        }
+    }
+
+    fn describe(&self, value: f64) -> String {
+        match &self.kind {
+            RuleKind::Threshold {
+                condition,
+                sustained,
+                ..
+            } if sustained.is_zero() => format!("{} is {value:.1}, {condition}", self.metric),
+            RuleKind::Threshold {
+                condition,
+                sustained,
+                ..
+            } => format!(
+                "{} is {value:.1}, {condition} for {}s",
+                self.metric,
+                sustained.as_secs()
+            ),
+            RuleKind::Anomaly { deviations } => format!(
+                "{} is {value:.1}, more than {deviations} deviations from normal",
+                self.metric
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Alert {
+    pub rule: String,
+    pub server: Uuid,
+    pub state: AlertState,
+    pub message: String,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.state {
+            AlertState::Firing => write!(f, "[{}] {}: {}", self.rule, self.server, self.message),
+            AlertState::Resolved => write!(f, "[{}] {}: resolved", self.rule, self.server),
+        }
+    }
+}
+
+pub trait AlertSink: Send + Sync {
+    fn notify<'a>(&'a self, client: &'a Client, alert: &'a Alert) -> BoxFuture<'a, Result<()>>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogSink;
+
+impl AlertSink for LogSink {
+    fn notify<'a>(&'a self, _client: &'a Client, alert: &'a Alert) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            match alert.state {
+                AlertState::Firing => log::warn!("{alert}"),
+                AlertState::Resolved => log::info!("{alert}"),
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Posts alerts in the server chat. The chat API has no private messages, so
+/// everyone on the server sees them; keep `prefix` recognisable for admins.
+#[derive(Debug, Clone)]
+pub struct ChatSink {
+    prefix: String,
+}
+
+impl ChatSink {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+}
+
+impl AlertSink for ChatSink {
+    fn notify<'a>(&'a self, client: &'a Client, alert: &'a Alert) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let msg = match alert.state {
+                AlertState::Firing => format!("{} {}", self.prefix, alert.message),
+                AlertState::Resolved => format!("{} resolved: {}", self.prefix, alert.rule),
+            };
+            client
+                .send_chat(&alert.server, &SendChatRequest { all: true, msg })
+                .await
+        })
+    }
+}
+
+/// Restarts the server when an alert fires. Limit it to the rules that should
+/// restart with [`AlertManager::sink_for`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RestartSink;
+
+impl AlertSink for RestartSink {
+    fn notify<'a>(&'a self, client: &'a Client, alert: &'a Alert) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if alert.state == AlertState::Firing {
+                client.restart_server(&alert.server).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+struct RoutedSink {
+    sink: Arc<dyn AlertSink>,
+    /// Rule names the sink receives; every rule when `None`.
+    rules: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActiveAlert {
+    notified_at: Instant,
+}
+
+pub struct AlertManager {
+    rules: Vec<AlertRule>,
+    sinks: Vec<RoutedSink>,
+    servers: Option<Vec<Uuid>>,
+    period: SystemResourcesPeriod,
+    repeat_after: Option<Duration>,
+    active: HashMap<(Uuid, String), ActiveAlert>,
+}
+
+impl Default for AlertManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlertManager {
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            sinks: Vec::new(),
+            servers: None,
+            period: SystemResourcesPeriod::Hour,
+            repeat_after: None,
+            active: HashMap::new(),
+        }
+    }
+
+    pub fn rule(mut self, rule: AlertRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn sink(mut self, sink: impl AlertSink + 'static) -> Self {
+        self.sinks.push(RoutedSink {
+            sink: Arc::new(sink),
+            rules: None,
+        });
+        self
+    }
+
+    /// Adds a sink that only receives alerts of the named rules.
+    pub fn sink_for<S: Into<String>>(
+        mut self,
+        rules: impl IntoIterator<Item = S>,
+        sink: impl AlertSink + 'static,
+    ) -> Self {
+        self.sinks.push(RoutedSink {
+            sink: Arc::new(sink),
+            rules: Some(rules.into_iter().map(Into::into).collect()),
+        });
+        self
+    }
+
+    /// Limits the manager to `ids` instead of every running server.
+    pub fn servers(mut self, ids: impl IntoIterator<Item = Uuid>) -> Self {
+        self.servers = Some(ids.into_iter().collect());
+        self
+    }
+
+    /// Resource period fetched per poll. Must cover the longest sustained
+    /// duration of the rules.
+    pub fn period(mut self, period: SystemResourcesPeriod) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// Notifies again while an alert keeps firing. Alerts are only sent on
+    /// state changes by default.
+    pub fn repeat_after(mut self, interval: Duration) -> Self {
+        self.repeat_after = Some(interval);
+        self
+    }
+
+    /// Evaluates every rule for `server` and returns the alerts that changed
+    /// state, or are due to repeat.
+    pub fn evaluate(&mut self, server: Uuid, resources: &ServerResourcesResponse) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+
+        for rule in &self.rules {
+            let key = (server, rule.name.clone());
+
+            match rule.evaluate(resources) {
+                Some(value) => {
+                    let due = match self.active.get(&key) {
+                        None => true,
+                        Some(active) => self
+                            .repeat_after
+                            .is_some_and(|interval| active.notified_at.elapsed() >= interval),
+                    };
+
+                    if due {
+                        self.active.insert(
+                            key,
+                            ActiveAlert {
+                                notified_at: Instant::now(),
+                            },
+                        );
+                        alerts.push(Alert {
+                            rule: rule.name.clone(),
+                            server,
+                            state: AlertState::Firing,
+                            message: rule.describe(value),
+                        });
+                    }
+                }
+                None => {
+                    if self.active.remove(&key).is_some() {
+                        alerts.push(Alert {
+                            rule: rule.name.clone(),
+                            server,
+                            state: AlertState::Resolved,
+                            message: format!("{} recovered", rule.metric),
+                        });
+                    }
+                }
+            }
+        }
+
+        alerts
+    }
+
+    pub async fn dispatch(&self, client: &Client, alert: &Alert) {
+        for routed in &self.sinks {
+            if routed
+                .rules
+                .as_ref()
+                .is_some_and(|rules| !rules.contains(&alert.rule))
+            {
+                continue;
+            }
+
+            if let Err(error) = routed.sink.notify(client, alert).await {
+                log::warn!("failed to deliver alert {}: {error}", alert.rule);
+            }
+        }
+    }
+
+    pub async fn poll(&mut self, client: &Client) -> Result<Vec<Alert>> {
+        let servers = client.get_servers().await?;
+        let mut alerts = Vec::new();
+
+        for server in servers {
+            let id = server.instance.id;
+            if self.servers.as_ref().is_some_and(|ids| !ids.contains(&id))
+                || server.instance.status.kind() != InstanceStatusKind::ServerStarted
+            {
+                continue;
+            }
+
+            match client.get_server_resources(&id, self.period.clone()).await {
+                Ok(resources) => alerts.extend(self.evaluate(id, &resources)),
+                Err(error) => log::warn!("failed to read resources of {id}: {error}"),
+            }
+        }
+
+        for alert in &alerts {
+            self.dispatch(client, alert).await;
+        }
+
+        Ok(alerts)
+    }
+
+    pub async fn run(&mut self, client: &Client, interval: Duration) -> Result<()> {
+        poll_every!(interval, "alert evaluation", self.poll(client).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PrometheusSeries;
+
+    fn series(values: &[(f64, f64)]) -> Vec<PrometheusSeries> {
+        vec![PrometheusSeries {
+            values: values
+                .iter()
+                .map(|(time, value)| (*time, value.to_string()))
+                .collect(),
+        }]
+    }
+
+    fn resources(fps: &[(f64, f64)], players: &[(f64, f64)]) -> ServerResourcesResponse {
+        ServerResourcesResponse {
+            cpus: Vec::new(),
+            ram: Vec::new(),
+            max_ram: 0,
+            players: series(players),
+            server_fps: series(fps),
+        }
+    }
+
+    fn low_fps() -> AlertRule {
+        AlertRule::threshold("low fps", Metric::ServerFps, Condition::Below(20.0))
+            .sustained(Duration::from_secs(120))
+    }
+
+    #[test]
+    fn threshold_must_hold_for_the_whole_duration() {
+        let held = resources(&[(0.0, 15.0), (60.0, 10.0), (120.0, 12.0)], &[]);
+        assert_eq!(low_fps().evaluate(&held), Some(12.0));
+
+        let dipped = resources(&[(0.0, 15.0), (60.0, 30.0), (120.0, 12.0)], &[]);
+        assert_eq!(low_fps().evaluate(&dipped), None);
+
+        let short = resources(&[(60.0, 10.0), (120.0, 12.0)], &[]);
+        assert_eq!(low_fps().evaluate(&short), None);
+    }
+
+    #[test]
+    fn guards_must_hold_for_the_whole_duration() {
+        let rule = low_fps().when(Metric::Players, Condition::Above(10.0));
+        let fps = [(0.0, 15.0), (60.0, 10.0), (120.0, 12.0)];
+
+        let busy = resources(&fps, &[(0.0, 20.0), (60.0, 20.0), (120.0, 20.0)]);
+        assert_eq!(rule.evaluate(&busy), Some(12.0));
+
+        // Players only joined at the end, so the low FPS came before them.
+        let joined = resources(&fps, &[(0.0, 2.0), (60.0, 2.0), (120.0, 20.0)]);
+        assert_eq!(rule.evaluate(&joined), None);
+
+        let no_players = resources(&fps, &[]);
+        assert_eq!(rule.evaluate(&no_players), None);
+    }
+
+    #[test]
+    fn detects_anomalies() {
+        let rule = AlertRule::anomaly("fps drop", Metric::ServerFps, 3.0);
+        let mut fps: Vec<_> = (0..10)
+            .map(|index| (f64::from(index) * 60.0, 59.0 + f64::from(index % 2) * 2.0))
+            .collect();
+
+        fps.push((600.0, 60.0));
+        assert_eq!(rule.evaluate(&resources(&fps, &[])), None);
+
+        fps.pop();
+        fps.push((600.0, 20.0));
+        assert_eq!(rule.evaluate(&resources(&fps, &[])), Some(20.0));
+
+        assert_eq!(rule.evaluate(&resources(&fps[5..], &[])), None);
+    }
+
+    #[test]
+    fn reports_state_changes_only() {
+        let mut manager = AlertManager::new().rule(AlertRule::threshold(
+            "low fps",
+            Metric::ServerFps,
+            Condition::Below(20.0),
+        ));
+        let server = Uuid::from_u128(1);
+        let low = resources(&[(0.0, 10.0)], &[]);
+
+        let alerts = manager.evaluate(server, &low);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].state, AlertState::Firing);
+        assert_eq!(alerts[0].message, "server fps is 10.0, below 20");
+        assert!(manager.evaluate(server, &low).is_empty());
+
+        let alerts = manager.evaluate(server, &resources(&[(60.0, 60.0)], &[]));
+        assert_eq!(alerts[0].state, AlertState::Resolved);
+        assert!(
+            manager
+                .evaluate(server, &resources(&[(60.0, 60.0)], &[]))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn repeats_firing_alerts() {
+        let mut manager = AlertManager::new()
+            .rule(AlertRule::threshold(
+                "low fps",
+                Metric::ServerFps,
+                Condition::Below(20.0),
+            ))
+            .repeat_after(Duration::ZERO);
+        let low = resources(&[(0.0, 10.0)], &[]);
+
+        assert_eq!(manager.evaluate(Uuid::nil(), &low).len(), 1);
+        assert_eq!(manager.evaluate(Uuid::nil(), &low).len(), 1);
+    }
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use nimbuspulse_client::{Client, MetricsExporter};
+
+// Reads NIMBUSPULSE_API_KEY, and optionally NIMBUSPULSE_EXPORTER_ADDRESS and
+// NIMBUSPULSE_EXPORTER_INTERVAL (seconds).
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let api_key = std::env::var("NIMBUSPULSE_API_KEY")
+        .map_err(|_| anyhow::anyhow!("NIMBUSPULSE_API_KEY is not set"))?;
+    let address = std::env::var("NIMBUSPULSE_EXPORTER_ADDRESS")
+        .unwrap_or_else(|_| "0.0.0.0:9464".to_string());
+    let interval = std::env::var("NIMBUSPULSE_EXPORTER_INTERVAL")
+        .ok()
+        .and_then(|interval| interval.parse().ok())
+        .unwrap_or(30);
+
+    MetricsExporter::new(Client::new(api_key))
+        .interval(Duration::from_secs(interval))
+        .serve(address)
+        .await
+}
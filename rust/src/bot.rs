@@ -0,0 +1,497 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{BoxFuture, Client, DcsChat, Player, SendChatRequest, poll_every};
+
+pub type CommandHandler =
+    Arc<dyn Fn(Client, CommandContext) -> BoxFuture<'static, Result<Option<String>>> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    Player,
+    Moderator,
+    Admin,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandContext {
+    pub instance_id: Uuid,
+    pub command: String,
+    pub args: Vec<String>,
+    pub player: Player,
+    pub role: Role,
+    pub message: DcsChat,
+}
+
+#[derive(Clone)]
+pub struct Command {
+    name: String,
+    aliases: Vec<String>,
+    usage: Option<String>,
+    min_args: usize,
+    max_args: Option<usize>,
+    role: Role,
+    cooldown: Duration,
+    handler: CommandHandler,
+}
+
+impl Command {
+    pub fn new<F, Fut>(name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(Client, CommandContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Option<String>>> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            aliases: Vec::new(),
+            usage: None,
+            min_args: 0,
+            max_args: None,
+            role: Role::Player,
+            cooldown: Duration::ZERO,
+            handler: Arc::new(move |client, context| Box::pin(handler(client, context))),
+        }
+    }
+
+    pub fn alias(mut self, alias: impl Into<String>) -> Self {
+        self.aliases.push(alias.into());
+        self
+    }
+
+    pub fn usage(mut self, usage: impl Into<String>) -> Self {
+        self.usage = Some(usage.into());
+        self
+    }
+
+    pub fn args(mut self, min: usize, max: Option<usize>) -> Self {
+        self.min_args = min;
+        self.max_args = max;
+        self
+    }
+
+    pub fn role(mut self, role: Role) -> Self {
+        self.role = role;
+        self
+    }
+
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+}
+
+impl std::fmt::Debug for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Command")
+            .field("name", &self.name)
+            .field("aliases", &self.aliases)
+            .field("usage", &self.usage)
+            .field("min_args", &self.min_args)
+            .field("max_args", &self.max_args)
+            .field("role", &self.role)
+            .field("cooldown", &self.cooldown)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+pub struct ChatBot {
+    prefix: String,
+    commands: Vec<Command>,
+    roles: HashMap<String, Role>,
+    default_role: Role,
+    reply_to_all: bool,
+    last_message_ids: HashMap<Uuid, i32>,
+    cooldowns: HashMap<(String, String), Instant>,
+}
+
+impl Default for ChatBot {
+    fn default() -> Self {
+        Self::new("-")
+    }
+}
+
+impl ChatBot {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            commands: Vec::new(),
+            roles: HashMap::new(),
+            default_role: Role::Player,
+            reply_to_all: true,
+            last_message_ids: HashMap::new(),
+            cooldowns: HashMap::new(),
+        }
+    }
+
+    pub fn command(mut self, command: Command) -> Self {
+        self.commands.push(command);
+        self
+    }
+
+    pub fn grant(mut self, ucid: impl Into<String>, role: Role) -> Self {
+        self.roles.insert(ucid.into(), role);
+        self
+    }
+
+    pub fn default_role(mut self, role: Role) -> Self {
+        self.default_role = role;
+        self
+    }
+
+    pub fn reply_to_all(mut self, reply_to_all: bool) -> Self {
+        self.reply_to_all = reply_to_all;
+        self
+    }
+
+    pub fn role_of(&self, ucid: &str) -> Role {
+        self.roles.get(ucid).copied().unwrap_or(self.default_role)
+    }
+
+    /// Splits a chat message into a command name and its arguments.
+    /// Double quotes group words into a single argument.
+    pub fn parse(&self, message: &str) -> Option<(String, Vec<String>)> {
+        let rest = message.trim().strip_prefix(self.prefix.as_str())?;
+        let mut tokens = tokenize(rest).into_iter();
+        let name = tokens.next()?;
+
+        Some((name, tokens.collect()))
+    }
+
+    pub async fn poll(&mut self, client: &Client, id: &Uuid) -> Result<usize> {
+        let chat = client.get_chat(id).await?;
+        let (resume_id, unseen) = self.unseen(id, chat);
+
+        let pending: Vec<_> = unseen
+            .into_iter()
+            .filter_map(|message| {
+                let (name, args) = self.parse(&message.message)?;
+                let index = self.commands.iter().position(|c| c.matches(&name))?;
+                Some((index, args, message))
+            })
+            .collect();
+
+        let runtime = if pending.is_empty() {
+            None
+        } else {
+            Some(client.get_runtime(id).await?)
+        };
+        // Advanced only now, so commands are retried when the runtime is unavailable.
+        self.last_message_ids.insert(*id, resume_id);
+
+        let Some(runtime) = runtime else {
+            return Ok(0);
+        };
+        let mut handled = 0;
+
+        for (index, args, message) in pending {
+            let Some(player) = runtime
+                .players
+                .players
+                .all
+                .values()
+                .find(|player| player.id == message.player_id)
+                .cloned()
+            else {
+                continue;
+            };
+
+            if let Some(reply) = self
+                .dispatch(client, id, index, args, player, message)
+                .await
+                && let Err(error) = client
+                    .send_chat(
+                        id,
+                        &SendChatRequest {
+                            all: self.reply_to_all,
+                            msg: reply,
+                        },
+                    )
+                    .await
+            {
+                log::warn!("chat bot reply failed for {id}: {error}");
+            }
+
+            handled += 1;
+        }
+
+        Ok(handled)
+    }
+
+    /// Messages after the last one seen and the id to resume after. The
+    /// first poll only skips the backlog so old commands are not replayed.
+    /// Ids start over when the server restarts, so a chat that ends before
+    /// the last message seen is read from the start.
+    fn unseen(&mut self, id: &Uuid, chat: Vec<DcsChat>) -> (i32, Vec<DcsChat>) {
+        let newest = chat.iter().map(|message| message.id).max().unwrap_or(0);
+
+        let last_id = match self.last_message_ids.get(id).copied() {
+            None => {
+                self.last_message_ids.insert(*id, newest);
+                return (newest, Vec::new());
+            }
+            Some(last_id) if !chat.is_empty() && newest < last_id => 0,
+            Some(last_id) => last_id,
+        };
+
+        let unseen = chat
+            .into_iter()
+            .filter(|message| message.id > last_id && !message.is_historical)
+            .collect();
+        (newest.max(last_id), unseen)
+    }
+
+    pub async fn run(&mut self, client: &Client, ids: &[Uuid], interval: Duration) -> Result<()> {
+        poll_every!(interval, "chat bot poll", {
+            for id in ids {
+                if let Err(error) = self.poll(client, id).await {
+                    log::warn!("chat bot poll failed for {id}: {error}");
+                }
+            }
+            Ok(())
+        })
+    }
+
+    async fn dispatch(
+        &mut self,
+        client: &Client,
+        id: &Uuid,
+        index: usize,
+        args: Vec<String>,
+        player: Player,
+        message: DcsChat,
+    ) -> Option<String> {
+        let command = self.commands[index].clone();
+        let role = self.role_of(&player.ucid);
+        let display = format!("{}{}", self.prefix, command.name);
+
+        if role < command.role {
+            return Some(format!(
+                "{}: you are not allowed to use {display}",
+                player.name
+            ));
+        }
+
+        if args.len() < command.min_args || command.max_args.is_some_and(|max| args.len() > max) {
+            let usage = command.usage.as_deref().unwrap_or_default();
+            return Some(format!("usage: {display} {usage}").trim_end().to_string());
+        }
+
+        let key = (command.name.clone(), player.ucid.clone());
+        if let Some(last_used) = self.cooldowns.get(&key) {
+            let elapsed = last_used.elapsed();
+            if elapsed < command.cooldown {
+                let remaining = (command.cooldown - elapsed).as_secs() + 1;
+                return Some(format!("{display} is on cooldown for {remaining}s"));
+            }
+        }
+
+        if !command.cooldown.is_zero() {
+            self.cooldowns.insert(key, Instant::now());
+        }
+
+        let context = CommandContext {
+            instance_id: *id,
+            command: command.name.clone(),
+            args,
+            player,
+            role,
+            message,
+        };
+
+        match (command.handler)(client.clone(), context).await {
+            Ok(reply) => reply,
+            Err(error) => Some(format!("{display} failed: {error}")),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut has_token = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                has_token = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if has_token {
+                    tokens.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_token = true;
+            }
+        }
+    }
+
+    if has_token {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{chat, player};
+
+    fn echo() -> Command {
+        Command::new("echo", |_, context: CommandContext| async move {
+            Ok(Some(context.args.join(" ")))
+        })
+    }
+
+    async fn run(bot: &mut ChatBot, player_name: &str, text: &str) -> Option<String> {
+        let (name, args) = bot.parse(text)?;
+        let index = bot.commands.iter().position(|c| c.matches(&name))?;
+        bot.dispatch(
+            &Client::new("key"),
+            &Uuid::nil(),
+            index,
+            args,
+            player(2, player_name),
+            chat(1, 2, text),
+        )
+        .await
+    }
+
+    #[test]
+    fn parse_groups_quoted_arguments() {
+        let bot = ChatBot::new("!");
+
+        assert_eq!(
+            bot.parse(r#"  !kick "Big Bird" teamkilling "#),
+            Some((
+                "kick".to_string(),
+                vec!["Big Bird".to_string(), "teamkilling".to_string()]
+            ))
+        );
+        assert_eq!(
+            bot.parse(r#"!say """#),
+            Some(("say".to_string(), vec![String::new()]))
+        );
+        assert_eq!(bot.parse("kick someone"), None);
+        assert_eq!(bot.parse("!"), None);
+    }
+
+    #[test]
+    fn roles_fall_back_to_the_default() {
+        let bot = ChatBot::new("!")
+            .grant("ucid-Admin", Role::Admin)
+            .default_role(Role::Moderator);
+
+        assert_eq!(bot.role_of("ucid-Admin"), Role::Admin);
+        assert_eq!(bot.role_of("someone"), Role::Moderator);
+    }
+
+    #[tokio::test]
+    async fn dispatch_runs_matching_commands_by_alias() {
+        let mut bot = ChatBot::new("!").command(echo().alias("say"));
+
+        assert_eq!(
+            run(&mut bot, "Viper", "!SAY hello world").await,
+            Some("hello world".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn dispatch_checks_roles_and_arguments() {
+        let mut bot = ChatBot::new("!")
+            .command(echo().role(Role::Admin))
+            .grant("ucid-Admin", Role::Admin);
+
+        assert_eq!(
+            run(&mut bot, "Viper", "!echo hi").await,
+            Some("Viper: you are not allowed to use !echo".to_string())
+        );
+        assert_eq!(
+            run(&mut bot, "Admin", "!echo hi").await,
+            Some("hi".to_string())
+        );
+
+        let mut bot = ChatBot::new("!").command(echo().args(1, Some(1)).usage("<text>"));
+        assert_eq!(
+            run(&mut bot, "Viper", "!echo").await,
+            Some("usage: !echo <text>".to_string())
+        );
+        assert_eq!(
+            run(&mut bot, "Viper", "!echo a b").await,
+            Some("usage: !echo <text>".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn dispatch_enforces_cooldowns_per_player() {
+        let mut bot = ChatBot::new("!").command(echo().cooldown(Duration::from_secs(60)));
+
+        assert_eq!(run(&mut bot, "a", "!echo hi").await, Some("hi".to_string()));
+        assert_eq!(
+            run(&mut bot, "a", "!echo hi").await,
+            Some("!echo is on cooldown for 60s".to_string())
+        );
+        assert_eq!(run(&mut bot, "b", "!echo hi").await, Some("hi".to_string()));
+    }
+
+    #[tokio::test]
+    async fn dispatch_reports_handler_errors() {
+        let mut bot =
+            ChatBot::new("!").command(Command::new("fail", |_, _| async { anyhow::bail!("boom") }));
+
+        assert_eq!(
+            run(&mut bot, "Viper", "!fail").await,
+            Some("!fail failed: boom".to_string())
+        );
+    }
+
+    #[test]
+    fn unseen_skips_the_backlog_and_follows_restarts() {
+        let mut bot = ChatBot::new("!");
+        let id = Uuid::nil();
+        let ids = |(_, unseen): (i32, Vec<DcsChat>)| -> Vec<i32> {
+            unseen.iter().map(|message| message.id).collect()
+        };
+
+        let (resume_id, unseen) = bot.unseen(&id, vec![chat(7, 2, "!old"), chat(8, 2, "!older")]);
+        assert_eq!((resume_id, unseen.len()), (8, 0));
+
+        let mut historical = chat(10, 2, "!replayed");
+        historical.is_historical = true;
+        let new = bot.unseen(
+            &id,
+            vec![chat(8, 2, "!older"), chat(9, 2, "!new"), historical],
+        );
+        assert_eq!(new.0, 10);
+        assert_eq!(ids(new), [9]);
+        bot.last_message_ids.insert(id, 10);
+
+        // An empty chat says nothing about a restart.
+        assert_eq!(bot.unseen(&id, Vec::new()), (10, Vec::new()));
+
+        // After a restart the ids start over below the last one seen.
+        let restarted = bot.unseen(&id, vec![chat(1, 2, "!hi"), chat(2, 2, "!there")]);
+        assert_eq!(restarted.0, 2);
+        assert_eq!(ids(restarted), [1, 2]);
+    }
+}
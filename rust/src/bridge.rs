@@ -0,0 +1,595 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use uuid::Uuid;
+
+use crate::http;
+use crate::{
+    BoxFuture, Client, Coalition, DcsChat, Players, SIGNATURE_HEADER, SendChatRequest,
+    TIMESTAMP_HEADER, poll_every, unix_now, verify_webhook,
+};
+
+/// How far the timestamp of a signed message may be off, so captured
+/// requests can't be replayed later.
+const MAX_CLOCK_SKEW: i64 = 300;
+/// How long texts relayed out are remembered to recognise them coming back.
+const ECHO_WINDOW: Duration = Duration::from_secs(300);
+
+/// A chat message on the external side of a [`ChatBridge`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BridgeMessage {
+    pub author: String,
+    pub content: String,
+}
+
+pub trait ChatTransport: Send + Sync {
+    /// Posts a message from the game to the external channel.
+    fn send<'a>(&'a self, message: &'a BridgeMessage) -> BoxFuture<'a, Result<()>>;
+
+    /// Messages posted to the external channel since the last call.
+    fn receive(&self) -> BoxFuture<'_, Result<Vec<BridgeMessage>>>;
+}
+
+type Inbox = Arc<Mutex<VecDeque<BridgeMessage>>>;
+
+fn drain(inbox: &Inbox) -> Result<Vec<BridgeMessage>> {
+    Ok(inbox
+        .lock()
+        .map_err(|_| anyhow!("bridge inbox lock poisoned"))?
+        .drain(..)
+        .collect())
+}
+
+/// Posts outgoing messages to a Discord-compatible webhook as
+/// `{"username", "content"}` and accepts incoming messages as
+/// [`BridgeMessage`] JSON POSTed to [`WebhookTransport::serve`].
+#[derive(Debug, Clone)]
+pub struct WebhookTransport {
+    url: String,
+    secret: Option<String>,
+    http: reqwest::Client,
+    inbox: Inbox,
+}
+
+impl WebhookTransport {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            secret: None,
+            http: reqwest::Client::new(),
+            inbox: Arc::default(),
+        }
+    }
+
+    /// Requires incoming messages to be signed with
+    /// [`sign_webhook`](crate::sign_webhook) within five minutes of the
+    /// local clock.
+    pub fn secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Accepts incoming messages until the listener fails. Runs alongside the
+    /// bridge, e.g. in a spawned task on a clone of the transport.
+    pub async fn serve(self, address: impl ToSocketAddrs) -> Result<()> {
+        let listener = TcpListener::bind(address).await?;
+
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let transport = self.clone();
+            tokio::spawn(async move {
+                if let Err(error) = transport.accept(stream).await {
+                    log::debug!("bridge request failed: {error}");
+                }
+            });
+        }
+    }
+
+    async fn accept(&self, mut stream: TcpStream) -> Result<()> {
+        let status = match http::read_request(&mut stream).await {
+            Ok(request) if request.method != "POST" => "405 Method Not Allowed",
+            Ok(request) => match self.inbound(&request.headers, &request.body) {
+                Ok(message) => {
+                    self.inbox
+                        .lock()
+                        .map_err(|_| anyhow!("bridge inbox lock poisoned"))?
+                        .push_back(message);
+                    "204 No Content"
+                }
+                Err(error) => {
+                    log::debug!("rejected bridge message: {error}");
+                    "400 Bad Request"
+                }
+            },
+            Err(error) => {
+                log::debug!("malformed bridge request: {error}");
+                "400 Bad Request"
+            }
+        };
+
+        http::respond(&mut stream, status, None, "").await
+    }
+
+    fn inbound(&self, headers: &HashMap<String, String>, body: &[u8]) -> Result<BridgeMessage> {
+        if let Some(secret) = &self.secret {
+            let header = |name: &str| headers.get(&name.to_ascii_lowercase());
+            let (Some(timestamp), Some(signature)) =
+                (header(TIMESTAMP_HEADER), header(SIGNATURE_HEADER))
+            else {
+                bail!("missing signature");
+            };
+            let timestamp: i64 = timestamp.parse()?;
+            if (unix_now() - timestamp).abs() > MAX_CLOCK_SKEW {
+                bail!("stale signature");
+            }
+            if !verify_webhook(secret, timestamp, body, signature) {
+                bail!("invalid signature");
+            }
+        }
+
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
+impl ChatTransport for WebhookTransport {
+    fn send<'a>(&'a self, message: &'a BridgeMessage) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let response = self
+                .http
+                .post(&self.url)
+                .json(&serde_json::json!({
+                    "username": message.author,
+                    "content": message.content,
+                }))
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                bail!("webhook responded with {}", response.status());
+            }
+            Ok(())
+        })
+    }
+
+    fn receive(&self) -> BoxFuture<'_, Result<Vec<BridgeMessage>>> {
+        Box::pin(async move { drain(&self.inbox) })
+    }
+}
+
+/// Keeps messages in memory. Clones share the same channel, so one clone can
+/// be handed to the bridge while another inspects and injects messages.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryTransport {
+    sent: Arc<Mutex<Vec<BridgeMessage>>>,
+    inbox: Inbox,
+}
+
+impl InMemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a message as if it was posted to the external channel.
+    pub fn push(&self, message: BridgeMessage) {
+        if let Ok(mut inbox) = self.inbox.lock() {
+            inbox.push_back(message);
+        }
+    }
+
+    /// Messages the bridge sent to the external channel so far.
+    pub fn sent(&self) -> Vec<BridgeMessage> {
+        self.sent
+            .lock()
+            .map(|sent| sent.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl ChatTransport for InMemoryTransport {
+    fn send<'a>(&'a self, message: &'a BridgeMessage) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            self.sent
+                .lock()
+                .map_err(|_| anyhow!("bridge outbox lock poisoned"))?
+                .push(message.clone());
+            Ok(())
+        })
+    }
+
+    fn receive(&self) -> BoxFuture<'_, Result<Vec<BridgeMessage>>> {
+        Box::pin(async move { drain(&self.inbox) })
+    }
+}
+
+/// At most `max` messages per `per`, over a sliding window.
+#[derive(Debug, Clone)]
+struct RateLimit {
+    max: usize,
+    per: Duration,
+    sent: VecDeque<Instant>,
+}
+
+impl RateLimit {
+    fn new(max: usize, per: Duration) -> Self {
+        Self {
+            max,
+            per,
+            sent: VecDeque::new(),
+        }
+    }
+
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= self.per)
+        {
+            self.sent.pop_front();
+        }
+
+        if self.sent.len() >= self.max {
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BridgeActivity {
+    pub outbound: usize,
+    pub inbound: usize,
+    /// Messages dropped by the rate limits.
+    pub dropped: usize,
+}
+
+/// Relays the chat of one server to a [`ChatTransport`] and back.
+///
+/// Messages sent by the server itself, including the ones the bridge injects,
+/// are never relayed out, and texts the bridge relayed out in the last five
+/// minutes are ignored when they come back from the other side.
+pub struct ChatBridge {
+    instance_id: Uuid,
+    transport: Box<dyn ChatTransport>,
+    outbound_format: String,
+    inbound_format: String,
+    outbound_limit: RateLimit,
+    inbound_limit: RateLimit,
+    last_chat_id: Option<i32>,
+    relayed: VecDeque<(Instant, String)>,
+}
+
+impl ChatBridge {
+    pub fn new(instance_id: Uuid, transport: impl ChatTransport + 'static) -> Self {
+        Self {
+            instance_id,
+            transport: Box::new(transport),
+            outbound_format: "{message}".to_string(),
+            inbound_format: "[{author}] {message}".to_string(),
+            outbound_limit: RateLimit::new(30, Duration::from_secs(60)),
+            inbound_limit: RateLimit::new(10, Duration::from_secs(60)),
+            last_chat_id: None,
+            relayed: VecDeque::new(),
+        }
+    }
+
+    /// Text of messages relayed out; the player name becomes the author.
+    /// `{name}`, `{side}` (red, blue or spectator) and `{message}` are
+    /// replaced. Defaults to `{message}`.
+    pub fn outbound_format(mut self, format: impl Into<String>) -> Self {
+        self.outbound_format = format.into();
+        self
+    }
+
+    /// Chat text of messages relayed in. `{author}` and `{message}` are
+    /// replaced. Defaults to `[{author}] {message}`.
+    pub fn inbound_format(mut self, format: impl Into<String>) -> Self {
+        self.inbound_format = format.into();
+        self
+    }
+
+    /// Messages relayed out per `per`, 30 per minute by default.
+    pub fn outbound_limit(mut self, max: usize, per: Duration) -> Self {
+        self.outbound_limit = RateLimit::new(max, per);
+        self
+    }
+
+    /// Messages relayed into the game per `per`, 10 per minute by default.
+    pub fn inbound_limit(mut self, max: usize, per: Duration) -> Self {
+        self.inbound_limit = RateLimit::new(max, per);
+        self
+    }
+
+    pub async fn poll(&mut self, client: &Client) -> Result<BridgeActivity> {
+        let now = Instant::now();
+        let mut activity = BridgeActivity::default();
+
+        let chat = client.get_chat(&self.instance_id).await?;
+        let outgoing = self.outgoing(chat);
+        if !outgoing.is_empty() {
+            // The chat is already consumed, so relay with what the messages
+            // carry rather than losing them when the runtime is unavailable.
+            let runtime = client
+                .get_runtime(&self.instance_id)
+                .await
+                .inspect_err(|error| {
+                    log::warn!("failed to read players of {}: {error}", self.instance_id)
+                })
+                .ok();
+            let players = runtime.as_ref().map(|runtime| &runtime.players.players);
+
+            for message in outgoing {
+                if !self.outbound_limit.try_acquire(now) {
+                    activity.dropped += 1;
+                    continue;
+                }
+
+                let bridged = self.format_outgoing(&message, players);
+                match self.transport.send(&bridged).await {
+                    Ok(()) => {
+                        self.relayed.push_back((now, bridged.content));
+                        activity.outbound += 1;
+                    }
+                    Err(error) => {
+                        log::warn!("failed to relay message from {}: {error}", self.instance_id)
+                    }
+                }
+            }
+        }
+
+        let received = self.transport.receive().await?;
+        for msg in self.incoming(received, now, &mut activity) {
+            match client
+                .send_chat(&self.instance_id, &SendChatRequest { all: true, msg })
+                .await
+            {
+                Ok(_) => activity.inbound += 1,
+                Err(error) => {
+                    log::warn!("failed to relay message into {}: {error}", self.instance_id)
+                }
+            }
+        }
+
+        Ok(activity)
+    }
+
+    /// New player messages. The first call only establishes where to resume
+    /// from, so the backlog is not replayed.
+    fn outgoing(&mut self, chat: Vec<DcsChat>) -> Vec<DcsChat> {
+        let newest = chat.iter().map(|message| message.id).max().unwrap_or(0);
+        let last_id = self
+            .last_chat_id
+            .replace(newest.max(self.last_chat_id.unwrap_or(newest)));
+        let Some(last_id) = last_id else {
+            return Vec::new();
+        };
+
+        chat.into_iter()
+            .filter(|message| {
+                message.id > last_id
+                    && !message.is_historical
+                    && message.player_id != Players::SERVER_PLAYER_ID
+            })
+            .collect()
+    }
+
+    /// Chat texts to relay into the game. Echoes of relayed messages are
+    /// skipped and messages over the rate limit are counted as dropped.
+    fn incoming(
+        &mut self,
+        received: Vec<BridgeMessage>,
+        now: Instant,
+        activity: &mut BridgeActivity,
+    ) -> Vec<String> {
+        self.relayed
+            .retain(|(relayed_at, _)| now.duration_since(*relayed_at) < ECHO_WINDOW);
+
+        let mut texts = Vec::new();
+        for message in received {
+            if self.is_echo(&message.content) {
+                continue;
+            }
+            if !self.inbound_limit.try_acquire(now) {
+                activity.dropped += 1;
+                continue;
+            }
+            texts.push(self.format_incoming(&message));
+        }
+        texts
+    }
+
+    /// Falls back to the name in the message and `spectator` for players
+    /// who already left or when `players` is unknown.
+    fn format_outgoing(&self, message: &DcsChat, players: Option<&Players>) -> BridgeMessage {
+        let player = players.and_then(|players| {
+            players
+                .all
+                .values()
+                .find(|player| player.id == message.player_id)
+        });
+        let side = player
+            .and_then(|player| Coalition::from_side(player.side))
+            .map(|coalition| coalition.to_string())
+            .unwrap_or_else(|| "spectator".to_string());
+        let name = player
+            .map(|player| player.name.as_str())
+            .unwrap_or(&message.player_name);
+
+        BridgeMessage {
+            author: name.to_string(),
+            content: self
+                .outbound_format
+                .replace("{name}", name)
+                .replace("{side}", &side)
+                .replace("{message}", &message.message),
+        }
+    }
+
+    fn format_incoming(&self, message: &BridgeMessage) -> String {
+        self.inbound_format
+            .replace("{author}", &message.author)
+            .replace("{message}", &message.content)
+    }
+
+    fn is_echo(&self, text: &str) -> bool {
+        self.relayed.iter().any(|(_, relayed)| relayed == text)
+    }
+
+    pub async fn run(&mut self, client: &Client, interval: Duration) -> Result<()> {
+        let what = format!("chat bridge of {}", self.instance_id);
+        poll_every!(interval, what, self.poll(client).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign_webhook;
+    use crate::test_support::{chat, player, runtime};
+
+    fn bridge() -> ChatBridge {
+        ChatBridge::new(Uuid::nil(), InMemoryTransport::new())
+    }
+
+    fn message(author: &str, content: &str) -> BridgeMessage {
+        BridgeMessage {
+            author: author.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    fn signed(secret: &str, timestamp: i64, body: &[u8]) -> HashMap<String, String> {
+        HashMap::from([
+            (TIMESTAMP_HEADER.to_ascii_lowercase(), timestamp.to_string()),
+            (
+                SIGNATURE_HEADER.to_ascii_lowercase(),
+                sign_webhook(secret, timestamp, body),
+            ),
+        ])
+    }
+
+    #[test]
+    fn rate_limit_slides() {
+        let start = Instant::now();
+        let mut limit = RateLimit::new(2, Duration::from_secs(60));
+
+        assert!(limit.try_acquire(start));
+        assert!(limit.try_acquire(start + Duration::from_secs(30)));
+        assert!(!limit.try_acquire(start + Duration::from_secs(59)));
+        // Only the first message left the window.
+        assert!(limit.try_acquire(start + Duration::from_secs(60)));
+        assert!(!limit.try_acquire(start + Duration::from_secs(89)));
+        assert!(limit.try_acquire(start + Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn drops_messages_over_the_inbound_limit() {
+        let mut bridge = bridge().inbound_limit(2, Duration::from_secs(60));
+        let mut activity = BridgeActivity::default();
+        let received = vec![message("a", "1"), message("b", "2"), message("c", "3")];
+
+        let texts = bridge.incoming(received, Instant::now(), &mut activity);
+        assert_eq!(texts, ["[a] 1", "[b] 2"]);
+        assert_eq!(activity.dropped, 1);
+    }
+
+    #[test]
+    fn ignores_echoes_of_relayed_messages() {
+        let mut bridge = bridge().inbound_limit(1, Duration::from_secs(60));
+        let now = Instant::now();
+        bridge.relayed.push_back((now, "hello".to_string()));
+        let mut activity = BridgeActivity::default();
+
+        // Echoes neither come back in nor use up the rate limit.
+        let received = vec![message("Pilot", "hello"), message("Pilot", "hi")];
+        let texts = bridge.incoming(received, now, &mut activity);
+        assert_eq!(texts, ["[Pilot] hi"]);
+        assert_eq!(activity.dropped, 0);
+
+        let later = now + ECHO_WINDOW;
+        let texts = bridge.incoming(vec![message("Pilot", "hello")], later, &mut activity);
+        assert_eq!(texts, ["[Pilot] hello"]);
+        assert!(bridge.relayed.is_empty());
+    }
+
+    #[test]
+    fn skips_the_backlog_and_server_messages() {
+        let mut bridge = bridge();
+        assert!(
+            bridge
+                .outgoing(vec![chat(1, 2, "old"), chat(2, 2, "older")])
+                .is_empty()
+        );
+
+        let mut historical = chat(5, 2, "replayed");
+        historical.is_historical = true;
+        let outgoing = bridge.outgoing(vec![
+            chat(2, 2, "older"),
+            chat(3, Players::SERVER_PLAYER_ID, "[Discord] hi"),
+            chat(4, 2, "new"),
+            historical,
+        ]);
+        let ids: Vec<_> = outgoing.iter().map(|message| message.id).collect();
+        assert_eq!(ids, [4]);
+
+        // An empty chat doesn't rewind.
+        assert!(bridge.outgoing(Vec::new()).is_empty());
+        assert!(bridge.outgoing(vec![chat(5, 2, "again")]).is_empty());
+    }
+
+    #[test]
+    fn formats_names_and_sides() {
+        let bridge = bridge().outbound_format("{name} ({side}): {message}");
+        let runtime = runtime([player(2, "Viper")]);
+        let players = &runtime.players.players;
+
+        let bridged = bridge.format_outgoing(&chat(1, 2, "fox two"), Some(players));
+        let side = Coalition::from_side(2).unwrap();
+        assert_eq!(bridged.author, "Viper");
+        assert_eq!(bridged.content, format!("Viper ({side}): fox two"));
+
+        // Players who left, or an unknown roster, fall back to the message.
+        let mut left = chat(2, 3, "o7");
+        left.player_name = "Eagle".to_string();
+        for players in [Some(players), None] {
+            let bridged = bridge.format_outgoing(&left, players);
+            assert_eq!(bridged.author, "Eagle");
+            assert_eq!(bridged.content, "Eagle (spectator): o7");
+        }
+
+        assert_eq!(bridge.format_incoming(&message("Ops", "RTB")), "[Ops] RTB");
+    }
+
+    #[test]
+    fn verifies_signed_messages() {
+        let transport = WebhookTransport::new("http://localhost").secret("secret");
+        let body = serde_json::to_vec(&message("Ops", "RTB")).unwrap();
+
+        let accepted = transport
+            .inbound(&signed("secret", unix_now(), &body), &body)
+            .unwrap();
+        assert_eq!(accepted, message("Ops", "RTB"));
+
+        assert!(transport.inbound(&HashMap::new(), &body).is_err());
+        assert!(
+            transport
+                .inbound(&signed("other", unix_now(), &body), &body)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_replayed_messages() {
+        let transport = WebhookTransport::new("http://localhost").secret("secret");
+        let body = serde_json::to_vec(&message("Ops", "RTB")).unwrap();
+
+        for skew in [-MAX_CLOCK_SKEW - 60, MAX_CLOCK_SKEW + 60] {
+            let headers = signed("secret", unix_now() + skew, &body);
+            let error = transport.inbound(&headers, &body).unwrap_err();
+            assert!(error.to_string().contains("stale"));
+        }
+    }
+}
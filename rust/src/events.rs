@@ -0,0 +1,797 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use crate::{
+    BoxFuture, Client, DcsChat, GameRuntime, InstanceResource, InstanceStatus, InstanceStatusKind,
+    Player, SrsClient, SrsServerInfo, Trigger, poll_every, unix_now,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    StatusChanged {
+        instance_id: Uuid,
+        from: InstanceStatusKind,
+        to: InstanceStatus,
+    },
+    PlayerJoined {
+        instance_id: Uuid,
+        player: Player,
+    },
+    PlayerLeft {
+        instance_id: Uuid,
+        player: Player,
+    },
+    ChatMessage {
+        instance_id: Uuid,
+        message: DcsChat,
+    },
+    MissionChanged {
+        instance_id: Uuid,
+        from: String,
+        to: String,
+    },
+    Paused {
+        instance_id: Uuid,
+    },
+    Resumed {
+        instance_id: Uuid,
+    },
+    SrsConnected {
+        instance_id: Uuid,
+        client: SrsClient,
+    },
+    SrsDisconnected {
+        instance_id: Uuid,
+        client: SrsClient,
+    },
+    TriggerExecuted {
+        instance_id: Uuid,
+        trigger: Trigger,
+    },
+    /// Kicked through a client set up with [`Client::with_events`].
+    PlayerKicked {
+        instance_id: Uuid,
+        player_id: i32,
+        reason: String,
+    },
+    /// Banned through a client set up with [`Client::with_events`].
+    PlayerBanned {
+        instance_id: Uuid,
+        player_id: i32,
+        ucid: String,
+        reason: String,
+        period: i64,
+    },
+    /// Started through a client set up with [`Client::with_events`].
+    MissionStarted {
+        instance_id: Uuid,
+        mission_idx: i32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    StatusChanged,
+    PlayerJoined,
+    PlayerLeft,
+    ChatMessage,
+    MissionChanged,
+    Paused,
+    Resumed,
+    SrsConnected,
+    SrsDisconnected,
+    TriggerExecuted,
+    PlayerKicked,
+    PlayerBanned,
+    MissionStarted,
+}
+
+impl Event {
+    pub fn instance_id(&self) -> Uuid {
+        match self {
+            Event::StatusChanged { instance_id, .. }
+            | Event::PlayerJoined { instance_id, .. }
+            | Event::PlayerLeft { instance_id, .. }
+            | Event::ChatMessage { instance_id, .. }
+            | Event::MissionChanged { instance_id, .. }
+            | Event::Paused { instance_id }
+            | Event::Resumed { instance_id }
+            | Event::SrsConnected { instance_id, .. }
+            | Event::SrsDisconnected { instance_id, .. }
+            | Event::TriggerExecuted { instance_id, .. }
+            | Event::PlayerKicked { instance_id, .. }
+            | Event::PlayerBanned { instance_id, .. }
+            | Event::MissionStarted { instance_id, .. } => *instance_id,
+        }
+    }
+
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::StatusChanged { .. } => EventKind::StatusChanged,
+            Event::PlayerJoined { .. } => EventKind::PlayerJoined,
+            Event::PlayerLeft { .. } => EventKind::PlayerLeft,
+            Event::ChatMessage { .. } => EventKind::ChatMessage,
+            Event::MissionChanged { .. } => EventKind::MissionChanged,
+            Event::Paused { .. } => EventKind::Paused,
+            Event::Resumed { .. } => EventKind::Resumed,
+            Event::SrsConnected { .. } => EventKind::SrsConnected,
+            Event::SrsDisconnected { .. } => EventKind::SrsDisconnected,
+            Event::TriggerExecuted { .. } => EventKind::TriggerExecuted,
+            Event::PlayerKicked { .. } => EventKind::PlayerKicked,
+            Event::PlayerBanned { .. } => EventKind::PlayerBanned,
+            Event::MissionStarted { .. } => EventKind::MissionStarted,
+        }
+    }
+}
+
+/// An event with the Unix time it was observed, as written by the JSON lines,
+/// stdout and webhook sinks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimedEvent {
+    pub time: i64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl TimedEvent {
+    pub fn now(event: Event) -> Self {
+        Self {
+            time: unix_now(),
+            event,
+        }
+    }
+}
+
+/// Receives every event of an [`EventBus`], stamped once per poll. Use a
+/// [`WebhookDispatcher`](crate::WebhookDispatcher) to post events to webhooks.
+pub trait EventSink: Send + Sync {
+    fn publish<'a>(&'a self, event: &'a TimedEvent) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Appends one JSON object per event to a file.
+#[derive(Debug)]
+pub struct JsonLinesSink {
+    file: Mutex<File>,
+}
+
+impl JsonLinesSink {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl EventSink for JsonLinesSink {
+    fn publish<'a>(&'a self, event: &'a TimedEvent) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut line = serde_json::to_string(event)?;
+            line.push('\n');
+
+            let mut file = self
+                .file
+                .lock()
+                .map_err(|_| anyhow!("event log lock poisoned"))?;
+            file.write_all(line.as_bytes())?;
+            Ok(())
+        })
+    }
+}
+
+/// Prints events to stdout as JSON lines.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutSink;
+
+impl EventSink for StdoutSink {
+    fn publish<'a>(&'a self, event: &'a TimedEvent) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            println!("{}", serde_json::to_string(event)?);
+            Ok(())
+        })
+    }
+}
+
+/// Forwards events into a tokio channel. The bus waits while the channel is
+/// full, so keep the receiver drained.
+#[derive(Debug, Clone)]
+pub struct ChannelSink {
+    sender: mpsc::Sender<Event>,
+}
+
+impl ChannelSink {
+    pub fn new(buffer: usize) -> (Self, mpsc::Receiver<Event>) {
+        let (sender, receiver) = mpsc::channel(buffer);
+        (Self { sender }, receiver)
+    }
+}
+
+impl From<mpsc::Sender<Event>> for ChannelSink {
+    fn from(sender: mpsc::Sender<Event>) -> Self {
+        Self { sender }
+    }
+}
+
+impl EventSink for ChannelSink {
+    fn publish<'a>(&'a self, event: &'a TimedEvent) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            self.sender
+                .send(event.event.clone())
+                .await
+                .map_err(|_| anyhow!("event receiver dropped"))
+        })
+    }
+}
+
+/// What the bus last saw of an instance. `None` fields have no baseline yet,
+/// so the next observation is recorded without emitting events.
+#[derive(Debug, Default)]
+struct InstanceState {
+    status: Option<InstanceStatusKind>,
+    players: Option<HashMap<String, Player>>,
+    paused: Option<bool>,
+    mission: Option<String>,
+    last_chat_id: Option<i32>,
+    srs: Option<HashMap<String, SrsClient>>,
+    triggers: Option<HashMap<Uuid, Option<String>>>,
+}
+
+#[derive(Debug, Default)]
+struct Extras {
+    chat: Option<Vec<DcsChat>>,
+    srs: Option<SrsServerInfo>,
+    triggers: Option<Vec<Trigger>>,
+}
+
+/// Polls every instance once per interval and turns status, player, chat,
+/// mission, pause, SRS and trigger changes into [`Event`]s for its sinks.
+/// The first poll of an instance only records a baseline.
+pub struct EventBus {
+    sinks: Vec<Arc<dyn EventSink>>,
+    servers: Option<Vec<Uuid>>,
+    chat: bool,
+    srs: bool,
+    triggers: bool,
+    actions: Option<mpsc::UnboundedReceiver<Event>>,
+    state: HashMap<Uuid, InstanceState>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            sinks: Vec::new(),
+            servers: None,
+            chat: true,
+            srs: true,
+            triggers: true,
+            actions: None,
+            state: HashMap::new(),
+        }
+    }
+
+    pub fn sink(mut self, sink: impl EventSink + 'static) -> Self {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// Limits the bus to `ids` instead of every server on the account.
+    pub fn servers(mut self, ids: impl IntoIterator<Item = Uuid>) -> Self {
+        self.servers = Some(ids.into_iter().collect());
+        self
+    }
+
+    /// Polls `get_chat` of running servers. On by default.
+    pub fn chat(mut self, enabled: bool) -> Self {
+        self.chat = enabled;
+        self
+    }
+
+    /// Polls SRS clients of running servers. On by default.
+    pub fn srs(mut self, enabled: bool) -> Self {
+        self.srs = enabled;
+        self
+    }
+
+    /// Polls triggers for executions. On by default.
+    pub fn triggers(mut self, enabled: bool) -> Self {
+        self.triggers = enabled;
+        self
+    }
+
+    /// Also publishes the actions reported by clients set up with
+    /// [`Client::with_events`] on every poll.
+    pub fn actions(mut self, receiver: mpsc::UnboundedReceiver<Event>) -> Self {
+        self.actions = Some(receiver);
+        self
+    }
+
+    pub async fn poll(&mut self, client: &Client) -> Result<Vec<Event>> {
+        let servers = client.get_servers().await?;
+        let servers: Vec<_> = servers
+            .into_iter()
+            .filter(|server| {
+                self.servers
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&server.instance.id))
+            })
+            .collect();
+
+        // The instance list carries status and runtime; everything else is one
+        // request per instance, fetched concurrently.
+        let mut tasks = JoinSet::new();
+        for server in &servers {
+            let status = server.instance.status.kind();
+            let started = status == InstanceStatusKind::ServerStarted;
+            let (chat, srs) = (self.chat && started, self.srs && started);
+            let triggers = self.triggers && status != InstanceStatusKind::ServerDeleted;
+            if !chat && !srs && !triggers {
+                continue;
+            }
+
+            let client = client.clone();
+            let id = server.instance.id;
+            tasks.spawn(async move {
+                let (chat, srs, triggers) = tokio::join!(
+                    fetch(chat, log::Level::Warn, "chat", &id, client.get_chat(&id)),
+                    // Fails on servers without the SRS mod.
+                    fetch(
+                        srs,
+                        log::Level::Debug,
+                        "SRS clients",
+                        &id,
+                        client.get_srs_clients(&id)
+                    ),
+                    fetch(
+                        triggers,
+                        log::Level::Warn,
+                        "triggers",
+                        &id,
+                        client.list_triggers(&id)
+                    ),
+                );
+                let extras = Extras {
+                    chat,
+                    srs,
+                    triggers,
+                };
+                (id, extras)
+            });
+        }
+
+        let mut extras: HashMap<Uuid, Extras> = tasks.join_all().await.into_iter().collect();
+        let mut events = Vec::new();
+
+        if let Some(actions) = &mut self.actions {
+            while let Ok(event) = actions.try_recv() {
+                events.push(event);
+            }
+        }
+
+        for server in &servers {
+            events.extend(self.observe(server));
+            if let Some(extras) = extras.remove(&server.instance.id) {
+                events.extend(self.observe_extras(server, extras));
+            }
+        }
+
+        // Every sink sees the same time for an event.
+        let time = unix_now();
+        let mut published = Vec::with_capacity(events.len());
+        for event in events {
+            let event = TimedEvent { time, event };
+            self.dispatch(&event).await;
+            published.push(event.event);
+        }
+
+        Ok(published)
+    }
+
+    pub async fn dispatch(&self, event: &TimedEvent) {
+        for sink in &self.sinks {
+            if let Err(error) = sink.publish(event).await {
+                log::warn!("failed to publish {:?} event: {error}", event.event.kind());
+            }
+        }
+    }
+
+    fn observe(&mut self, server: &InstanceResource) -> Vec<Event> {
+        let instance_id = server.instance.id;
+        let state = self.state.entry(instance_id).or_default();
+        let mut events = Vec::new();
+
+        let status = server.instance.status.kind();
+        if let Some(previous) = state.status.replace(status)
+            && previous != status
+        {
+            events.push(Event::StatusChanged {
+                instance_id,
+                from: previous,
+                to: server.instance.status.clone(),
+            });
+        }
+
+        if status != InstanceStatusKind::ServerStarted {
+            // Players and voice clients drop with the server; mission, pause
+            // and chat ids start over with the next runtime.
+            if let Some(previous) = state.players.replace(HashMap::new()) {
+                player_events(instance_id, &previous, &HashMap::new(), &mut events);
+            }
+            if let Some(previous) = state.srs.replace(HashMap::new()) {
+                srs_events(instance_id, &previous, &HashMap::new(), &mut events);
+            }
+            state.paused = None;
+            state.mission = None;
+            state.last_chat_id = None;
+            return events;
+        }
+
+        // Started, but the runtime has not been reported yet.
+        let Some(GameRuntime::Dcs(runtime)) = &server.runtime else {
+            return events;
+        };
+
+        let players: HashMap<String, Player> = runtime
+            .players
+            .players
+            .clients()
+            .map(|player| (player.ucid.clone(), player.clone()))
+            .collect();
+        if let Some(previous) = state.players.replace(players.clone()) {
+            player_events(instance_id, &previous, &players, &mut events);
+        }
+
+        if let Some(paused) = state.paused.replace(runtime.paused)
+            && paused != runtime.paused
+        {
+            events.push(if runtime.paused {
+                Event::Paused { instance_id }
+            } else {
+                Event::Resumed { instance_id }
+            });
+        }
+
+        let mission = &runtime.mission_info.mission_filename;
+        if let Some(previous) = state.mission.replace(mission.clone())
+            && previous != *mission
+        {
+            events.push(Event::MissionChanged {
+                instance_id,
+                from: previous,
+                to: mission.clone(),
+            });
+        }
+
+        events
+    }
+
+    fn observe_extras(&mut self, server: &InstanceResource, extras: Extras) -> Vec<Event> {
+        let instance_id = server.instance.id;
+        let state = self.state.entry(instance_id).or_default();
+        let mut events = Vec::new();
+
+        if let Some(chat) = extras.chat {
+            let newest = chat.iter().map(|message| message.id).max().unwrap_or(0);
+            let last_id = state
+                .last_chat_id
+                .replace(newest.max(state.last_chat_id.unwrap_or(0)));
+
+            if let Some(last_id) = last_id {
+                events.extend(
+                    chat.into_iter()
+                        .filter(|message| message.id > last_id && !message.is_historical)
+                        .map(|message| Event::ChatMessage {
+                            instance_id,
+                            message,
+                        }),
+                );
+            }
+        }
+
+        if let Some(srs) = extras.srs {
+            let clients: HashMap<String, SrsClient> = srs
+                .clients
+                .into_iter()
+                .map(|client| (client.client_guid.clone(), client))
+                .collect();
+            if let Some(previous) = state.srs.replace(clients.clone()) {
+                srs_events(instance_id, &previous, &clients, &mut events);
+            }
+        }
+
+        if let Some(triggers) = extras.triggers {
+            let executions: HashMap<Uuid, Option<String>> = triggers
+                .iter()
+                .filter_map(|trigger| Some((trigger.id?, trigger.last_executed_at.clone())))
+                .collect();
+
+            if let Some(previous) = state.triggers.replace(executions) {
+                events.extend(
+                    triggers
+                        .into_iter()
+                        .filter(|trigger| {
+                            let Some(id) = trigger.id else {
+                                return false;
+                            };
+                            trigger.last_executed_at.is_some()
+                                && previous
+                                    .get(&id)
+                                    .is_some_and(|last| *last != trigger.last_executed_at)
+                        })
+                        .map(|trigger| Event::TriggerExecuted {
+                            instance_id,
+                            trigger,
+                        }),
+                );
+            }
+        }
+
+        events
+    }
+
+    pub async fn run(&mut self, client: &Client, interval: Duration) -> Result<()> {
+        poll_every!(interval, "event polling", self.poll(client).await)
+    }
+}
+
+async fn fetch<T>(
+    enabled: bool,
+    level: log::Level,
+    what: &str,
+    id: &Uuid,
+    request: impl Future<Output = Result<T>>,
+) -> Option<T> {
+    if !enabled {
+        return None;
+    }
+
+    request
+        .await
+        .inspect_err(|error| log::log!(level, "failed to read {what} of {id}: {error}"))
+        .ok()
+}
+
+fn player_events(
+    instance_id: Uuid,
+    previous: &HashMap<String, Player>,
+    current: &HashMap<String, Player>,
+    events: &mut Vec<Event>,
+) {
+    events.extend(added(previous, current).map(|player| Event::PlayerJoined {
+        instance_id,
+        player,
+    }));
+    events.extend(added(current, previous).map(|player| Event::PlayerLeft {
+        instance_id,
+        player,
+    }));
+}
+
+fn srs_events(
+    instance_id: Uuid,
+    previous: &HashMap<String, SrsClient>,
+    current: &HashMap<String, SrsClient>,
+    events: &mut Vec<Event>,
+) {
+    events.extend(added(previous, current).map(|client| Event::SrsConnected {
+        instance_id,
+        client,
+    }));
+    events.extend(
+        added(current, previous).map(|client| Event::SrsDisconnected {
+            instance_id,
+            client,
+        }),
+    );
+}
+
+/// Values of `current` whose key is missing from `previous`.
+fn added<'a, T: Clone>(
+    previous: &'a HashMap<String, T>,
+    current: &'a HashMap<String, T>,
+) -> impl Iterator<Item = T> + 'a {
+    current
+        .iter()
+        .filter(|(key, _)| !previous.contains_key(*key))
+        .map(|(_, value)| value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{chat, player, running_server, server};
+    use crate::{InstanceStoppedReason, SrsServerInfo};
+
+    fn srs(guids: &[&str]) -> SrsServerInfo {
+        SrsServerInfo {
+            clients: guids
+                .iter()
+                .map(|guid| SrsClient {
+                    client_guid: guid.to_string(),
+                    name: guid.to_string(),
+                    coalition: 0,
+                    allow_record: false,
+                    seat: 0,
+                })
+                .collect(),
+            server_version: "2.1".to_string(),
+        }
+    }
+
+    fn kinds(events: &[Event]) -> Vec<EventKind> {
+        events.iter().map(Event::kind).collect()
+    }
+
+    #[test]
+    fn first_observation_is_a_baseline() {
+        let mut bus = EventBus::new();
+        let server = running_server(1, "Training", [player(2, "Viper")]);
+
+        assert!(bus.observe(&server).is_empty());
+        assert!(bus.observe(&server).is_empty());
+    }
+
+    #[test]
+    fn reports_players_pause_and_mission_changes() {
+        let mut bus = EventBus::new();
+        bus.observe(&running_server(1, "Training", [player(2, "Viper")]));
+
+        let mut server = running_server(1, "Training", [player(3, "Hornet")]);
+        if let Some(GameRuntime::Dcs(runtime)) = &mut server.runtime {
+            runtime.paused = true;
+            runtime.mission_info.mission_filename = "Night.miz".to_string();
+        }
+
+        assert_eq!(
+            kinds(&bus.observe(&server)),
+            [
+                EventKind::PlayerJoined,
+                EventKind::PlayerLeft,
+                EventKind::Paused,
+                EventKind::MissionChanged,
+            ]
+        );
+    }
+
+    #[test]
+    fn stopping_drops_players() {
+        let mut bus = EventBus::new();
+        bus.observe(&running_server(1, "Training", [player(2, "Viper")]));
+
+        let mut stopped = server(1, "Training");
+        stopped.instance.status = InstanceStatus::ServerStopped {
+            was_error: false,
+            reason: InstanceStoppedReason::StoppedNormally,
+        };
+
+        assert_eq!(
+            kinds(&bus.observe(&stopped)),
+            [EventKind::StatusChanged, EventKind::PlayerLeft]
+        );
+    }
+
+    #[test]
+    fn reports_new_chat_and_srs_clients() {
+        let mut bus = EventBus::new();
+        let server = running_server(1, "Training", []);
+        let extras = |chat, srs| Extras {
+            chat: Some(chat),
+            srs: Some(srs),
+            triggers: None,
+        };
+
+        let baseline = extras(vec![chat(1, 2, "old")], srs(&["a"]));
+        assert!(bus.observe_extras(&server, baseline).is_empty());
+
+        let mut historical = chat(3, 2, "replayed");
+        historical.is_historical = true;
+        let next = extras(
+            vec![chat(1, 2, "old"), chat(2, 2, "new"), historical],
+            srs(&["b"]),
+        );
+        let events = bus.observe_extras(&server, next);
+
+        assert_eq!(
+            kinds(&events),
+            [
+                EventKind::ChatMessage,
+                EventKind::SrsConnected,
+                EventKind::SrsDisconnected,
+            ]
+        );
+        assert!(matches!(&events[0], Event::ChatMessage { message, .. } if message.id == 2));
+    }
+
+    #[test]
+    fn follows_chat_ids_that_start_over_after_a_restart() {
+        let mut bus = EventBus::new();
+        let server = running_server(1, "Training", []);
+        let extras = |chat| Extras {
+            chat: Some(chat),
+            srs: None,
+            triggers: None,
+        };
+
+        bus.observe(&server);
+        bus.observe_extras(&server, extras(vec![chat(40, 2, "before")]));
+
+        let mut stopped = server.clone();
+        stopped.instance.status = InstanceStatus::ServerStopped {
+            was_error: false,
+            reason: InstanceStoppedReason::StoppedNormally,
+        };
+        bus.observe(&stopped);
+        bus.observe(&server);
+
+        assert!(
+            bus.observe_extras(&server, extras(vec![chat(1, 2, "baseline")]))
+                .is_empty()
+        );
+        let events = bus.observe_extras(
+            &server,
+            extras(vec![chat(1, 2, "baseline"), chat(2, 2, "after")]),
+        );
+        assert!(matches!(&events[..], [Event::ChatMessage { message, .. }] if message.id == 2));
+    }
+
+    #[test]
+    fn serializes_timed_events_flat() {
+        let event = TimedEvent {
+            time: 1_700_000_000,
+            event: Event::Paused {
+                instance_id: Uuid::nil(),
+            },
+        };
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "time": 1_700_000_000,
+                "type": "paused",
+                "instance_id": Uuid::nil(),
+            })
+        );
+        assert_eq!(serde_json::from_value::<TimedEvent>(json).unwrap(), event);
+    }
+
+    #[tokio::test]
+    async fn publishes_the_stamped_event_to_every_sink() {
+        let path = std::env::temp_dir().join(format!("events-{}.jsonl", Uuid::now_v7()));
+        let (channel, mut receiver) = ChannelSink::new(1);
+        let bus = EventBus::new()
+            .sink(JsonLinesSink::open(&path).unwrap())
+            .sink(channel);
+        let event = TimedEvent {
+            time: 42,
+            event: Event::Resumed {
+                instance_id: Uuid::nil(),
+            },
+        };
+
+        bus.dispatch(&event).await;
+
+        assert_eq!(receiver.recv().await, Some(event.event.clone()));
+        let line = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(serde_json::from_str::<TimedEvent>(&line).unwrap(), event);
+    }
+}
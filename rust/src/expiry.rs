@@ -0,0 +1,378 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    BillingType, Client, InstanceResource, InstanceStatus, SendChatRequest, poll_every, unix_now,
+};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpiryEvent {
+    Upcoming {
+        id: Uuid,
+        name: Option<String>,
+        rented_until: i64,
+        lead_time: Duration,
+    },
+    /// An in-game countdown message is due. [`ExpiryMonitor::scan`] only
+    /// returns it once the message was posted.
+    Countdown {
+        id: Uuid,
+        rented_until: i64,
+        lead_time: Duration,
+    },
+    Expired {
+        id: Uuid,
+        name: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ExpiryMonitor {
+    lead_times: Vec<Duration>,
+    countdown: Vec<Duration>,
+    countdown_message: String,
+    notified: HashMap<Uuid, (i64, HashSet<Duration>)>,
+    counted_down: HashMap<Uuid, (i64, HashSet<Duration>)>,
+    expired: HashSet<Uuid>,
+}
+
+impl Default for ExpiryMonitor {
+    fn default() -> Self {
+        Self::new(vec![
+            Duration::from_secs(7 * DAY),
+            Duration::from_secs(3 * DAY),
+            Duration::from_secs(DAY),
+            Duration::from_secs(HOUR),
+        ])
+    }
+}
+
+impl ExpiryMonitor {
+    pub fn new(lead_times: Vec<Duration>) -> Self {
+        Self {
+            lead_times,
+            countdown: Vec::new(),
+            countdown_message: "This server's rental expires in {remaining}.".to_string(),
+            notified: HashMap::new(),
+            counted_down: HashMap::new(),
+            expired: HashSet::new(),
+        }
+    }
+
+    /// Posts a chat message on running monthly servers at each lead time.
+    pub fn in_game_countdown(mut self, lead_times: Vec<Duration>) -> Self {
+        self.countdown = lead_times;
+        self
+    }
+
+    /// `{remaining}` is replaced with the time left, e.g. "30 minutes".
+    pub fn countdown_message(mut self, message: impl Into<String>) -> Self {
+        self.countdown_message = message.into();
+        self
+    }
+
+    pub fn check(&mut self, servers: &[InstanceResource], now: i64) -> Vec<ExpiryEvent> {
+        let mut events = Vec::new();
+
+        for server in servers {
+            let instance = &server.instance;
+            let id = instance.id;
+            let name = server.name().map(str::to_string);
+
+            let Some(rented_until) = instance.rented_until else {
+                continue;
+            };
+
+            if rented_until <= now || instance.status == InstanceStatus::ServerExpired {
+                if self.expired.insert(id) {
+                    events.push(ExpiryEvent::Expired { id, name });
+                }
+                continue;
+            }
+
+            self.expired.remove(&id);
+            let remaining = Duration::from_secs((rented_until - now) as u64);
+
+            if let Some(lead_time) = next_crossed(
+                &self.lead_times,
+                &mut self.notified,
+                id,
+                rented_until,
+                remaining,
+            ) {
+                events.push(ExpiryEvent::Upcoming {
+                    id,
+                    name,
+                    rented_until,
+                    lead_time,
+                });
+            }
+
+            let announces = instance.billing_type == BillingType::Monthly
+                && instance.status == InstanceStatus::ServerStarted;
+            if announces
+                && let Some(lead_time) = next_crossed(
+                    &self.countdown,
+                    &mut self.counted_down,
+                    id,
+                    rented_until,
+                    remaining,
+                )
+            {
+                events.push(ExpiryEvent::Countdown {
+                    id,
+                    rented_until,
+                    lead_time,
+                });
+            }
+        }
+
+        events
+    }
+
+    pub async fn scan(&mut self, client: &Client) -> Result<Vec<ExpiryEvent>> {
+        let servers = client.get_servers().await?;
+        let now = unix_now();
+        let mut events = Vec::new();
+
+        for event in self.check(&servers, now) {
+            if let ExpiryEvent::Countdown {
+                id,
+                rented_until,
+                lead_time,
+            } = &event
+            {
+                let remaining = Duration::from_secs((rented_until - now).max(0) as u64);
+                let msg = self
+                    .countdown_message
+                    .replace("{remaining}", &format_remaining(remaining));
+
+                if let Err(error) = client
+                    .send_chat(id, &SendChatRequest { all: true, msg })
+                    .await
+                {
+                    log::warn!("failed to post expiry countdown on {id}: {error}");
+                    self.forget_countdown(id, lead_time);
+                    continue;
+                }
+            }
+
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+
+    /// Lets the next check report the countdown again.
+    fn forget_countdown(&mut self, id: &Uuid, lead_time: &Duration) {
+        if let Some((_, reported)) = self.counted_down.get_mut(id) {
+            reported.remove(lead_time);
+        }
+    }
+
+    pub async fn run(
+        &mut self,
+        client: &Client,
+        interval: Duration,
+        mut on_event: impl FnMut(ExpiryEvent),
+    ) -> Result<()> {
+        poll_every!(
+            interval,
+            "expiry scan",
+            self.scan(client)
+                .await
+                .map(|events| events.into_iter().for_each(&mut on_event))
+        )
+    }
+}
+
+/// Returns the smallest lead time `remaining` has crossed that was not yet
+/// reported, so a first scan close to expiry reports once instead of once
+/// per lead time. A renewal (new `rented_until`) resets the history.
+fn next_crossed(
+    lead_times: &[Duration],
+    history: &mut HashMap<Uuid, (i64, HashSet<Duration>)>,
+    id: Uuid,
+    rented_until: i64,
+    remaining: Duration,
+) -> Option<Duration> {
+    let (until, reported) = history
+        .entry(id)
+        .or_insert_with(|| (rented_until, HashSet::new()));
+    if *until != rented_until {
+        *until = rented_until;
+        reported.clear();
+    }
+
+    let crossed: Vec<_> = lead_times
+        .iter()
+        .copied()
+        .filter(|lead_time| remaining <= *lead_time)
+        .collect();
+    let smallest = crossed.iter().copied().min()?;
+
+    if reported.contains(&smallest) {
+        return None;
+    }
+
+    reported.extend(crossed);
+    Some(smallest)
+}
+
+fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    let (value, unit) = if seconds >= DAY {
+        (seconds / DAY, "day")
+    } else if seconds >= HOUR {
+        (seconds / HOUR, "hour")
+    } else {
+        ((seconds / MINUTE).max(1), "minute")
+    };
+
+    if value == 1 {
+        format!("1 {unit}")
+    } else {
+        format!("{value} {unit}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::server;
+
+    fn expiring(rented_until: i64, billing_type: BillingType) -> InstanceResource {
+        let mut server = server(1, "Training");
+        server.instance.rented_until = Some(rented_until);
+        server.instance.billing_type = billing_type;
+        server
+    }
+
+    fn lead_times(events: &[ExpiryEvent]) -> Vec<(&'static str, u64)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                ExpiryEvent::Upcoming { lead_time, .. } => Some(("upcoming", lead_time.as_secs())),
+                ExpiryEvent::Countdown { lead_time, .. } => {
+                    Some(("countdown", lead_time.as_secs()))
+                }
+                ExpiryEvent::Expired { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_each_lead_time_once() {
+        let mut monitor =
+            ExpiryMonitor::new(vec![Duration::from_secs(DAY), Duration::from_secs(HOUR)]);
+        let servers = [expiring(10 * DAY as i64, BillingType::Hourly)];
+
+        assert!(monitor.check(&servers, 0).is_empty());
+        let day_before = 9 * DAY as i64;
+        assert_eq!(
+            lead_times(&monitor.check(&servers, day_before)),
+            [("upcoming", DAY)]
+        );
+        assert!(monitor.check(&servers, day_before + 60).is_empty());
+
+        let hour_before = 10 * DAY as i64 - HOUR as i64;
+        assert_eq!(
+            lead_times(&monitor.check(&servers, hour_before)),
+            [("upcoming", HOUR)]
+        );
+    }
+
+    #[test]
+    fn first_check_close_to_expiry_reports_once() {
+        let mut monitor = ExpiryMonitor::default();
+        let servers = [expiring(HOUR as i64 / 2, BillingType::Hourly)];
+
+        assert_eq!(
+            lead_times(&monitor.check(&servers, 0)),
+            [("upcoming", HOUR)]
+        );
+        assert!(monitor.check(&servers, 60).is_empty());
+    }
+
+    #[test]
+    fn renewal_resets_the_history() {
+        let mut monitor = ExpiryMonitor::new(vec![Duration::from_secs(DAY)]);
+
+        let before = [expiring(DAY as i64, BillingType::Hourly)];
+        assert_eq!(monitor.check(&before, 0).len(), 1);
+
+        let renewed = [expiring(DAY as i64 + 60, BillingType::Hourly)];
+        assert_eq!(monitor.check(&renewed, 120).len(), 1);
+    }
+
+    #[test]
+    fn expiry_is_reported_once() {
+        let mut monitor = ExpiryMonitor::default();
+        let servers = [expiring(100, BillingType::Hourly)];
+
+        assert_eq!(
+            monitor.check(&servers, 100),
+            [ExpiryEvent::Expired {
+                id: Uuid::from_u128(1),
+                name: Some("Training".to_string())
+            }]
+        );
+        assert!(monitor.check(&servers, 200).is_empty());
+    }
+
+    #[test]
+    fn countdown_is_only_for_running_monthly_servers() {
+        let mut monitor = ExpiryMonitor::new(Vec::new())
+            .in_game_countdown(vec![Duration::from_secs(30 * MINUTE)]);
+
+        let hourly = [expiring(HOUR as i64, BillingType::Hourly)];
+        assert!(monitor.check(&hourly, HOUR as i64 / 2).is_empty());
+
+        let mut stopped = expiring(HOUR as i64, BillingType::Monthly);
+        stopped.instance.status = InstanceStatus::ServerStopped {
+            was_error: false,
+            reason: crate::InstanceStoppedReason::StoppedNormally,
+        };
+        assert!(monitor.check(&[stopped], HOUR as i64 / 2).is_empty());
+
+        let monthly = [expiring(HOUR as i64, BillingType::Monthly)];
+        assert_eq!(
+            lead_times(&monitor.check(&monthly, HOUR as i64 / 2)),
+            [("countdown", 30 * MINUTE)]
+        );
+        assert!(monitor.check(&monthly, HOUR as i64 / 2 + 60).is_empty());
+    }
+
+    #[test]
+    fn forgotten_countdowns_are_reported_again() {
+        let lead_time = Duration::from_secs(30 * MINUTE);
+        let mut monitor = ExpiryMonitor::new(Vec::new()).in_game_countdown(vec![lead_time]);
+        let monthly = [expiring(HOUR as i64, BillingType::Monthly)];
+
+        assert_eq!(monitor.check(&monthly, HOUR as i64 / 2).len(), 1);
+        monitor.forget_countdown(&Uuid::from_u128(1), &lead_time);
+        assert_eq!(monitor.check(&monthly, HOUR as i64 / 2 + 60).len(), 1);
+    }
+
+    #[test]
+    fn formats_remaining_time() {
+        assert_eq!(
+            format_remaining(Duration::from_secs(2 * DAY + HOUR)),
+            "2 days"
+        );
+        assert_eq!(format_remaining(Duration::from_secs(HOUR)), "1 hour");
+        assert_eq!(format_remaining(Duration::from_secs(90 * MINUTE)), "1 hour");
+        assert_eq!(
+            format_remaining(Duration::from_secs(30 * MINUTE)),
+            "30 minutes"
+        );
+        assert_eq!(format_remaining(Duration::from_secs(10)), "1 minute");
+    }
+}
@@ -0,0 +1,418 @@
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::RwLock;
+use tokio::task::JoinSet;
+
+use crate::http;
+use crate::{
+    Client, Coalition, GameRuntime, InstanceResource, InstanceStatusKind, Region,
+    ServerResourcesResponse, SrsServerInfo, SystemResourcesPeriod, poll_every,
+};
+
+/// Serves fleet metrics in the Prometheus text exposition format on
+/// `/metrics`, refreshed in the background every `interval`.
+#[derive(Debug, Clone)]
+pub struct MetricsExporter {
+    client: Client,
+    interval: Duration,
+}
+
+impl MetricsExporter {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            interval: Duration::from_secs(30),
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Collects the metrics of every instance once.
+    pub async fn collect(&self) -> Result<String> {
+        let servers = self.client.get_servers().await?;
+        let mut tasks = JoinSet::new();
+
+        for (index, server) in servers.into_iter().enumerate() {
+            if server.instance.status.kind() == InstanceStatusKind::ServerDeleted {
+                continue;
+            }
+
+            let client = self.client.clone();
+            tasks.spawn(async move {
+                let details = if server.instance.status.kind() == InstanceStatusKind::ServerStarted
+                {
+                    Some(fetch_details(&client, &server).await)
+                } else {
+                    None
+                };
+                (index, server, details)
+            });
+        }
+
+        let mut instances = tasks.join_all().await;
+        instances.sort_by_key(|(index, _, _)| *index);
+
+        let mut metrics = Metrics::default();
+        for (_, server, details) in &instances {
+            let (resources, srs) = match details {
+                Some((resources, srs)) => (resources.as_ref(), srs.as_ref()),
+                None => (None, None),
+            };
+            record_instance(&mut metrics, server, resources, srs);
+        }
+
+        Ok(metrics.render())
+    }
+
+    async fn refresh(&self, body: &RwLock<String>) -> Result<()> {
+        *body.write().await = self.collect().await?;
+        Ok(())
+    }
+
+    pub async fn serve(self, address: impl ToSocketAddrs) -> Result<()> {
+        let listener = TcpListener::bind(address).await?;
+        let body = Arc::new(RwLock::new(String::new()));
+
+        let refresher = {
+            let body = body.clone();
+            let exporter = self.clone();
+            tokio::spawn(async move {
+                poll_every!(
+                    exporter.interval,
+                    "metrics collection",
+                    exporter.refresh(&body).await
+                )
+            })
+        };
+
+        let result = loop {
+            let (stream, _) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(error) => break Err(error.into()),
+            };
+
+            let body = body.clone();
+            tokio::spawn(async move {
+                if let Err(error) = respond(stream, &body).await {
+                    log::debug!("metrics request failed: {error}");
+                }
+            });
+        };
+
+        refresher.abort();
+        result
+    }
+}
+
+async fn fetch_details(
+    client: &Client,
+    server: &InstanceResource,
+) -> (Option<ServerResourcesResponse>, Option<SrsServerInfo>) {
+    let id = &server.instance.id;
+    let (resources, srs) = tokio::join!(
+        client.get_server_resources(id, SystemResourcesPeriod::Now),
+        client.get_srs_clients(id)
+    );
+
+    let resources = resources
+        .inspect_err(|error| log::warn!("failed to read resources of {id}: {error}"))
+        .ok();
+    let srs = srs
+        .inspect_err(|error| log::debug!("failed to read SRS clients of {id}: {error}"))
+        .ok();
+
+    (resources, srs)
+}
+
+fn record_instance(
+    metrics: &mut Metrics,
+    server: &InstanceResource,
+    resources: Option<&ServerResourcesResponse>,
+    srs: Option<&SrsServerInfo>,
+) {
+    let instance = &server.instance;
+    let labels = format!(
+        "instance_id=\"{}\",region=\"{}\",status=\"{:?}\"",
+        instance.id,
+        region_label(&server.node.region),
+        instance.status.kind()
+    );
+
+    metrics.add(
+        "nimbuspulse_instance_info",
+        "Static instance information, always 1.",
+        format!(
+            "{labels},name=\"{}\",billing_type=\"{}\"",
+            escape(server.name().unwrap_or_default()),
+            instance.billing_type
+        ),
+        1.0,
+    );
+
+    if let Some(rented_until) = instance.rented_until {
+        metrics.add(
+            "nimbuspulse_rented_until_timestamp_seconds",
+            "Unix time the rental expires.",
+            labels.clone(),
+            rented_until as f64,
+        );
+    }
+
+    if let Some(GameRuntime::Dcs(runtime)) = &server.runtime {
+        let players: Vec<_> = runtime.players.players.clients().collect();
+        for (side, count) in [
+            (
+                "spectator",
+                players
+                    .iter()
+                    .filter(|player| Coalition::from_side(player.side).is_none())
+                    .count(),
+            ),
+            (
+                "red",
+                players
+                    .iter()
+                    .filter(|player| player.side == Coalition::Red.side())
+                    .count(),
+            ),
+            (
+                "blue",
+                players
+                    .iter()
+                    .filter(|player| player.side == Coalition::Blue.side())
+                    .count(),
+            ),
+        ] {
+            metrics.add(
+                "nimbuspulse_players",
+                "Connected players per side.",
+                format!("{labels},side=\"{side}\""),
+                count as f64,
+            );
+        }
+
+        metrics.add(
+            "nimbuspulse_mission_time_seconds",
+            "Mission time of the running mission.",
+            labels.clone(),
+            f64::from(runtime.mission_info.mission_time),
+        );
+        metrics.add(
+            "nimbuspulse_paused",
+            "Whether the mission is paused.",
+            labels.clone(),
+            if runtime.paused { 1.0 } else { 0.0 },
+        );
+    }
+
+    if let Some(resources) = resources {
+        let latest =
+            |samples: Vec<(std::time::SystemTime, f64)>| samples.last().map(|(_, value)| *value);
+
+        if let Some(fps) = latest(resources.server_fps_samples()) {
+            metrics.add(
+                "nimbuspulse_server_fps",
+                "Server frames per second.",
+                labels.clone(),
+                fps,
+            );
+        }
+        if let Some(cpu) = latest(resources.cpu_samples()) {
+            metrics.add("nimbuspulse_cpu_usage", "CPU usage.", labels.clone(), cpu);
+        }
+        if let Some(ram) = latest(resources.ram_samples()) {
+            metrics.add("nimbuspulse_ram_bytes", "RAM usage.", labels.clone(), ram);
+        }
+        metrics.add(
+            "nimbuspulse_ram_max_bytes",
+            "RAM available to the instance.",
+            labels.clone(),
+            resources.max_ram as f64,
+        );
+    }
+
+    if let Some(srs) = srs {
+        metrics.add(
+            "nimbuspulse_srs_clients",
+            "Connected SRS clients.",
+            labels,
+            srs.clients.len() as f64,
+        );
+    }
+}
+
+async fn respond(mut stream: TcpStream, body: &RwLock<String>) -> Result<()> {
+    let request = match http::read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(error) => {
+            log::debug!("malformed metrics request: {error}");
+            return http::respond(&mut stream, "400 Bad Request", None, "").await;
+        }
+    };
+
+    if request.method == "GET" && request.path == "/metrics" {
+        let body = body.read().await;
+        http::respond(
+            &mut stream,
+            "200 OK",
+            Some("text/plain; version=0.0.4"),
+            &body,
+        )
+        .await
+    } else {
+        http::respond(&mut stream, "404 Not Found", None, "").await
+    }
+}
+
+#[derive(Debug)]
+struct MetricFamily {
+    name: &'static str,
+    help: &'static str,
+    samples: Vec<(String, f64)>,
+}
+
+/// Metric families in insertion order, so HELP and TYPE are written once per
+/// family as the exposition format requires.
+#[derive(Debug, Default)]
+struct Metrics {
+    families: Vec<MetricFamily>,
+}
+
+impl Metrics {
+    fn add(&mut self, name: &'static str, help: &'static str, labels: String, value: f64) {
+        match self.families.iter_mut().find(|family| family.name == name) {
+            Some(family) => family.samples.push((labels, value)),
+            None => self.families.push(MetricFamily {
+                name,
+                help,
+                samples: vec![(labels, value)],
+            }),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut output = String::new();
+
+        for family in &self.families {
+            let name = family.name;
+            let _ = writeln!(output, "# HELP {name} {}", family.help);
+            let _ = writeln!(output, "# TYPE {name} gauge");
+            for (labels, value) in &family.samples {
+                let _ = writeln!(output, "{name}{{{labels}}} {value}");
+            }
+        }
+
+        output
+    }
+}
+
+fn region_label(region: &Region) -> &'static str {
+    match region {
+        Region::Germany => "de",
+        Region::USA => "us",
+        Region::Invalid => "invalid",
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{player, running_server, server};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn writes_help_and_type_once_per_family() {
+        let mut metrics = Metrics::default();
+        metrics.add("a", "First.", "x=\"1\"".to_string(), 1.0);
+        metrics.add("b", "Second.", "x=\"1\"".to_string(), 2.5);
+        metrics.add("a", "First.", "x=\"2\"".to_string(), 3.0);
+
+        assert_eq!(
+            metrics.render(),
+            "# HELP a First.\n# TYPE a gauge\na{x=\"1\"} 1\na{x=\"2\"} 3\n\
+             # HELP b Second.\n# TYPE b gauge\nb{x=\"1\"} 2.5\n"
+        );
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape("a \"b\"\\c\n"), "a \\\"b\\\"\\\\c\\n");
+    }
+
+    #[test]
+    fn records_players_per_side() {
+        let mut red = player(2, "Red");
+        red.side = Coalition::Red.side();
+        let mut spectator = player(3, "Spectator");
+        spectator.side = 0;
+        let server = running_server(1, "Training", [red, spectator, player(4, "Blue")]);
+
+        let mut metrics = Metrics::default();
+        record_instance(&mut metrics, &server, None, None);
+        let output = metrics.render();
+
+        let labels = format!(
+            "instance_id=\"{}\",region=\"de\",status=\"ServerStarted\"",
+            server.instance.id
+        );
+        for (side, count) in [("spectator", 1), ("red", 1), ("blue", 1)] {
+            assert!(output.contains(&format!(
+                "nimbuspulse_players{{{labels},side=\"{side}\"}} {count}\n"
+            )));
+        }
+        assert!(output.contains("name=\"Training\",billing_type=\"hourly\"} 1\n"));
+        assert!(!output.contains("nimbuspulse_srs_clients"));
+    }
+
+    #[test]
+    fn stopped_instances_only_get_info() {
+        let mut metrics = Metrics::default();
+        record_instance(&mut metrics, &server(1, "Training"), None, None);
+
+        let families: Vec<_> = metrics.families.iter().map(|family| family.name).collect();
+        assert_eq!(families, ["nimbuspulse_instance_info"]);
+    }
+
+    #[tokio::test]
+    async fn serves_metrics() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let body = Arc::new(RwLock::new("up 1\n".to_string()));
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let _ = respond(stream, &body).await;
+            }
+        });
+
+        let get = |path: &'static str| async move {
+            let mut stream = TcpStream::connect(address).await.unwrap();
+            stream
+                .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        let metrics = get("/metrics").await;
+        assert!(metrics.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(metrics.ends_with("\r\n\r\nup 1\n"));
+        assert!(get("/").await.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
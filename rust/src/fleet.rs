@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use crate::{
+    Client, CreateTriggerRequest, Instance, InstanceResource, TriggerDiff, TriggerSyncOptions,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FleetOperation {
+    Start,
+    Stop,
+    Restart,
+    FullRestart,
+    Update,
+}
+
+impl fmt::Display for FleetOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FleetOperation::Start => write!(f, "start"),
+            FleetOperation::Stop => write!(f, "stop"),
+            FleetOperation::Restart => write!(f, "restart"),
+            FleetOperation::FullRestart => write!(f, "full restart"),
+            FleetOperation::Update => write!(f, "update"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FleetOptions {
+    pub concurrency: usize,
+    /// Servers per rolling batch. A batch finishes before the next one starts.
+    pub batch_size: Option<usize>,
+    pub batch_delay: Duration,
+    pub stop_on_failure: bool,
+}
+
+impl Default for FleetOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            batch_size: None,
+            batch_delay: Duration::ZERO,
+            stop_on_failure: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerOutcome<T> {
+    Succeeded(T),
+    Failed(String),
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerResult<T> {
+    pub id: Uuid,
+    pub outcome: ServerOutcome<T>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FleetReport<T = Instance> {
+    pub results: Vec<ServerResult<T>>,
+}
+
+impl<T> FleetReport<T> {
+    pub fn succeeded(&self) -> impl Iterator<Item = (&Uuid, &T)> {
+        self.results
+            .iter()
+            .filter_map(|result| match &result.outcome {
+                ServerOutcome::Succeeded(value) => Some((&result.id, value)),
+                _ => None,
+            })
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = (&Uuid, &str)> {
+        self.results
+            .iter()
+            .filter_map(|result| match &result.outcome {
+                ServerOutcome::Failed(error) => Some((&result.id, error.as_str())),
+                _ => None,
+            })
+    }
+
+    pub fn skipped(&self) -> impl Iterator<Item = &Uuid> {
+        self.results
+            .iter()
+            .filter(|result| matches!(result.outcome, ServerOutcome::Skipped))
+            .map(|result| &result.id)
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.results
+            .iter()
+            .all(|result| matches!(result.outcome, ServerOutcome::Succeeded(_)))
+    }
+}
+
+impl<T> fmt::Display for FleetReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} succeeded, {} failed, {} skipped",
+            self.succeeded().count(),
+            self.failed().count(),
+            self.skipped().count()
+        )?;
+
+        for (id, error) in self.failed() {
+            writeln!(f, "  {id}: {error}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Fleet {
+    client: Client,
+    ids: Vec<Uuid>,
+}
+
+impl Fleet {
+    pub fn new(client: Client, ids: impl IntoIterator<Item = Uuid>) -> Self {
+        Self {
+            client,
+            ids: ids.into_iter().collect(),
+        }
+    }
+
+    pub async fn matching(
+        client: Client,
+        filter: impl Fn(&InstanceResource) -> bool,
+    ) -> Result<Self> {
+        let ids: Vec<_> = client
+            .get_servers()
+            .await?
+            .iter()
+            .filter(|server| filter(server))
+            .map(|server| server.instance.id)
+            .collect();
+
+        Ok(Self::new(client, ids))
+    }
+
+    pub fn ids(&self) -> &[Uuid] {
+        &self.ids
+    }
+
+    pub async fn run(&self, operation: FleetOperation, options: &FleetOptions) -> FleetReport {
+        self.run_with(options, move |client, id| async move {
+            match operation {
+                FleetOperation::Start => client.start_server(&id).await,
+                FleetOperation::Stop => client.stop_server(&id).await,
+                FleetOperation::Restart => client.restart_server(&id).await,
+                FleetOperation::FullRestart => client.full_restart_server(&id).await,
+                FleetOperation::Update => client.update_game_server(&id).await,
+            }
+        })
+        .await
+    }
+
+    /// Applies one trigger set to every server, see [`Client::sync_triggers`].
+    pub async fn sync_triggers(
+        &self,
+        desired: &[CreateTriggerRequest],
+        sync: &TriggerSyncOptions,
+        options: &FleetOptions,
+    ) -> FleetReport<TriggerDiff> {
+        let sync = *sync;
+        let desired: Arc<[CreateTriggerRequest]> = desired.into();
+        self.run_with(options, move |client, id| {
+            let desired = desired.clone();
+            async move { client.sync_triggers(&id, &desired, &sync).await }
+        })
+        .await
+    }
+
+    pub async fn run_with<F, Fut, T>(&self, options: &FleetOptions, operation: F) -> FleetReport<T>
+    where
+        F: Fn(Client, Uuid) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+        T: Send + 'static,
+    {
+        let operation = Arc::new(operation);
+        let semaphore = Arc::new(Semaphore::new(options.concurrency.max(1)));
+        let failed = Arc::new(AtomicBool::new(false));
+        let batch_size = options.batch_size.unwrap_or(self.ids.len()).max(1);
+        let mut outcomes: Vec<Option<ServerOutcome<T>>> = self.ids.iter().map(|_| None).collect();
+
+        for (batch_index, batch) in self.ids.chunks(batch_size).enumerate() {
+            if batch_index > 0 && !options.batch_delay.is_zero() {
+                tokio::time::sleep(options.batch_delay).await;
+            }
+
+            let mut tasks = JoinSet::new();
+            let mut task_indices = HashMap::new();
+
+            for (offset, id) in batch.iter().enumerate() {
+                let index = batch_index * batch_size + offset;
+                if options.stop_on_failure && failed.load(Ordering::SeqCst) {
+                    continue;
+                }
+
+                let Ok(permit) = semaphore.clone().acquire_owned().await else {
+                    break;
+                };
+
+                if options.stop_on_failure && failed.load(Ordering::SeqCst) {
+                    continue;
+                }
+
+                let operation = operation.clone();
+                let failed = failed.clone();
+                let client = self.client.clone();
+                let id = *id;
+
+                let handle = tasks.spawn(async move {
+                    let _permit = permit;
+                    let result = operation(client, id).await;
+                    if result.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    result
+                });
+                task_indices.insert(handle.id(), index);
+            }
+
+            while let Some(joined) = tasks.join_next_with_id().await {
+                let (task_id, outcome) = match joined {
+                    Ok((task_id, Ok(value))) => (task_id, ServerOutcome::Succeeded(value)),
+                    Ok((task_id, Err(error))) => {
+                        (task_id, ServerOutcome::Failed(error.to_string()))
+                    }
+                    Err(error) => {
+                        failed.store(true, Ordering::SeqCst);
+                        (error.id(), ServerOutcome::Failed(error.to_string()))
+                    }
+                };
+
+                if let Some(index) = task_indices.get(&task_id) {
+                    outcomes[*index] = Some(outcome);
+                }
+            }
+        }
+
+        FleetReport {
+            results: self
+                .ids
+                .iter()
+                .zip(outcomes)
+                .map(|(id, outcome)| ServerResult {
+                    id: *id,
+                    outcome: outcome.unwrap_or(ServerOutcome::Skipped),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    fn fleet(count: u128) -> Fleet {
+        Fleet::new(Client::new("key"), (1..=count).map(Uuid::from_u128))
+    }
+
+    #[tokio::test]
+    async fn results_keep_the_server_order() {
+        let report = fleet(5)
+            .run_with(&FleetOptions::default(), |_, id| async move {
+                // Later servers finish first.
+                tokio::time::sleep(Duration::from_millis(50 - id.as_u128() as u64 * 10)).await;
+                if id.as_u128() == 3 {
+                    anyhow::bail!("unreachable");
+                }
+                Ok(id.as_u128())
+            })
+            .await;
+
+        assert_eq!(
+            report
+                .succeeded()
+                .map(|(_, value)| *value)
+                .collect::<Vec<_>>(),
+            [1, 2, 4, 5]
+        );
+        assert_eq!(
+            report.failed().collect::<Vec<_>>(),
+            [(&Uuid::from_u128(3), "unreachable")]
+        );
+        assert!(!report.is_success());
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "4 succeeded, 1 failed, 0 skipped\n  {}: unreachable\n",
+                Uuid::from_u128(3)
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn concurrency_is_bounded() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let options = FleetOptions {
+            concurrency: 2,
+            ..Default::default()
+        };
+
+        let report = fleet(6)
+            .run_with(&options, {
+                let running = running.clone();
+                let peak = peak.clone();
+                move |_, _| {
+                    let running = running.clone();
+                    let peak = peak.clone();
+                    async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok(())
+                    }
+                }
+            })
+            .await;
+
+        assert!(report.is_success());
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn stop_on_failure_skips_later_batches() {
+        let options = FleetOptions {
+            concurrency: 1,
+            batch_size: Some(2),
+            stop_on_failure: true,
+            ..Default::default()
+        };
+
+        let report = fleet(5)
+            .run_with(&options, |_, id| async move {
+                if id.as_u128() == 2 {
+                    anyhow::bail!("failed");
+                }
+                Ok(())
+            })
+            .await;
+
+        assert_eq!(report.succeeded().count(), 1);
+        assert_eq!(report.failed().count(), 1);
+        assert_eq!(
+            report.skipped().copied().collect::<Vec<_>>(),
+            [3, 4, 5].map(Uuid::from_u128)
+        );
+    }
+}
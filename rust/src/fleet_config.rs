@@ -0,0 +1,653 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    BillingType, Client, CreateTriggerRequest, DcsSettingsUpdatePayload, EditInstanceRequest,
+    GameRuntime, InstanceResource, InstanceStatus, Region, SettingChange, Settings, SettingsPatch,
+    Terrain, TriggerChange, TriggerDiff,
+};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FleetConfig {
+    /// Delete triggers that exist on a server but are not declared for it.
+    #[serde(default)]
+    pub prune_triggers: bool,
+    #[serde(default)]
+    pub servers: Vec<ServerSpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServerSpec {
+    pub name: String,
+    pub region: Region,
+    pub billing_type: BillingType,
+    pub plan: Uuid,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub max_players: u32,
+    #[serde(default)]
+    pub active_mods: Vec<String>,
+    #[serde(default)]
+    pub terrains: Vec<Terrain>,
+    #[serde(default)]
+    pub use_voice_chat: bool,
+    #[serde(default)]
+    pub enable_io: bool,
+    #[serde(default)]
+    pub enable_os: bool,
+    #[serde(default)]
+    pub enable_lfs: bool,
+    #[serde(default)]
+    pub settings: SettingsPatch,
+    #[serde(default)]
+    pub missions: Vec<String>,
+    #[serde(default)]
+    pub triggers: Vec<CreateTriggerRequest>,
+    #[serde(default)]
+    pub files: Vec<FileSync>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileSync {
+    pub local: PathBuf,
+    pub remote: String,
+}
+
+impl FleetConfig {
+    pub fn from_toml_str(source: &str) -> Result<Self> {
+        Ok(toml::from_str(source)?)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(source: &str) -> Result<Self> {
+        Ok(serde_yaml::from_str(source)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        let mut config = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml_str(&source)?,
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Self::from_yaml_str(&source)?,
+            _ => bail!("unsupported fleet config format: {}", path.display()),
+        };
+
+        // Local files are declared relative to the config file.
+        if let Some(directory) = path.parent() {
+            for file in config.servers.iter_mut().flat_map(|spec| &mut spec.files) {
+                file.local = directory.join(&file.local);
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub async fn plan(&self, client: &Client) -> Result<FleetPlan> {
+        let servers = client.get_servers().await?;
+        let mut actions = Vec::new();
+
+        for spec in &self.servers {
+            let Some(server) = servers
+                .iter()
+                .filter(|server| !is_deleted(server))
+                .find(|server| server.name() == Some(spec.name.as_str()))
+            else {
+                actions.push(PlannedAction {
+                    server: spec.name.clone(),
+                    action: PlanAction::CreateServer {
+                        spec: Box::new(spec.clone()),
+                    },
+                });
+                continue;
+            };
+
+            for action in self.plan_server(client, spec, server).await? {
+                actions.push(PlannedAction {
+                    server: spec.name.clone(),
+                    action,
+                });
+            }
+        }
+
+        Ok(FleetPlan { actions })
+    }
+
+    async fn plan_server(
+        &self,
+        client: &Client,
+        spec: &ServerSpec,
+        server: &InstanceResource,
+    ) -> Result<Vec<PlanAction>> {
+        let instance = &server.instance;
+        let id = instance.id;
+        let mut actions = Vec::new();
+
+        if instance.billing_type != spec.billing_type
+            || server.node.region != spec.region
+            || instance.product_id != spec.plan
+        {
+            actions.push(PlanAction::Unsupported {
+                id,
+                reason: "region, billing type or plan differ; recreate the server to change them"
+                    .to_string(),
+            });
+        }
+
+        let wanted_mods: HashSet<_> = spec.active_mods.iter().collect();
+        let active_mods: HashSet<_> = instance.active_mods.iter().collect();
+        if wanted_mods != active_mods {
+            actions.push(PlanAction::Unsupported {
+                id,
+                reason: "active mods differ; mods can only be chosen at creation".to_string(),
+            });
+        }
+
+        let wanted_terrains: HashSet<_> = spec.terrains.iter().collect();
+        let installed_terrains: HashSet<_> = instance.wanted_terrains.iter().collect();
+        if wanted_terrains != installed_terrains {
+            actions.push(PlanAction::ChangeTerrains {
+                id,
+                from: instance.wanted_terrains.clone(),
+                to: spec.terrains.clone(),
+            });
+        }
+
+        let update = DcsSettingsUpdatePayload {
+            enable_io: spec.enable_io,
+            enable_os: spec.enable_os,
+            enable_lfs: spec.enable_lfs,
+        };
+        let current = instance
+            .dcs_settings
+            .as_ref()
+            .map(|settings| DcsSettingsUpdatePayload {
+                enable_io: settings.enable_io,
+                enable_os: settings.enable_os,
+                enable_lfs: settings.enable_lfs,
+            });
+        if current.as_ref() != Some(&update) {
+            actions.push(PlanAction::UpdateServer {
+                id,
+                request: EditInstanceRequest::dcs(update),
+            });
+        }
+
+        match &server.runtime {
+            Some(GameRuntime::Dcs(runtime)) => {
+                let current = &runtime.settings.settings;
+                actions.extend(settings_action(id, &spec.settings, current));
+
+                let missing: Vec<_> = spec
+                    .missions
+                    .iter()
+                    .filter(|mission| !current.mission_list.contains(mission))
+                    .cloned()
+                    .collect();
+                if !missing.is_empty() {
+                    actions.push(PlanAction::AddMissions {
+                        id,
+                        missions: missing,
+                    });
+                }
+            }
+            None if !spec.settings.is_empty() || !spec.missions.is_empty() => {
+                actions.push(PlanAction::Unsupported {
+                    id,
+                    reason: "runtime is not available; settings and missions cannot be compared"
+                        .to_string(),
+                });
+            }
+            None => {}
+        }
+
+        let triggers = client.list_triggers(&id).await?;
+        let diff = TriggerDiff::between(id, &triggers, &spec.triggers, self.prune_triggers);
+        actions.extend(trigger_actions(diff));
+
+        for file in &spec.files {
+            let local = std::fs::read(&file.local)
+                .with_context(|| format!("failed to read {}", file.local.display()))?;
+
+            let up_to_date = client
+                .download_file(&id, &file.remote)
+                .await
+                .is_ok_and(|remote| remote == local);
+
+            if !up_to_date {
+                actions.push(PlanAction::UploadFile {
+                    id,
+                    local: file.local.clone(),
+                    remote: file.remote.clone(),
+                });
+            }
+        }
+
+        Ok(actions)
+    }
+}
+
+/// The settings patch needed to reach `wanted`, if any. Passwords are
+/// reported hashed or not at all, so they are sent along with other changes
+/// but never count as drift on their own.
+fn settings_action(id: Uuid, wanted: &SettingsPatch, current: &Settings) -> Option<PlanAction> {
+    let mismatched = wanted.mismatches(current);
+    if mismatched.is_empty() {
+        return None;
+    }
+
+    let mut patched = current.clone();
+    wanted.apply(&mut patched);
+    let changes = current
+        .diff(&patched)
+        .into_iter()
+        .filter(|change| mismatched.contains(&change.field))
+        .collect();
+
+    Some(PlanAction::PatchSettings {
+        id,
+        patch: Box::new(wanted.clone()),
+        changes,
+    })
+}
+
+/// Plan actions of a trigger diff. Duplicates left by a diff without pruning
+/// can't be resolved by updating, so they're reported as unsupported.
+fn trigger_actions(diff: TriggerDiff) -> Vec<PlanAction> {
+    let id = diff.instance_id;
+    let changes = diff.changes.into_iter().map(|change| match change {
+        TriggerChange::Create { trigger } => PlanAction::CreateTrigger { id, trigger },
+        TriggerChange::Update {
+            trigger_id,
+            trigger,
+            ..
+        } => PlanAction::UpdateTrigger {
+            id,
+            trigger_id,
+            trigger,
+        },
+        TriggerChange::Delete { trigger_id, name } => PlanAction::DeleteTrigger {
+            id,
+            trigger_id,
+            name,
+        },
+    });
+    let duplicates = diff
+        .duplicates
+        .into_iter()
+        .map(|name| PlanAction::Unsupported {
+            id,
+            reason: format!(
+                "trigger {name} exists more than once; set prune_triggers to remove the copies"
+            ),
+        });
+
+    changes.chain(duplicates).collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanAction {
+    /// Only creates the server; settings, missions, triggers and files are
+    /// planned on the next run once the server exists.
+    CreateServer {
+        spec: Box<ServerSpec>,
+    },
+    ChangeTerrains {
+        id: Uuid,
+        from: Vec<Terrain>,
+        to: Vec<Terrain>,
+    },
+    UpdateServer {
+        id: Uuid,
+        request: EditInstanceRequest,
+    },
+    PatchSettings {
+        id: Uuid,
+        patch: Box<SettingsPatch>,
+        changes: Vec<SettingChange>,
+    },
+    AddMissions {
+        id: Uuid,
+        missions: Vec<String>,
+    },
+    CreateTrigger {
+        id: Uuid,
+        trigger: CreateTriggerRequest,
+    },
+    UpdateTrigger {
+        id: Uuid,
+        trigger_id: Uuid,
+        trigger: CreateTriggerRequest,
+    },
+    DeleteTrigger {
+        id: Uuid,
+        trigger_id: Uuid,
+        name: String,
+    },
+    UploadFile {
+        id: Uuid,
+        local: PathBuf,
+        remote: String,
+    },
+    /// Drift that cannot be resolved through the API.
+    Unsupported {
+        id: Uuid,
+        reason: String,
+    },
+}
+
+impl fmt::Display for PlanAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanAction::CreateServer { spec } => write!(
+                f,
+                "+ create server in {:?} ({})",
+                spec.region, spec.billing_type
+            ),
+            PlanAction::ChangeTerrains { from, to, .. } => {
+                write!(f, "~ terrains {from:?} -> {to:?}")
+            }
+            PlanAction::UpdateServer { request, .. } => {
+                let EditInstanceRequest::Dcs(settings) = request;
+                write!(
+                    f,
+                    "~ enable_io = {}, enable_os = {}, enable_lfs = {}",
+                    settings.enable_io, settings.enable_os, settings.enable_lfs
+                )
+            }
+            PlanAction::PatchSettings { changes, .. } => {
+                let changes: Vec<_> = changes.iter().map(ToString::to_string).collect();
+                write!(f, "~ settings: {}", changes.join("; "))
+            }
+            PlanAction::AddMissions { missions, .. } => {
+                write!(f, "+ missions {}", missions.join(", "))
+            }
+            PlanAction::CreateTrigger { trigger, .. } => write!(f, "+ trigger {}", trigger.name),
+            PlanAction::UpdateTrigger { trigger, .. } => {
+                write!(f, "~ trigger {}", trigger.name)
+            }
+            PlanAction::DeleteTrigger { name, .. } => write!(f, "- trigger {name}"),
+            PlanAction::UploadFile { local, remote, .. } => {
+                write!(f, "~ file {remote} from {}", local.display())
+            }
+            PlanAction::Unsupported { reason, .. } => write!(f, "! {reason}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedAction {
+    pub server: String,
+    pub action: PlanAction,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FleetPlan {
+    pub actions: Vec<PlannedAction>,
+}
+
+impl FleetPlan {
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Executes every action in order and stops at the first failure.
+    /// Unsupported actions are skipped.
+    pub async fn apply(&self, client: &Client) -> Result<usize> {
+        let mut applied = 0;
+
+        for planned in &self.actions {
+            let result = match &planned.action {
+                PlanAction::CreateServer { spec } => client
+                    .create_server(
+                        spec.region.clone(),
+                        spec.billing_type.clone(),
+                        spec.name.clone(),
+                        spec.password.clone(),
+                        spec.max_players,
+                        spec.plan,
+                        spec.active_mods.clone(),
+                        spec.terrains.clone(),
+                        spec.use_voice_chat,
+                        spec.enable_io,
+                        spec.enable_os,
+                        spec.enable_lfs,
+                    )
+                    .await
+                    .map(|_| ()),
+                PlanAction::ChangeTerrains { id, to, .. } => {
+                    client.change_server_terrains(id, to).await
+                }
+                PlanAction::UpdateServer { id, request } => {
+                    client.update_server(id, request).await.map(|_| ())
+                }
+                PlanAction::PatchSettings { id, patch, .. } => {
+                    client.patch_settings(id, patch).await.map(|_| ())
+                }
+                PlanAction::AddMissions { id, missions } => {
+                    client.add_missions(id, missions).await.map(|_| ())
+                }
+                PlanAction::CreateTrigger { id, trigger } => {
+                    client.create_trigger(id, trigger).await.map(|_| ())
+                }
+                PlanAction::UpdateTrigger {
+                    id,
+                    trigger_id,
+                    trigger,
+                } => client
+                    .update_trigger(id, trigger_id, trigger)
+                    .await
+                    .map(|_| ()),
+                PlanAction::DeleteTrigger { id, trigger_id, .. } => {
+                    client.delete_trigger(id, trigger_id).await
+                }
+                PlanAction::UploadFile { id, local, remote } => {
+                    client.upload_file_from(id, remote, local).await
+                }
+                PlanAction::Unsupported { reason, .. } => {
+                    log::warn!("skipping {}: {reason}", planned.server);
+                    continue;
+                }
+            };
+
+            result.with_context(|| format!("{}: {}", planned.server, planned.action))?;
+            applied += 1;
+        }
+
+        Ok(applied)
+    }
+}
+
+impl fmt::Display for FleetPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.actions.is_empty() {
+            return writeln!(f, "no changes");
+        }
+
+        for planned in &self.actions {
+            writeln!(f, "{}: {}", planned.server, planned.action)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_deleted(server: &InstanceResource) -> bool {
+    server.instance.want_delete || server.instance.status == InstanceStatus::ServerDeleted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TriggerAction, TriggerCondition};
+
+    const CONFIG: &str = r#"
+prune_triggers = true
+
+[[servers]]
+name = "Training"
+region = "de"
+billing_type = "hourly"
+plan = "0190d6f2-7f3c-7cc2-9a3e-6c5d2f1b8a10"
+max_players = 16
+terrains = ["Caucasus"]
+missions = ["C:\\Missions\\Training.miz"]
+
+[servers.settings]
+maxPlayers = 16
+advanced = { maxPing = 300 }
+
+[[servers.triggers]]
+name = "nightly restart"
+condition = { type = "Schedule", config = { cron_expression = "0 4 * * *" } }
+action = { type = "RestartInstance" }
+
+[[servers.files]]
+local = "scripts/hooks.lua"
+remote = "Scripts/Hooks/hooks.lua"
+"#;
+
+    #[test]
+    fn parses_toml() {
+        let config = FleetConfig::from_toml_str(CONFIG).unwrap();
+        let server = &config.servers[0];
+
+        assert!(config.prune_triggers);
+        assert_eq!(server.region, Region::Germany);
+        assert_eq!(server.billing_type, BillingType::Hourly);
+        assert_eq!(server.terrains, [Terrain::Caucasus]);
+        assert_eq!(server.settings.max_players, Some(16));
+        assert_eq!(server.settings.advanced.max_ping, Some(300));
+        assert!(!server.enable_io);
+        assert_eq!(
+            server.triggers[0].condition,
+            TriggerCondition::Schedule {
+                cron_expression: "0 4 * * *".to_string()
+            }
+        );
+        assert_eq!(server.triggers[0].action, TriggerAction::RestartInstance);
+    }
+
+    fn write_config(file_name: &str) -> (PathBuf, PathBuf) {
+        let directory = std::env::temp_dir().join(format!("fleet-config-{}", Uuid::now_v7()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(file_name);
+        std::fs::write(&path, CONFIG).unwrap();
+        (directory, path)
+    }
+
+    #[test]
+    fn load_resolves_files_relative_to_the_config() {
+        let (directory, path) = write_config("fleet.toml");
+
+        let config = FleetConfig::load(&path);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(
+            config.unwrap().servers[0].files[0].local,
+            directory.join("scripts/hooks.lua")
+        );
+    }
+
+    #[test]
+    fn load_rejects_unknown_formats() {
+        let (directory, path) = write_config("fleet.ini");
+
+        let config = FleetConfig::load(&path);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert!(
+            config
+                .unwrap_err()
+                .to_string()
+                .starts_with("unsupported fleet config format")
+        );
+    }
+
+    #[test]
+    fn passwords_alone_are_not_drift() {
+        let current = crate::test_support::settings();
+        let mut wanted: SettingsPatch = serde_json::from_str(
+            r#"{"password": "secret", "maxPlayers": 16, "advanced": {"redPassword": "red"}}"#,
+        )
+        .unwrap();
+        assert_eq!(settings_action(Uuid::nil(), &wanted, &current), None);
+
+        wanted.max_players = Some(32);
+        let Some(PlanAction::PatchSettings { patch, changes, .. }) =
+            settings_action(Uuid::nil(), &wanted, &current)
+        else {
+            panic!("expected a settings patch");
+        };
+        assert_eq!(patch.password.as_deref(), Some("secret"));
+        let fields: Vec<_> = changes.iter().map(|change| change.field).collect();
+        assert_eq!(fields, ["max_players"]);
+    }
+
+    #[test]
+    fn describes_trigger_actions() {
+        let config = FleetConfig::from_toml_str(CONFIG).unwrap();
+        let trigger = config.servers[0].triggers[0].clone();
+        let id = Uuid::nil();
+
+        let create = PlanAction::CreateTrigger {
+            id,
+            trigger: trigger.clone(),
+        };
+        let update = PlanAction::UpdateTrigger {
+            id,
+            trigger_id: Uuid::nil(),
+            trigger,
+        };
+        let delete = PlanAction::DeleteTrigger {
+            id,
+            trigger_id: Uuid::nil(),
+            name: "old".to_string(),
+        };
+
+        assert_eq!(create.to_string(), "+ trigger nightly restart");
+        assert_eq!(update.to_string(), "~ trigger nightly restart");
+        assert_eq!(delete.to_string(), "- trigger old");
+    }
+
+    #[test]
+    fn plans_trigger_changes_and_reports_duplicates() {
+        use crate::test_support::trigger;
+
+        let existing = [
+            trigger(1, "nightly restart", "0 3 * * *"),
+            trigger(2, "nightly restart", "0 2 * * *"),
+        ];
+        let desired = [trigger(0, "nightly restart", "0 4 * * *").to_request()];
+
+        let diff = TriggerDiff::between(Uuid::nil(), &existing, &desired, false);
+        let actions: Vec<_> = trigger_actions(diff)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                "~ trigger nightly restart",
+                "! trigger nightly restart exists more than once; set prune_triggers to remove the copies",
+            ]
+        );
+
+        let diff = TriggerDiff::between(Uuid::nil(), &existing, &desired, true);
+        let actions = trigger_actions(diff);
+        assert!(matches!(
+            actions[..],
+            [
+                PlanAction::UpdateTrigger { trigger_id: kept, .. },
+                PlanAction::DeleteTrigger { trigger_id: pruned, .. },
+            ] if kept == Uuid::from_u128(1) && pruned == Uuid::from_u128(2)
+        ));
+    }
+}
@@ -0,0 +1,624 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension, params};
+use uuid::Uuid;
+
+use crate::{Client, DcsRuntime, GameRuntime, poll_every, unix_now};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS players (
+    ucid TEXT PRIMARY KEY,
+    first_seen INTEGER NOT NULL,
+    last_seen INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS player_names (
+    ucid TEXT NOT NULL,
+    name TEXT NOT NULL,
+    first_seen INTEGER NOT NULL,
+    last_seen INTEGER NOT NULL,
+    PRIMARY KEY (ucid, name)
+);
+CREATE TABLE IF NOT EXISTS player_ips (
+    ucid TEXT NOT NULL,
+    ipaddr TEXT NOT NULL,
+    first_seen INTEGER NOT NULL,
+    last_seen INTEGER NOT NULL,
+    PRIMARY KEY (ucid, ipaddr)
+);
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ucid TEXT NOT NULL,
+    instance_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    last_seen INTEGER NOT NULL,
+    ended_at INTEGER,
+    ping_samples INTEGER NOT NULL,
+    ping_sum INTEGER NOT NULL,
+    ping_min INTEGER NOT NULL,
+    ping_max INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS sessions_ucid ON sessions (ucid);
+CREATE INDEX IF NOT EXISTS sessions_instance ON sessions (instance_id, started_at);
+CREATE TABLE IF NOT EXISTS session_slots (
+    session_id INTEGER NOT NULL REFERENCES sessions (id),
+    side INTEGER NOT NULL,
+    slot TEXT NOT NULL,
+    first_seen INTEGER NOT NULL,
+    last_seen INTEGER NOT NULL,
+    PRIMARY KEY (session_id, side, slot)
+);
+CREATE TABLE IF NOT EXISTS bans (
+    ucid TEXT NOT NULL,
+    instance_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    ipaddr TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    banned_from INTEGER NOT NULL,
+    banned_until INTEGER NOT NULL,
+    PRIMARY KEY (ucid, instance_id, banned_from)
+);
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasRecord {
+    pub value: String,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionRecord {
+    pub id: i64,
+    pub ucid: String,
+    pub instance_id: Uuid,
+    pub name: String,
+    pub started_at: i64,
+    pub last_seen: i64,
+    pub ended_at: Option<i64>,
+    pub ping: PingStats,
+}
+
+impl SessionRecord {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.last_seen.saturating_sub(self.started_at).max(0) as u64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PingStats {
+    pub samples: i64,
+    pub min: i32,
+    pub max: i32,
+    pub mean: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotRecord {
+    pub side: i32,
+    pub slot: String,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BanRecord {
+    pub ucid: String,
+    pub instance_id: Uuid,
+    pub name: String,
+    pub ipaddr: String,
+    pub reason: String,
+    pub banned_from: i64,
+    pub banned_until: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerSummary {
+    pub ucid: String,
+    pub first_seen: i64,
+    pub last_seen: i64,
+    pub names: Vec<AliasRecord>,
+    pub ip_addresses: Vec<AliasRecord>,
+    pub sessions: i64,
+    pub playtime: Duration,
+    pub sides: Vec<i32>,
+    pub slots: Vec<SlotRecord>,
+    pub ping: PingStats,
+    pub bans: Vec<BanRecord>,
+}
+
+#[derive(Debug)]
+pub struct PlayerHistory {
+    connection: Connection,
+    session_gap: Duration,
+}
+
+impl PlayerHistory {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection,
+            session_gap: Duration::from_secs(5 * 60),
+        })
+    }
+
+    /// Sessions that have not been seen for longer than `gap` are closed and a
+    /// new session is started the next time the player shows up.
+    pub fn session_gap(mut self, gap: Duration) -> Self {
+        self.session_gap = gap;
+        self
+    }
+
+    pub fn record(&mut self, instance_id: &Uuid, runtime: &DcsRuntime, now: i64) -> Result<()> {
+        let instance = instance_id.to_string();
+        let gap = self.session_gap.as_secs() as i64;
+        let tx = self.connection.transaction()?;
+        let mut online = HashSet::new();
+
+        for player in runtime.players.players.clients() {
+            online.insert(player.ucid.clone());
+
+            tx.execute(
+                "INSERT INTO players (ucid, first_seen, last_seen) VALUES (?1, ?2, ?2)
+                 ON CONFLICT (ucid) DO UPDATE SET last_seen = excluded.last_seen",
+                params![player.ucid, now],
+            )?;
+            tx.execute(
+                "INSERT INTO player_names (ucid, name, first_seen, last_seen) VALUES (?1, ?2, ?3, ?3)
+                 ON CONFLICT (ucid, name) DO UPDATE SET last_seen = excluded.last_seen",
+                params![player.ucid, player.name, now],
+            )?;
+            tx.execute(
+                "INSERT INTO player_ips (ucid, ipaddr, first_seen, last_seen) VALUES (?1, ?2, ?3, ?3)
+                 ON CONFLICT (ucid, ipaddr) DO UPDATE SET last_seen = excluded.last_seen",
+                params![player.ucid, player.ipaddr, now],
+            )?;
+
+            let open: Option<(i64, i64)> = tx
+                .query_row(
+                    "SELECT id, last_seen FROM sessions
+                     WHERE ucid = ?1 AND instance_id = ?2 AND ended_at IS NULL",
+                    params![player.ucid, instance],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+
+            let session_id = match open {
+                Some((id, last_seen)) if now - last_seen <= gap => {
+                    tx.execute(
+                        "UPDATE sessions SET
+                            name = ?2,
+                            last_seen = ?3,
+                            ping_samples = ping_samples + 1,
+                            ping_sum = ping_sum + ?4,
+                            ping_min = MIN(ping_min, ?4),
+                            ping_max = MAX(ping_max, ?4)
+                         WHERE id = ?1",
+                        params![id, player.name, now, player.ping],
+                    )?;
+                    id
+                }
+                stale => {
+                    if let Some((id, _)) = stale {
+                        tx.execute(
+                            "UPDATE sessions SET ended_at = last_seen WHERE id = ?1",
+                            params![id],
+                        )?;
+                    }
+
+                    tx.execute(
+                        "INSERT INTO sessions (
+                            ucid, instance_id, name, started_at, last_seen,
+                            ping_samples, ping_sum, ping_min, ping_max
+                         ) VALUES (?1, ?2, ?3, ?4, ?4, 1, ?5, ?5, ?5)",
+                        params![player.ucid, instance, player.name, now, player.ping],
+                    )?;
+                    tx.last_insert_rowid()
+                }
+            };
+
+            tx.execute(
+                "INSERT INTO session_slots (session_id, side, slot, first_seen, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?4)
+                 ON CONFLICT (session_id, side, slot) DO UPDATE SET last_seen = excluded.last_seen",
+                params![session_id, player.side, player.slot, now],
+            )?;
+        }
+
+        {
+            // Players missing from a single poll keep their session open until
+            // the gap has passed, so short drop-outs do not split sessions.
+            let mut open = tx.prepare(
+                "SELECT id, ucid FROM sessions
+                 WHERE instance_id = ?1 AND ended_at IS NULL AND ?2 - last_seen > ?3",
+            )?;
+            let left: Vec<i64> = open
+                .query_map(params![instance, now, gap], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?
+                .into_iter()
+                .filter(|(_, ucid)| !online.contains(ucid))
+                .map(|(id, _)| id)
+                .collect();
+
+            for id in left {
+                tx.execute(
+                    "UPDATE sessions SET ended_at = last_seen WHERE id = ?1",
+                    params![id],
+                )?;
+            }
+        }
+
+        for ban in &runtime.players.players.banned {
+            tx.execute(
+                "INSERT OR IGNORE INTO bans (
+                    ucid, instance_id, name, ipaddr, reason, banned_from, banned_until
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    ban.ucid,
+                    instance,
+                    ban.name,
+                    ban.ipaddr,
+                    ban.reason,
+                    ban.banned_from,
+                    ban.banned_until
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    pub async fn poll(&mut self, client: &Client) -> Result<usize> {
+        let servers = client.get_servers().await?;
+        let now = unix_now();
+        let mut recorded = 0;
+
+        for server in servers {
+            if let Some(GameRuntime::Dcs(runtime)) = &server.runtime {
+                self.record(&server.instance.id, runtime, now)?;
+                recorded += 1;
+            }
+        }
+
+        Ok(recorded)
+    }
+
+    pub async fn run(&mut self, client: &Client, interval: Duration) -> Result<()> {
+        poll_every!(interval, "player history poll", self.poll(client).await)
+    }
+
+    pub fn aliases(&self, ucid: &str) -> Result<Vec<AliasRecord>> {
+        self.alias_records(
+            "SELECT name, first_seen, last_seen FROM player_names
+             WHERE ucid = ?1 ORDER BY last_seen DESC",
+            ucid,
+        )
+    }
+
+    pub fn ip_addresses(&self, ucid: &str) -> Result<Vec<AliasRecord>> {
+        self.alias_records(
+            "SELECT ipaddr, first_seen, last_seen FROM player_ips
+             WHERE ucid = ?1 ORDER BY last_seen DESC",
+            ucid,
+        )
+    }
+
+    /// UCIDs that have used a name matching the SQL `LIKE` pattern.
+    pub fn find_by_name(&self, pattern: &str) -> Result<Vec<String>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT DISTINCT ucid FROM player_names WHERE name LIKE ?1 ORDER BY ucid")?;
+        let rows = statement.query_map(params![pattern], |row| row.get(0))?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn sessions(&self, ucid: &str) -> Result<Vec<SessionRecord>> {
+        self.session_records(
+            "SELECT id, ucid, instance_id, name, started_at, last_seen, ended_at,
+                    ping_samples, ping_sum, ping_min, ping_max
+             FROM sessions WHERE ucid = ?1 ORDER BY started_at DESC",
+            params![ucid],
+        )
+    }
+
+    /// Sessions on `instance_id` (or any instance) that overlap `[from, to]`.
+    pub fn sessions_between(
+        &self,
+        instance_id: Option<&Uuid>,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<SessionRecord>> {
+        self.session_records(
+            "SELECT id, ucid, instance_id, name, started_at, last_seen, ended_at,
+                    ping_samples, ping_sum, ping_min, ping_max
+             FROM sessions
+             WHERE (?1 IS NULL OR instance_id = ?1) AND started_at <= ?3 AND last_seen >= ?2
+             ORDER BY started_at",
+            params![instance_id.map(|id| id.to_string()), from, to],
+        )
+    }
+
+    pub fn slots(&self, ucid: &str) -> Result<Vec<SlotRecord>> {
+        let mut statement = self.connection.prepare(
+            "SELECT slots.side, slots.slot, MIN(slots.first_seen), MAX(slots.last_seen)
+             FROM session_slots slots JOIN sessions ON sessions.id = slots.session_id
+             WHERE sessions.ucid = ?1
+             GROUP BY slots.side, slots.slot
+             ORDER BY MAX(slots.last_seen) DESC",
+        )?;
+        let rows = statement.query_map(params![ucid], |row| {
+            Ok(SlotRecord {
+                side: row.get(0)?,
+                slot: row.get(1)?,
+                first_seen: row.get(2)?,
+                last_seen: row.get(3)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn bans(&self, ucid: &str) -> Result<Vec<BanRecord>> {
+        let mut statement = self.connection.prepare(
+            "SELECT ucid, instance_id, name, ipaddr, reason, banned_from, banned_until
+             FROM bans WHERE ucid = ?1 ORDER BY banned_from DESC",
+        )?;
+        let rows = statement.query_map(params![ucid], |row| {
+            Ok(BanRecord {
+                ucid: row.get(0)?,
+                instance_id: parse_uuid(row.get(1)?)?,
+                name: row.get(2)?,
+                ipaddr: row.get(3)?,
+                reason: row.get(4)?,
+                banned_from: row.get(5)?,
+                banned_until: row.get(6)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn summary(&self, ucid: &str) -> Result<Option<PlayerSummary>> {
+        let Some((first_seen, last_seen)) = self
+            .connection
+            .query_row(
+                "SELECT first_seen, last_seen FROM players WHERE ucid = ?1",
+                params![ucid],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        let (sessions, playtime, samples, ping_sum, ping_min, ping_max) =
+            self.connection.query_row(
+                "SELECT COUNT(*), COALESCE(SUM(last_seen - started_at), 0),
+                        COALESCE(SUM(ping_samples), 0), COALESCE(SUM(ping_sum), 0),
+                        COALESCE(MIN(ping_min), 0), COALESCE(MAX(ping_max), 0)
+                 FROM sessions WHERE ucid = ?1",
+                params![ucid],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, i64>(3)?,
+                        row.get::<_, i32>(4)?,
+                        row.get::<_, i32>(5)?,
+                    ))
+                },
+            )?;
+
+        let slots = self.slots(ucid)?;
+        let mut sides: Vec<i32> = slots.iter().map(|slot| slot.side).collect();
+        sides.sort_unstable();
+        sides.dedup();
+
+        Ok(Some(PlayerSummary {
+            ucid: ucid.to_string(),
+            first_seen,
+            last_seen,
+            names: self.aliases(ucid)?,
+            ip_addresses: self.ip_addresses(ucid)?,
+            sessions,
+            playtime: Duration::from_secs(playtime.max(0) as u64),
+            sides,
+            slots,
+            ping: ping_stats(samples, ping_sum, ping_min, ping_max),
+            bans: self.bans(ucid)?,
+        }))
+    }
+
+    fn alias_records(&self, query: &str, ucid: &str) -> Result<Vec<AliasRecord>> {
+        let mut statement = self.connection.prepare(query)?;
+        let rows = statement.query_map(params![ucid], |row| {
+            Ok(AliasRecord {
+                value: row.get(0)?,
+                first_seen: row.get(1)?,
+                last_seen: row.get(2)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn session_records(
+        &self,
+        query: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<SessionRecord>> {
+        let mut statement = self.connection.prepare(query)?;
+        let rows = statement.query_map(params, |row| {
+            Ok(SessionRecord {
+                id: row.get(0)?,
+                ucid: row.get(1)?,
+                instance_id: parse_uuid(row.get(2)?)?,
+                name: row.get(3)?,
+                started_at: row.get(4)?,
+                last_seen: row.get(5)?,
+                ended_at: row.get(6)?,
+                ping: ping_stats(row.get(7)?, row.get(8)?, row.get(9)?, row.get(10)?),
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+fn ping_stats(samples: i64, sum: i64, min: i32, max: i32) -> PingStats {
+    PingStats {
+        samples,
+        min,
+        max,
+        mean: if samples > 0 {
+            (sum / samples) as i32
+        } else {
+            0
+        },
+    }
+}
+
+fn parse_uuid(value: String) -> rusqlite::Result<Uuid> {
+    Uuid::parse_str(&value).map_err(|error| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(error))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BannedPlayer;
+    use crate::test_support::{player, runtime};
+
+    fn history() -> PlayerHistory {
+        PlayerHistory::open_in_memory()
+            .unwrap()
+            .session_gap(Duration::from_secs(60))
+    }
+
+    #[test]
+    fn short_drop_outs_keep_the_session_open() {
+        let id = Uuid::nil();
+        let mut history = history();
+
+        history
+            .record(&id, &runtime([player(2, "Viper")]), 0)
+            .unwrap();
+        history.record(&id, &runtime([]), 30).unwrap();
+        history
+            .record(&id, &runtime([player(2, "Viper")]), 60)
+            .unwrap();
+
+        let sessions = history.sessions("ucid-Viper").unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].ended_at, None);
+        assert_eq!(sessions[0].duration(), Duration::from_secs(60));
+        assert_eq!(sessions[0].ping.samples, 2);
+    }
+
+    #[test]
+    fn sessions_close_after_the_gap() {
+        let id = Uuid::nil();
+        let mut history = history();
+
+        history
+            .record(&id, &runtime([player(2, "Viper")]), 0)
+            .unwrap();
+        history.record(&id, &runtime([]), 61).unwrap();
+        history
+            .record(&id, &runtime([player(2, "Viper")]), 120)
+            .unwrap();
+
+        let sessions = history.sessions("ucid-Viper").unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].started_at, 120);
+        assert_eq!(sessions[0].ended_at, None);
+        assert_eq!(sessions[1].ended_at, Some(0));
+    }
+
+    #[test]
+    fn returning_after_the_gap_starts_a_new_session() {
+        let id = Uuid::nil();
+        let mut history = history();
+
+        history
+            .record(&id, &runtime([player(2, "Viper")]), 0)
+            .unwrap();
+        history
+            .record(&id, &runtime([player(2, "Viper")]), 100)
+            .unwrap();
+
+        let sessions = history.sessions("ucid-Viper").unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[1].ended_at, Some(0));
+    }
+
+    #[test]
+    fn summary_collects_aliases_slots_and_bans() {
+        let id = Uuid::nil();
+        let mut history = history();
+        let mut renamed = player(2, "Viper");
+        renamed.name = "Maverick".to_string();
+        renamed.ping = 150;
+        renamed.side = 1;
+
+        history
+            .record(&id, &runtime([player(2, "Viper")]), 0)
+            .unwrap();
+        let mut banned = runtime([renamed]);
+        banned.players.players.banned.push(BannedPlayer {
+            banned_from: 20,
+            banned_until: 100,
+            ipaddr: "127.0.0.1".to_string(),
+            name: "Maverick".to_string(),
+            reason: "teamkilling".to_string(),
+            ucid: "ucid-Viper".to_string(),
+        });
+        history.record(&id, &banned, 20).unwrap();
+
+        let summary = history.summary("ucid-Viper").unwrap().unwrap();
+        assert_eq!(summary.first_seen, 0);
+        assert_eq!(summary.last_seen, 20);
+        assert_eq!(
+            summary
+                .names
+                .iter()
+                .map(|name| name.value.as_str())
+                .collect::<Vec<_>>(),
+            ["Maverick", "Viper"]
+        );
+        assert_eq!(summary.sessions, 1);
+        assert_eq!(summary.playtime, Duration::from_secs(20));
+        assert_eq!(summary.sides, [1, 2]);
+        assert_eq!(
+            summary.ping,
+            PingStats {
+                samples: 2,
+                min: 50,
+                max: 150,
+                mean: 100
+            }
+        );
+        assert_eq!(summary.bans.len(), 1);
+        assert_eq!(history.find_by_name("mav%").unwrap(), ["ucid-Viper"]);
+        assert!(history.summary("unknown").unwrap().is_none());
+    }
+}
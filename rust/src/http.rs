@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+/// How long a client gets to send a complete request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Request {
+    pub method: String,
+    pub path: String,
+    /// Header names are lowercased.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+/// Reads one request, giving up after [`READ_TIMEOUT`] so slow clients can't
+/// hold connections open.
+pub(crate) async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    tokio::time::timeout(READ_TIMEOUT, read(stream))
+        .await
+        .context("request timed out")?
+}
+
+async fn read(stream: &mut TcpStream) -> Result<Request> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 4096];
+
+    let header_end = loop {
+        if let Some(position) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 || request.len() + read > MAX_REQUEST_SIZE {
+            bail!("incomplete request");
+        }
+        request.extend_from_slice(&buffer[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&request[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(path)) = (request_line.next(), request_line.next()) else {
+        bail!("malformed request line");
+    };
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    let length: usize = headers
+        .get("content-length")
+        .map(|length| length.parse())
+        .transpose()?
+        .unwrap_or(0);
+    if header_end + length > MAX_REQUEST_SIZE {
+        bail!("request too large");
+    }
+
+    let mut body = request.split_off(header_end);
+    while body.len() < length {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            bail!("incomplete body");
+        }
+        body.extend_from_slice(&buffer[..read]);
+    }
+    body.truncate(length);
+
+    Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers,
+        body,
+    })
+}
+
+/// Writes a response with `status` like `200 OK` and closes the connection.
+pub(crate) async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: Option<&str>,
+    body: &str,
+) -> Result<()> {
+    let content_type = content_type
+        .map(|content_type| format!("Content-Type: {content_type}\r\n"))
+        .unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {status}\r\n{content_type}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn parse(raw: &'static [u8]) -> Result<Request> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut client = TcpStream::connect(address).await.unwrap();
+            client.write_all(raw).await.unwrap();
+            // Keep the connection open so only the request framing ends reads.
+            tokio::time::sleep(READ_TIMEOUT * 2).await;
+        });
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        read_request(&mut stream).await
+    }
+
+    #[tokio::test]
+    async fn reads_headers_and_body() {
+        let request =
+            parse(b"POST /chat HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 4\r\n\r\n{}  extra")
+                .await
+                .unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/chat");
+        assert_eq!(request.headers["content-type"], "application/json");
+        assert_eq!(request.body, b"{}  ");
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_bodies() {
+        let error = parse(b"POST / HTTP/1.1\r\nContent-Length: 1000000\r\n\r\n")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("too large"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn times_out_stalled_requests() {
+        let error = parse(b"GET /metrics HTTP/1.1\r\n").await.unwrap_err();
+        assert!(error.to_string().contains("timed out"));
+    }
+}
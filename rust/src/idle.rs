@@ -0,0 +1,496 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
+use uuid::Uuid;
+
+use crate::{
+    BillingType, Client, HOURS_PER_MONTH, InstanceResource, InstanceStatusKind, PriceTable,
+    SystemResourcesPeriod, parse_timezone, poll_every, unix_now,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdleEvent {
+    Stopped { id: Uuid, idle_for: Duration },
+    Restarted { id: Uuid },
+    Failed { id: Uuid, error: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StoppedServer {
+    since: i64,
+    plan: Uuid,
+    billing_type: BillingType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StoppedTime {
+    plan: Uuid,
+    billing_type: BillingType,
+    secs: u64,
+}
+
+/// Stops servers nobody has played on for a while, and optionally starts them
+/// again on a schedule.
+#[derive(Debug, Clone)]
+pub struct IdleMonitor {
+    idle_after: Duration,
+    hourly_only: bool,
+    servers: Option<Vec<Uuid>>,
+    restart: Option<(Cron, Tz)>,
+    next_restart: Option<DateTime<Utc>>,
+    idle_since: HashMap<Uuid, i64>,
+    stopped: HashMap<Uuid, StoppedServer>,
+    /// Time servers spent stopped by the monitor, excluding current stops.
+    stopped_time: HashMap<Uuid, StoppedTime>,
+}
+
+impl IdleMonitor {
+    pub fn new(idle_after: Duration) -> Self {
+        Self {
+            idle_after,
+            hourly_only: true,
+            servers: None,
+            restart: None,
+            next_restart: None,
+            idle_since: HashMap::new(),
+            stopped: HashMap::new(),
+            stopped_time: HashMap::new(),
+        }
+    }
+
+    /// Also stops monthly servers. Off by default since stopping them saves
+    /// nothing.
+    pub fn include_monthly(mut self, include: bool) -> Self {
+        self.hourly_only = !include;
+        self
+    }
+
+    /// Limits the monitor to `ids` instead of every server on the account.
+    pub fn servers(mut self, ids: impl IntoIterator<Item = Uuid>) -> Self {
+        self.servers = Some(ids.into_iter().collect());
+        self
+    }
+
+    /// Starts servers the monitor stopped whenever `cron_expression` fires.
+    /// `timezone` is an IANA name like `Europe/Berlin`.
+    pub fn restart_on(mut self, cron_expression: &str, timezone: &str) -> Result<Self> {
+        let schedule = Cron::from_str(cron_expression)
+            .with_context(|| format!("invalid cron expression: {cron_expression}"))?;
+        self.restart = Some((schedule, parse_timezone(timezone)?));
+        self.next_restart = None;
+        Ok(self)
+    }
+
+    fn is_watched(&self, server: &InstanceResource) -> bool {
+        let instance = &server.instance;
+
+        self.servers
+            .as_ref()
+            .is_none_or(|ids| ids.contains(&instance.id))
+            && (!self.hourly_only || instance.billing_type == BillingType::Hourly)
+    }
+
+    pub async fn poll(&mut self, client: &Client) -> Result<Vec<IdleEvent>> {
+        let servers = client.get_servers().await?;
+        let now = unix_now();
+        let mut events = Vec::new();
+
+        for (server, since) in self.idle_servers(&servers, now) {
+            let id = server.instance.id;
+
+            // The runtime only shows the current players, so confirm with the
+            // player series that nobody joined in between polls.
+            match self.last_active(client, &id, now).await {
+                Ok(Some(active)) if active > since => {
+                    self.idle_since.insert(id, active);
+                    continue;
+                }
+                Ok(_) => {}
+                Err(error) => log::warn!("failed to read player series of {id}: {error}"),
+            }
+
+            match client.stop_server(&id).await {
+                Ok(_) => events.push(self.mark_stopped(server, since, now)),
+                Err(error) => events.push(IdleEvent::Failed {
+                    id,
+                    error: error.to_string(),
+                }),
+            }
+        }
+
+        // Keep the stops reported even when the restart schedule fails.
+        let due = match self.restarts_due(&servers, Utc::now()) {
+            Ok(due) => due,
+            Err(error) => {
+                log::warn!("failed to check the idle restart schedule: {error}");
+                Vec::new()
+            }
+        };
+        for id in due {
+            match client.start_server(&id).await {
+                Ok(_) => {
+                    self.mark_started(&id, unix_now());
+                    events.push(IdleEvent::Restarted { id });
+                }
+                Err(error) => events.push(IdleEvent::Failed {
+                    id,
+                    error: error.to_string(),
+                }),
+            }
+        }
+        Ok(events)
+    }
+
+    /// Watched servers that have been empty for `idle_after`, with the time
+    /// they became empty.
+    fn idle_servers<'a>(
+        &mut self,
+        servers: &'a [InstanceResource],
+        now: i64,
+    ) -> Vec<(&'a InstanceResource, i64)> {
+        let mut idle = Vec::new();
+        let watched: Vec<_> = servers
+            .iter()
+            .filter(|server| self.is_watched(server))
+            .collect();
+
+        for server in watched {
+            let id = server.instance.id;
+            let status = &server.instance.status;
+
+            if status.is_transitional() {
+                continue;
+            }
+
+            if status.kind() != InstanceStatusKind::ServerStarted {
+                self.idle_since.remove(&id);
+                continue;
+            }
+
+            // Started again by someone else.
+            self.mark_started(&id, now);
+
+            if server.player_count() > 0 {
+                self.idle_since.remove(&id);
+                continue;
+            }
+
+            let since = *self.idle_since.entry(id).or_insert(now);
+            if now - since >= self.idle_after.as_secs() as i64 {
+                idle.push((server, since));
+            }
+        }
+
+        idle
+    }
+
+    fn mark_stopped(&mut self, server: &InstanceResource, since: i64, now: i64) -> IdleEvent {
+        let id = server.instance.id;
+        self.idle_since.remove(&id);
+        self.stopped.insert(
+            id,
+            StoppedServer {
+                since: now,
+                plan: server.instance.product_id,
+                billing_type: server.instance.billing_type.clone(),
+            },
+        );
+
+        IdleEvent::Stopped {
+            id,
+            idle_for: Duration::from_secs((now - since) as u64),
+        }
+    }
+
+    /// Timestamp of the last sample with players inside the idle window.
+    async fn last_active(&self, client: &Client, id: &Uuid, now: i64) -> Result<Option<i64>> {
+        let resources = client
+            .get_server_resources(id, SystemResourcesPeriod::Hour)
+            .await?;
+        let window_start = (now - self.idle_after.as_secs() as i64).max(0) as u64;
+
+        Ok(resources
+            .player_samples()
+            .into_iter()
+            .filter(|(_, players)| *players > 0.0)
+            .filter_map(|(time, _)| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since_epoch| since_epoch.as_secs())
+            .filter(|timestamp| *timestamp >= window_start)
+            .map(|timestamp| timestamp as i64)
+            .max())
+    }
+
+    /// Servers the monitor stopped that are due for their scheduled restart.
+    fn restarts_due(
+        &mut self,
+        servers: &[InstanceResource],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<Uuid>> {
+        let Some((schedule, timezone)) = &self.restart else {
+            return Ok(Vec::new());
+        };
+
+        let next = match self.next_restart {
+            Some(next) => next,
+            None => {
+                let next = schedule
+                    .find_next_occurrence(&now.with_timezone(timezone), false)?
+                    .with_timezone(&Utc);
+                self.next_restart = Some(next);
+                next
+            }
+        };
+
+        if now < next {
+            return Ok(Vec::new());
+        }
+        self.next_restart = None;
+
+        Ok(self
+            .stopped
+            .keys()
+            .copied()
+            .filter(|id| {
+                servers.iter().any(|server| {
+                    server.instance.id == *id
+                        && server.instance.status.kind() == InstanceStatusKind::ServerStopped
+                })
+            })
+            .collect())
+    }
+
+    fn mark_started(&mut self, id: &Uuid, now: i64) {
+        if let Some(stopped) = self.stopped.remove(id) {
+            let time = self.stopped_time.entry(*id).or_insert(StoppedTime {
+                plan: stopped.plan,
+                billing_type: stopped.billing_type,
+                secs: 0,
+            });
+            time.secs += (now - stopped.since).max(0) as u64;
+        }
+    }
+
+    /// Hours servers spent stopped by the monitor, including servers that are
+    /// still stopped.
+    pub fn stopped_hours(&self) -> f64 {
+        let now = unix_now();
+        let finished: u64 = self.stopped_time.values().map(|time| time.secs).sum();
+        let ongoing: u64 = self
+            .stopped
+            .values()
+            .map(|stopped| (now - stopped.since).max(0) as u64)
+            .sum();
+
+        (finished + ongoing) as f64 / 3600.0
+    }
+
+    /// Money saved so far. Only hourly billed servers save anything; monthly
+    /// ones are paid for either way.
+    pub fn savings(&self, prices: &PriceTable) -> f64 {
+        let now = unix_now();
+        let hourly = |plan: &Uuid| {
+            prices
+                .plan(plan)
+                .and_then(|price| price.hourly)
+                .unwrap_or_default()
+        };
+
+        let finished = self
+            .stopped_time
+            .values()
+            .filter(|time| time.billing_type == BillingType::Hourly)
+            .map(|time| hourly(&time.plan) * time.secs as f64 / 3600.0);
+        let ongoing = self
+            .stopped
+            .values()
+            .filter(|stopped| stopped.billing_type == BillingType::Hourly)
+            .map(|stopped| hourly(&stopped.plan) * (now - stopped.since).max(0) as f64 / 3600.0);
+
+        finished.chain(ongoing).sum()
+    }
+
+    /// Extrapolates [`Self::savings`] over the time the monitor has run to a
+    /// full month.
+    pub fn projected_monthly_savings(&self, prices: &PriceTable, running_for: Duration) -> f64 {
+        let hours = running_for.as_secs_f64() / 3600.0;
+        if hours <= 0.0 {
+            return 0.0;
+        }
+
+        self.savings(prices) / hours * HOURS_PER_MONTH
+    }
+
+    pub async fn run(
+        &mut self,
+        client: &Client,
+        interval: Duration,
+        mut on_event: impl FnMut(IdleEvent),
+    ) -> Result<()> {
+        poll_every!(
+            interval,
+            "idle check",
+            self.poll(client)
+                .await
+                .map(|events| events.into_iter().for_each(&mut on_event))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{player, running_server, server};
+    use crate::{InstanceStatus, InstanceStoppedReason};
+    use chrono::TimeZone;
+
+    /// Server 1 stopped by `monitor` at 1200 after being empty since 600.
+    fn stop_idle_server(monitor: &mut IdleMonitor) -> [InstanceResource; 1] {
+        let empty = [running_server(1, "Training", [])];
+        let busy = [running_server(1, "Training", [player(2, "Alpha")])];
+
+        assert!(monitor.idle_servers(&empty, 0).is_empty());
+        assert!(monitor.idle_servers(&busy, 300).is_empty());
+        assert!(monitor.idle_servers(&empty, 600).is_empty());
+        assert!(monitor.idle_servers(&empty, 1000).is_empty());
+
+        let idle = monitor.idle_servers(&empty, 1200);
+        assert_eq!(idle.len(), 1);
+        let (server, since) = idle[0];
+        assert_eq!(since, 600);
+        assert_eq!(
+            monitor.mark_stopped(server, since, 1200),
+            IdleEvent::Stopped {
+                id: Uuid::from_u128(1),
+                idle_for: Duration::from_secs(600),
+            }
+        );
+
+        let mut stopped = empty;
+        stopped[0].instance.status = InstanceStatus::ServerStopped {
+            was_error: false,
+            reason: InstanceStoppedReason::StoppedNormally,
+        };
+        stopped
+    }
+
+    fn prices() -> PriceTable {
+        let mut prices = PriceTable::default();
+        prices.plans.insert(
+            Uuid::nil(),
+            crate::PlanPrice {
+                hourly: Some(0.5),
+                ..Default::default()
+            },
+        );
+        prices
+    }
+
+    #[test]
+    fn watches_hourly_servers_by_default() {
+        let hourly = server(1, "Hourly");
+        let mut monthly = server(2, "Monthly");
+        monthly.instance.billing_type = BillingType::Monthly;
+
+        let monitor = IdleMonitor::new(Duration::from_secs(600));
+        assert!(monitor.is_watched(&hourly));
+        assert!(!monitor.is_watched(&monthly));
+
+        let monitor = monitor.include_monthly(true).servers([Uuid::from_u128(2)]);
+        assert!(!monitor.is_watched(&hourly));
+        assert!(monitor.is_watched(&monthly));
+    }
+
+    #[test]
+    fn stops_idle_servers_and_restarts_them_on_schedule() {
+        let mut monitor = IdleMonitor::new(Duration::from_secs(600))
+            .restart_on("0 4 * * *", "UTC")
+            .unwrap();
+        let stopped = stop_idle_server(&mut monitor);
+        let id = Uuid::from_u128(1);
+
+        assert!(monitor.idle_servers(&stopped, 1800).is_empty());
+
+        let night = Utc.with_ymd_and_hms(2026, 1, 15, 3, 0, 0).unwrap();
+        assert!(monitor.restarts_due(&stopped, night).unwrap().is_empty());
+        let morning = night + chrono::Duration::hours(2);
+        assert_eq!(monitor.restarts_due(&stopped, morning).unwrap(), [id]);
+
+        // Started by the monitor or someone else, either ends the stop.
+        let started = [running_server(1, "Training", [])];
+        assert!(monitor.idle_servers(&started, 4800).is_empty());
+        assert!(monitor.stopped.is_empty());
+        assert_eq!(monitor.stopped_time[&id].secs, 3600);
+    }
+
+    #[test]
+    fn keeps_stopped_servers_when_the_restart_schedule_fails() {
+        // February 30th never comes, so there is no next restart.
+        let mut monitor = IdleMonitor::new(Duration::from_secs(600))
+            .restart_on("0 4 30 2 *", "UTC")
+            .unwrap();
+        let stopped = stop_idle_server(&mut monitor);
+
+        let now = Utc.with_ymd_and_hms(2026, 1, 15, 3, 0, 0).unwrap();
+        assert!(monitor.restarts_due(&stopped, now).is_err());
+        assert!(monitor.stopped.contains_key(&Uuid::from_u128(1)));
+    }
+
+    #[test]
+    fn rejects_invalid_restart_schedules() {
+        let monitor = IdleMonitor::new(Duration::from_secs(600));
+        assert!(monitor.clone().restart_on("not cron", "UTC").is_err());
+        assert!(monitor.restart_on("0 4 * * *", "Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn counts_stopped_time_and_savings() {
+        let mut monitor = IdleMonitor::new(Duration::from_secs(600));
+        let id = Uuid::from_u128(1);
+        monitor.stopped.insert(
+            id,
+            StoppedServer {
+                since: 0,
+                plan: Uuid::nil(),
+                billing_type: BillingType::Hourly,
+            },
+        );
+
+        monitor.mark_started(&id, 7200);
+        assert!(monitor.stopped.is_empty());
+        assert_eq!(monitor.stopped_hours(), 2.0);
+        assert_eq!(monitor.savings(&prices()), 1.0);
+
+        // Starting again without a stop in between adds nothing.
+        monitor.mark_started(&id, 9000);
+        assert_eq!(monitor.stopped_hours(), 2.0);
+
+        let projected =
+            monitor.projected_monthly_savings(&prices(), Duration::from_secs(730 * 3600));
+        assert_eq!(projected, 1.0);
+        assert_eq!(
+            monitor.projected_monthly_savings(&prices(), Duration::ZERO),
+            0.0
+        );
+    }
+
+    #[test]
+    fn monthly_servers_save_nothing() {
+        let mut monitor = IdleMonitor::new(Duration::from_secs(600));
+        monitor.stopped_time.insert(
+            Uuid::from_u128(1),
+            StoppedTime {
+                plan: Uuid::nil(),
+                billing_type: BillingType::Monthly,
+                secs: 3600,
+            },
+        );
+
+        assert_eq!(monitor.stopped_hours(), 1.0);
+        assert_eq!(monitor.savings(&prices()), 0.0);
+    }
+}
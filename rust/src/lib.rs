@@ -1,8 +1,49 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::future::Future;
 use std::path::PathBuf;
+use std::pin::Pin;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use anyhow::{Ok, Result, bail};
+pub use alerts::{
+    Alert, AlertManager, AlertRule, AlertSink, AlertState, ChatSink, Condition, LogSink, Metric,
+    RestartSink,
+};
+use anyhow::{Context, Ok, Result, bail};
+pub use bot::{ChatBot, Command, CommandContext, CommandHandler, Role};
+pub use bridge::{
+    BridgeActivity, BridgeMessage, ChatBridge, ChatTransport, InMemoryTransport, WebhookTransport,
+};
+pub use events::{
+    ChannelSink, Event, EventBus, EventKind, EventSink, JsonLinesSink, StdoutSink, TimedEvent,
+};
+pub use expiry::{ExpiryEvent, ExpiryMonitor};
+#[cfg(feature = "exporter")]
+pub use exporter::MetricsExporter;
+pub use fleet::{Fleet, FleetOperation, FleetOptions, FleetReport, ServerOutcome, ServerResult};
+pub use fleet_config::{FileSync, FleetConfig, FleetPlan, PlanAction, PlannedAction, ServerSpec};
+#[cfg(feature = "history")]
+pub use history::{
+    AliasRecord, BanRecord, PingStats, PlayerHistory, PlayerSummary, SessionRecord, SlotRecord,
+};
+pub use idle::{IdleEvent, IdleMonitor};
+pub use maintenance::{
+    MaintenanceAction, MaintenanceJob, MaintenanceOutcome, MaintenanceRecord, MaintenanceScheduler,
+    PlayerPolicy,
+};
+pub use moderation::{
+    AutoModerator, ModerationAction, ModerationPolicy, NamePattern, Sanction, Violation,
+};
+pub use pricing::{CostEstimate, FleetCost, HOURS_PER_MONTH, PlanPrice, PriceTable, UptimeTracker};
+pub use query::{InstanceQuery, Instances};
+#[cfg(feature = "recorder")]
+pub use recorder::{MonthlyStats, RecordedSample, ResourceMetric, ResourceRecorder, SampleQuery};
+pub use rotation::{MissionRotation, RotationDecision, RotationEntry, RotationReason, TimeWindow};
 use serde::{Deserialize, Serialize};
+pub use simulator::{Firing, Overlap, SimulationReport, Timeline, TriggerSimulator};
+pub use trigger_sync::{TriggerChange, TriggerDiff, TriggerSet, TriggerSyncOptions};
 pub use types::billing::BillingType;
+pub use types::coalition::{Coalition, CoalitionPassword};
 pub use types::dcs_api::{
     AddMissionsResponse, BanPlayerRequest, BanPlayerResponse, DeleteMissionsResponse,
     GetPauseServerResponse, GetResumeServerResponse, KickPlayerRequest, KickPlayerResponse,
@@ -13,7 +54,7 @@ pub use types::dcs_chat::DcsChat;
 pub use types::dcs_runtime::{
     AdvancedSettings, BannedPlayer, CurrentRuntimeAction, DcsRuntime, GetMissionInfoResponse,
     GetMissionListResponse, GetPlayersResponse, GetServerSettingsResponse, Player, Players,
-    Settings,
+    ResumeMode, ServerMode, Settings,
 };
 pub use types::dcs_settings::{DcsSettings, DcsSettingsPayload, DcsSettingsUpdatePayload};
 pub use types::files::{
@@ -21,16 +62,24 @@ pub use types::files::{
 };
 pub use types::instance::{
     ApiError, GameRuntime, GameType, Instance, InstanceNodeResource, InstanceResource,
-    InstanceStatus, InstanceStoppedReason, InstancesResponse, Terrain,
+    InstanceStatus, InstanceStatusKind, InstanceStoppedReason, InstancesResponse, Terrain,
 };
 pub use types::region::Region;
+pub use types::resource_stats::{AlignedSample, ResourceSummary, SeriesStats};
+pub use types::settings_patch::{AdvancedSettingsPatch, SettingsPatch};
+pub use types::settings_validation::{IssueSeverity, SettingChange, SettingsIssue};
 pub use types::srs::{SrsClient, SrsModRequest, SrsServerInfo};
 pub use types::system_resources::{PrometheusSeries, ServerResourcesResponse};
 pub use types::system_resources_periode::SystemResourcesPeriod;
 pub use types::triggers::{
-    ComparisonOperator, CreateTriggerRequest, Trigger, TriggerAction, TriggerCondition,
+    ComparisonOperator, CreateTriggerRequest, Trigger, TriggerAction, TriggerBuilder,
+    TriggerCondition, UpdateTriggerRequest,
 };
 pub use types::webconsole::WebConsoleExecuteRequest;
+pub use webhooks::{
+    DeadLetter, EVENT_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER, Webhook, WebhookDispatcher,
+    sign_webhook, verify_webhook,
+};
 
 use reqwest::multipart::{Form, Part};
 use tokio::fs::File;
@@ -38,7 +87,66 @@ use tokio::io::AsyncWriteExt;
 
 pub use uuid::Uuid;
 
+mod alerts;
+mod bot;
+mod bridge;
+mod events;
+mod expiry;
+#[cfg(feature = "exporter")]
+mod exporter;
+mod fleet;
+mod fleet_config;
+#[cfg(feature = "history")]
+mod history;
+mod http;
+mod idle;
+mod maintenance;
+mod moderation;
+mod pricing;
+mod query;
+#[cfg(feature = "recorder")]
+mod recorder;
+mod rotation;
+mod simulator;
+#[cfg(test)]
+mod test_support;
+mod trigger_sync;
 mod types;
+mod webhooks;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Seconds since the Unix epoch.
+pub(crate) fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Parses an IANA time zone name like `Europe/Berlin`.
+pub(crate) fn parse_timezone(name: &str) -> anyhow::Result<chrono_tz::Tz> {
+    name.parse()
+        .map_err(|_| anyhow::anyhow!("unknown time zone: {name}"))
+}
+
+/// Evaluates `$poll`, a `Result`, every `$interval` until the future is
+/// dropped. Failures are logged as `{$what} failed` and polling carries on.
+/// A macro rather than a function taking a closure, so the polls can borrow
+/// their poller mutably and the `run` futures stay `Send`.
+macro_rules! poll_every {
+    ($interval:expr, $what:expr, $poll:expr $(,)?) => {
+        loop {
+            let result: anyhow::Result<_> = $poll;
+            if let Err(error) = result {
+                log::warn!("{} failed: {error}", $what);
+            }
+
+            tokio::time::sleep($interval).await;
+        }
+    };
+}
+pub(crate) use poll_every;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct CreateInstanceRequest {
@@ -72,6 +180,9 @@ impl From<DcsSettingsUpdatePayload> for EditInstanceRequest {
 pub struct Client {
     api_key: String,
     reqwest_client: reqwest::Client,
+    events: Option<tokio::sync::mpsc::UnboundedSender<Event>>,
+    patch_verify_attempts: u32,
+    patch_verify_interval: std::time::Duration,
 }
 
 impl Client {
@@ -81,6 +192,32 @@ impl Client {
         Self {
             reqwest_client: reqwest::Client::new(),
             api_key: api_key.into(),
+            events: None,
+            patch_verify_attempts: 5,
+            patch_verify_interval: std::time::Duration::from_secs(2),
+        }
+    }
+
+    /// Reports kicks, bans and mission starts made through this client, e.g.
+    /// to an [`EventBus`] via [`EventBus::actions`].
+    pub fn with_events(mut self, sender: tokio::sync::mpsc::UnboundedSender<Event>) -> Self {
+        self.events = Some(sender);
+        self
+    }
+
+    /// How often [`Client::patch_settings`] re-reads the runtime, and how
+    /// long it waits before each read, until the patch shows up. 5 times
+    /// every 2 seconds by default; 0 attempts skips the check.
+    pub fn with_patch_verification(mut self, attempts: u32, interval: std::time::Duration) -> Self {
+        self.patch_verify_attempts = attempts;
+        self.patch_verify_interval = interval;
+        self
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(events) = &self.events {
+            // Nobody listening anymore is not an error of the request.
+            let _ = events.send(event);
         }
     }
 
@@ -118,6 +255,7 @@ impl Client {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_server(
         &self,
         region: Region,
@@ -478,13 +616,20 @@ impl Client {
     }
 
     pub async fn start_mission(&self, id: &Uuid, mission_idx: i32) -> Result<StartServerResponse> {
-        self.send_json(self.reqwest_client.post(format!(
-            "{}/game_servers/{}/dcs-api/missions/{}/start",
-            Self::BASE_URL,
-            id,
-            mission_idx
-        )))
-        .await
+        let response = self
+            .send_json(self.reqwest_client.post(format!(
+                "{}/game_servers/{}/dcs-api/missions/{}/start",
+                Self::BASE_URL,
+                id,
+                mission_idx
+            )))
+            .await?;
+
+        self.emit(Event::MissionStarted {
+            instance_id: *id,
+            mission_idx,
+        });
+        Ok(response)
     }
 
     pub async fn pause_server(&self, id: &Uuid) -> Result<GetPauseServerResponse> {
@@ -522,21 +667,100 @@ impl Client {
         .await
     }
 
+    pub async fn patch_settings(
+        &self,
+        id: &Uuid,
+        patch: &SettingsPatch,
+    ) -> Result<Vec<&'static str>> {
+        let mut settings = self.get_runtime(id).await?.settings.settings;
+        let changed = patch.apply(&mut settings);
+        if changed.is_empty() {
+            return Ok(changed);
+        }
+
+        if !self.save_settings(id, &settings).await? {
+            bail!("failed to save settings");
+        }
+
+        // The runtime snapshot is refreshed asynchronously, so give it a few
+        // chances to pick up the new settings before reporting a mismatch.
+        let mut mismatched = Vec::new();
+        for _ in 0..self.patch_verify_attempts {
+            tokio::time::sleep(self.patch_verify_interval).await;
+
+            mismatched = patch.mismatches(&self.get_runtime(id).await?.settings.settings);
+            if mismatched.is_empty() {
+                return Ok(changed);
+            }
+        }
+
+        if mismatched.is_empty() {
+            return Ok(changed);
+        }
+        bail!("settings were not applied: {}", mismatched.join(", "))
+    }
+
+    pub async fn validate_settings(
+        &self,
+        id: &Uuid,
+        settings: &Settings,
+    ) -> Result<Vec<SettingsIssue>> {
+        let mut issues = settings.validate();
+        let mut listings: HashMap<&str, FileListResponse> = HashMap::new();
+
+        for mission in &settings.mission_list {
+            let (directory, file_name) = match mission.rfind(['/', '\\']) {
+                Some(index) => (&mission[..index], &mission[index + 1..]),
+                None => ("", mission.as_str()),
+            };
+
+            let listing = match listings.entry(directory) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(
+                    self.list_files(id, directory)
+                        .await
+                        .with_context(|| format!("failed to list missions in {directory}"))?,
+                ),
+            };
+            let exists = listing
+                .files
+                .iter()
+                .any(|file| !file.is_directory && file.name == file_name);
+
+            if !exists {
+                issues.push(SettingsIssue::error(
+                    "mission_list",
+                    format!("mission {mission} does not exist on the server"),
+                ));
+            }
+        }
+
+        Ok(issues)
+    }
+
     pub async fn kick_player(
         &self,
         id: &Uuid,
         request: &KickPlayerRequest,
     ) -> Result<KickPlayerResponse> {
-        self.send_json(
-            self.reqwest_client
-                .post(format!(
-                    "{}/game_servers/{}/dcs-api/kick",
-                    Self::BASE_URL,
-                    id
-                ))
-                .json(request),
-        )
-        .await
+        let response = self
+            .send_json(
+                self.reqwest_client
+                    .post(format!(
+                        "{}/game_servers/{}/dcs-api/kick",
+                        Self::BASE_URL,
+                        id
+                    ))
+                    .json(request),
+            )
+            .await?;
+
+        self.emit(Event::PlayerKicked {
+            instance_id: *id,
+            player_id: request.id,
+            reason: request.reason.clone(),
+        });
+        Ok(response)
     }
 
     pub async fn ban_player(
@@ -544,16 +768,26 @@ impl Client {
         id: &Uuid,
         request: &BanPlayerRequest,
     ) -> Result<BanPlayerResponse> {
-        self.send_json(
-            self.reqwest_client
-                .post(format!(
-                    "{}/game_servers/{}/dcs-api/ban",
-                    Self::BASE_URL,
-                    id
-                ))
-                .json(request),
-        )
-        .await
+        let response = self
+            .send_json(
+                self.reqwest_client
+                    .post(format!(
+                        "{}/game_servers/{}/dcs-api/ban",
+                        Self::BASE_URL,
+                        id
+                    ))
+                    .json(request),
+            )
+            .await?;
+
+        self.emit(Event::PlayerBanned {
+            instance_id: *id,
+            player_id: request.id,
+            ucid: request.ucid.clone(),
+            reason: request.reason.clone(),
+            period: request.period,
+        });
+        Ok(response)
     }
 
     pub async fn send_chat(
@@ -649,6 +883,80 @@ impl Client {
         .await
     }
 
+    pub async fn get_trigger(&self, id: &Uuid, trigger_id: &Uuid) -> Result<Trigger> {
+        self.send_json(self.reqwest_client.get(format!(
+            "{}/game_servers/{}/triggers/{}",
+            Self::BASE_URL,
+            id,
+            trigger_id
+        )))
+        .await
+    }
+
+    /// Replaces the trigger in place, keeping its id and execution history.
+    pub async fn update_trigger(
+        &self,
+        id: &Uuid,
+        trigger_id: &Uuid,
+        request: &CreateTriggerRequest,
+    ) -> Result<Trigger> {
+        self.send_json(
+            self.reqwest_client
+                .put(format!(
+                    "{}/game_servers/{}/triggers/{}",
+                    Self::BASE_URL,
+                    id,
+                    trigger_id
+                ))
+                .json(request),
+        )
+        .await
+    }
+
+    pub async fn patch_trigger(
+        &self,
+        id: &Uuid,
+        trigger_id: &Uuid,
+        request: &UpdateTriggerRequest,
+    ) -> Result<Trigger> {
+        self.send_json(
+            self.reqwest_client
+                .patch(format!(
+                    "{}/game_servers/{}/triggers/{}",
+                    Self::BASE_URL,
+                    id,
+                    trigger_id
+                ))
+                .json(request),
+        )
+        .await
+    }
+
+    pub async fn enable_trigger(&self, id: &Uuid, trigger_id: &Uuid) -> Result<Trigger> {
+        self.set_trigger_enabled(id, trigger_id, true).await
+    }
+
+    pub async fn disable_trigger(&self, id: &Uuid, trigger_id: &Uuid) -> Result<Trigger> {
+        self.set_trigger_enabled(id, trigger_id, false).await
+    }
+
+    async fn set_trigger_enabled(
+        &self,
+        id: &Uuid,
+        trigger_id: &Uuid,
+        enabled: bool,
+    ) -> Result<Trigger> {
+        self.patch_trigger(
+            id,
+            trigger_id,
+            &UpdateTriggerRequest {
+                enabled: Some(enabled),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
     pub async fn delete_trigger(&self, id: &Uuid, trigger_id: &Uuid) -> Result<()> {
         self.send_unit(
             self.reqwest_client.delete(format!(
@@ -661,4 +969,29 @@ impl Client {
         )
         .await
     }
+
+    /// Brings the server's triggers in line with `desired`, matching them by
+    /// name. Missing triggers are created and changed ones updated in place;
+    /// undeclared ones and extra copies of declared ones are only deleted
+    /// with [`TriggerSyncOptions::prune`]. Returns the changes, which are not
+    /// applied on a dry run. Changes that fail don't stop the others and are
+    /// listed in [`TriggerDiff::failed`].
+    pub async fn sync_triggers(
+        &self,
+        id: &Uuid,
+        desired: &[CreateTriggerRequest],
+        options: &TriggerSyncOptions,
+    ) -> Result<TriggerDiff> {
+        trigger_sync::validate(desired)?;
+
+        let existing = self.list_triggers(id).await?;
+        let mut diff = TriggerDiff::between(*id, &existing, desired, options.prune);
+        if !options.dry_run
+            && let Err(error) = diff.apply(self).await
+        {
+            log::warn!("{error}");
+        }
+
+        Ok(diff)
+    }
 }
@@ -0,0 +1,553 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use crate::{Client, InstanceResource, InstanceStatusKind, SendChatRequest, parse_timezone};
+
+/// Default number of records a [`MaintenanceScheduler`] keeps.
+const MAX_RECORDS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MaintenanceAction {
+    Restart,
+    FullRestart,
+}
+
+impl fmt::Display for MaintenanceAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaintenanceAction::Restart => write!(f, "restart"),
+            MaintenanceAction::FullRestart => write!(f, "full restart"),
+        }
+    }
+}
+
+/// What to do when players are still connected at the scheduled time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerPolicy {
+    /// Run regardless of the player count.
+    Always,
+    /// Skip this occurrence when more than `max_players` are connected.
+    Skip { max_players: usize },
+    /// Check again every `retry` until at most `max_players` are connected,
+    /// giving up after `max_delay`. The final warning is sent again before a
+    /// postponed action runs.
+    Postpone {
+        max_players: usize,
+        retry: Duration,
+        max_delay: Duration,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaintenanceOutcome {
+    Completed,
+    Skipped { reason: String },
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceRecord {
+    pub job: String,
+    pub server: Uuid,
+    pub action: MaintenanceAction,
+    pub scheduled_for: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Time spent waiting for players to leave.
+    pub postponed: Duration,
+    pub outcome: MaintenanceOutcome,
+}
+
+impl fmt::Display for MaintenanceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} {} scheduled for {}: ",
+            self.job, self.action, self.server, self.scheduled_for
+        )?;
+
+        match &self.outcome {
+            MaintenanceOutcome::Completed if self.postponed.is_zero() => write!(f, "completed"),
+            MaintenanceOutcome::Completed => write!(
+                f,
+                "completed after postponing {}s",
+                self.postponed.as_secs()
+            ),
+            MaintenanceOutcome::Skipped { reason } => write!(f, "skipped ({reason})"),
+            MaintenanceOutcome::Failed(error) => write!(f, "failed ({error})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MaintenanceJob {
+    name: String,
+    servers: Vec<Uuid>,
+    schedule: Cron,
+    timezone: Tz,
+    action: MaintenanceAction,
+    warnings: Vec<Duration>,
+    message: String,
+    policy: PlayerPolicy,
+}
+
+impl MaintenanceJob {
+    /// `cron_expression` uses the five field format of trigger schedules, with
+    /// an optional leading seconds field. It is evaluated in UTC unless a
+    /// [`Self::timezone`] is set.
+    pub fn new(
+        name: impl Into<String>,
+        cron_expression: &str,
+        servers: impl IntoIterator<Item = Uuid>,
+    ) -> Result<Self> {
+        let schedule = Cron::from_str(cron_expression)
+            .with_context(|| format!("invalid cron expression: {cron_expression}"))?;
+
+        Ok(Self {
+            name: name.into(),
+            servers: servers.into_iter().collect(),
+            schedule,
+            timezone: Tz::UTC,
+            action: MaintenanceAction::Restart,
+            warnings: [30, 10, 5, 1]
+                .into_iter()
+                .map(|minutes| Duration::from_secs(minutes * 60))
+                .collect(),
+            message: "Server {action} in {minutes} minute(s).".to_string(),
+            policy: PlayerPolicy::Always,
+        })
+    }
+
+    pub fn timezone(mut self, name: &str) -> Result<Self> {
+        self.timezone = parse_timezone(name)?;
+        Ok(self)
+    }
+
+    pub fn action(mut self, action: MaintenanceAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Lead times of the in-game warnings before the scheduled time.
+    pub fn warnings(mut self, warnings: Vec<Duration>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// `{action}` and `{minutes}` are replaced in the warning message.
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    pub fn player_policy(mut self, policy: PlayerPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn next_run(&self, after: DateTime<Utc>) -> Result<DateTime<Utc>> {
+        let next = self
+            .schedule
+            .find_next_occurrence(&after.with_timezone(&self.timezone), false)?;
+        Ok(next.with_timezone(&Utc))
+    }
+
+    /// Warns and runs the action on every server of the job for one occurrence.
+    pub async fn execute(&self, client: &Client, at: DateTime<Utc>) -> Vec<MaintenanceRecord> {
+        let mut tasks = JoinSet::new();
+        for (index, id) in self.servers.iter().enumerate() {
+            let job = self.clone();
+            let client = client.clone();
+            let id = *id;
+            tasks.spawn(async move { (index, job.execute_on(&client, id, at).await) });
+        }
+
+        let mut records: Vec<_> = tasks.join_all().await;
+        records.sort_by_key(|(index, _)| *index);
+        records.into_iter().map(|(_, record)| record).collect()
+    }
+
+    async fn execute_on(&self, client: &Client, id: Uuid, at: DateTime<Utc>) -> MaintenanceRecord {
+        let mut warnings = self.warnings.clone();
+        warnings.sort_unstable_by(|a, b| b.cmp(a));
+        let mut warned = false;
+
+        for lead_time in warnings {
+            let Ok(lead_time_delta) = chrono::Duration::from_std(lead_time) else {
+                continue;
+            };
+            let warn_at = at - lead_time_delta;
+            if warn_at < Utc::now() {
+                continue;
+            }
+
+            sleep_until(warn_at).await;
+            // Failing to look the server up is reported when the action runs.
+            let skip_reason = match client.get_server(&id).await {
+                Ok(server) => self.skip_reason(&server),
+                Err(_) => None,
+            };
+            match skip_reason {
+                Some(reason) => log::info!("not warning {id} about {}: {reason}", self.action),
+                None => {
+                    self.warn(client, id, lead_time).await;
+                    warned = true;
+                }
+            }
+        }
+
+        sleep_until(at).await;
+        let (outcome, postponed) = self.run_action(client, id, warned).await;
+
+        MaintenanceRecord {
+            job: self.name.clone(),
+            server: id,
+            action: self.action,
+            scheduled_for: at,
+            finished_at: Utc::now(),
+            postponed,
+            outcome,
+        }
+    }
+
+    fn warning_message(&self, lead_time: Duration) -> String {
+        self.message
+            .replace("{action}", &self.action.to_string())
+            .replace("{minutes}", &lead_time.as_secs().div_ceil(60).to_string())
+    }
+
+    fn cancellation_message(&self) -> String {
+        format!("Scheduled {} cancelled.", self.action)
+    }
+
+    /// Why the action would be skipped on `server` right now. Postponing is
+    /// not a reason, players may still leave in time.
+    fn skip_reason(&self, server: &InstanceResource) -> Option<String> {
+        let status = server.instance.status.kind();
+        if status != InstanceStatusKind::ServerStarted {
+            return Some(format!("server is {status:?}"));
+        }
+
+        let players = server.player_count();
+        match self.policy {
+            PlayerPolicy::Skip { max_players } if players > max_players => {
+                Some(format!("{players} players connected"))
+            }
+            _ => None,
+        }
+    }
+
+    async fn warn(&self, client: &Client, id: Uuid, lead_time: Duration) {
+        self.post(client, id, self.warning_message(lead_time)).await;
+    }
+
+    async fn post(&self, client: &Client, id: Uuid, msg: String) {
+        if let Err(error) = client
+            .send_chat(&id, &SendChatRequest { all: true, msg })
+            .await
+        {
+            log::warn!("failed to post maintenance message on {id}: {error}");
+        }
+    }
+
+    /// Players who were warned are told when the action is skipped on a
+    /// running server.
+    async fn run_action(
+        &self,
+        client: &Client,
+        id: Uuid,
+        warned: bool,
+    ) -> (MaintenanceOutcome, Duration) {
+        let mut postponed = Duration::ZERO;
+
+        loop {
+            let server = match client.get_server(&id).await {
+                Ok(server) => server,
+                Err(error) => return (MaintenanceOutcome::Failed(error.to_string()), postponed),
+            };
+
+            if let Some(reason) = self.skip_reason(&server) {
+                if warned && server.instance.status.kind() == InstanceStatusKind::ServerStarted {
+                    self.post(client, id, self.cancellation_message()).await;
+                }
+                return (MaintenanceOutcome::Skipped { reason }, postponed);
+            }
+
+            let players = server.player_count();
+            match self.policy {
+                PlayerPolicy::Postpone {
+                    max_players,
+                    retry,
+                    max_delay,
+                } if players > max_players => {
+                    if postponed + retry > max_delay {
+                        let reason = format!(
+                            "{players} players still connected after {}s",
+                            postponed.as_secs()
+                        );
+                        if warned {
+                            self.post(client, id, self.cancellation_message()).await;
+                        }
+                        return (MaintenanceOutcome::Skipped { reason }, postponed);
+                    }
+
+                    tokio::time::sleep(retry).await;
+                    postponed += retry;
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Players who joined while the action was postponed have not seen
+            // the warnings yet.
+            if !postponed.is_zero()
+                && let Some(&lead_time) = self.warnings.iter().min()
+            {
+                self.warn(client, id, lead_time).await;
+                tokio::time::sleep(lead_time).await;
+            }
+
+            let result = match self.action {
+                MaintenanceAction::Restart => client.restart_server(&id).await,
+                MaintenanceAction::FullRestart => client.full_restart_server(&id).await,
+            };
+
+            let outcome = match result {
+                Ok(_) => MaintenanceOutcome::Completed,
+                Err(error) => MaintenanceOutcome::Failed(error.to_string()),
+            };
+            return (outcome, postponed);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MaintenanceScheduler {
+    client: Client,
+    jobs: Vec<MaintenanceJob>,
+    records: Arc<Mutex<VecDeque<MaintenanceRecord>>>,
+    max_records: usize,
+}
+
+impl MaintenanceScheduler {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            jobs: Vec::new(),
+            records: Arc::default(),
+            max_records: MAX_RECORDS,
+        }
+    }
+
+    pub fn job(mut self, job: MaintenanceJob) -> Self {
+        self.jobs.push(job);
+        self
+    }
+
+    /// How many of the latest records to keep, 1000 by default.
+    pub fn max_records(mut self, max_records: usize) -> Self {
+        self.max_records = max_records;
+        self
+    }
+
+    pub fn jobs(&self) -> &[MaintenanceJob] {
+        &self.jobs
+    }
+
+    /// Outcomes of the latest occurrences, oldest first.
+    pub fn records(&self) -> Vec<MaintenanceRecord> {
+        self.records
+            .lock()
+            .map(|records| records.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Runs every job on its schedule until one of them can no longer compute
+    /// a next occurrence.
+    pub async fn run(&self) -> Result<()> {
+        let mut tasks = JoinSet::new();
+
+        for job in self.jobs.clone() {
+            let client = self.client.clone();
+            let records = self.records.clone();
+            let max_records = self.max_records;
+
+            tasks.spawn(async move {
+                let mut after = Utc::now();
+                loop {
+                    let at = job.next_run(after)?;
+                    for record in job.execute(&client, at).await {
+                        log::info!("{record}");
+                        if let Ok(mut records) = records.lock() {
+                            push_record(&mut records, record, max_records);
+                        }
+                    }
+                    after = at.max(Utc::now());
+                }
+            });
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            let result: Result<()> = joined?;
+            result?;
+        }
+
+        Ok(())
+    }
+}
+
+fn push_record(
+    records: &mut VecDeque<MaintenanceRecord>,
+    record: MaintenanceRecord,
+    max_records: usize,
+) {
+    records.push_back(record);
+    while records.len() > max_records {
+        records.pop_front();
+    }
+}
+
+async fn sleep_until(at: DateTime<Utc>) {
+    if let Ok(delay) = (at - Utc::now()).to_std() {
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InstanceStatus;
+
+    fn job() -> MaintenanceJob {
+        MaintenanceJob::new("nightly", "0 4 * * *", [Uuid::from_u128(1)]).unwrap()
+    }
+
+    fn record(job: &str, outcome: MaintenanceOutcome, postponed: u64) -> MaintenanceRecord {
+        let at = DateTime::from_timestamp(0, 0).unwrap();
+        MaintenanceRecord {
+            job: job.to_string(),
+            server: Uuid::nil(),
+            action: MaintenanceAction::Restart,
+            scheduled_for: at,
+            finished_at: at,
+            postponed: Duration::from_secs(postponed),
+            outcome,
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        assert!(MaintenanceJob::new("broken", "61 * * * *", []).is_err());
+    }
+
+    #[test]
+    fn skips_stopped_servers_and_busy_servers_under_skip() {
+        use crate::test_support::{player, running_server};
+
+        let busy = running_server(1, "Training", [player(2, "Alpha"), player(3, "Bravo")]);
+        let mut stopped = running_server(1, "Training", []);
+        stopped.instance.status = InstanceStatus::ServerExpired;
+
+        let skip = job().player_policy(PlayerPolicy::Skip { max_players: 1 });
+        assert_eq!(
+            skip.skip_reason(&busy).as_deref(),
+            Some("2 players connected")
+        );
+        assert_eq!(
+            skip.skip_reason(&stopped).as_deref(),
+            Some("server is ServerExpired")
+        );
+
+        let postpone = job().player_policy(PlayerPolicy::Postpone {
+            max_players: 1,
+            retry: Duration::from_secs(60),
+            max_delay: Duration::from_secs(600),
+        });
+        assert_eq!(postpone.skip_reason(&busy), None);
+        assert_eq!(job().skip_reason(&busy), None);
+        assert_eq!(skip.cancellation_message(), "Scheduled restart cancelled.");
+    }
+
+    #[test]
+    fn next_run_uses_the_timezone() {
+        let after = DateTime::parse_from_rfc3339("2026-01-15T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            job().next_run(after).unwrap().to_rfc3339(),
+            "2026-01-16T04:00:00+00:00"
+        );
+        assert_eq!(
+            job()
+                .timezone("Europe/Berlin")
+                .unwrap()
+                .next_run(after)
+                .unwrap()
+                .to_rfc3339(),
+            "2026-01-16T03:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn formats_warnings() {
+        let job = job().action(MaintenanceAction::FullRestart);
+
+        assert_eq!(
+            job.warning_message(Duration::from_secs(300)),
+            "Server full restart in 5 minute(s)."
+        );
+        assert_eq!(
+            job.message("{action} soon ({minutes}m)")
+                .warning_message(Duration::from_secs(30)),
+            "full restart soon (1m)"
+        );
+    }
+
+    #[test]
+    fn formats_records() {
+        let completed = record("nightly", MaintenanceOutcome::Completed, 0).to_string();
+        assert!(completed.starts_with("[nightly] restart "));
+        assert!(completed.ends_with(": completed"));
+
+        let postponed = record("nightly", MaintenanceOutcome::Completed, 120).to_string();
+        assert!(postponed.ends_with(": completed after postponing 120s"));
+
+        let skipped = MaintenanceOutcome::Skipped {
+            reason: "3 players connected".to_string(),
+        };
+        assert!(
+            record("nightly", skipped, 0)
+                .to_string()
+                .ends_with(": skipped (3 players connected)")
+        );
+    }
+
+    #[test]
+    fn keeps_the_latest_records() {
+        let mut records = VecDeque::new();
+        for job in ["a", "b", "c"] {
+            push_record(
+                &mut records,
+                record(job, MaintenanceOutcome::Completed, 0),
+                2,
+            );
+        }
+
+        let jobs: Vec<_> = records.iter().map(|record| record.job.as_str()).collect();
+        assert_eq!(jobs, ["b", "c"]);
+    }
+}
@@ -0,0 +1,594 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use uuid::Uuid;
+
+use crate::{
+    BanPlayerRequest, Client, DcsChat, DcsRuntime, KickPlayerRequest, Player, SendChatRequest,
+    poll_every,
+};
+
+#[derive(Debug, Clone)]
+pub enum ModerationPolicy {
+    /// Falls back to `AdvancedSettings.max_ping` when `limit` is `None`.
+    MaxPing {
+        limit: Option<i32>,
+        samples: u32,
+    },
+    BannedWords {
+        words: Vec<String>,
+    },
+    NameBlocklist {
+        patterns: Vec<NamePattern>,
+    },
+    TeamKills {
+        max_reports: u32,
+    },
+    Whitelist {
+        ucids: HashSet<String>,
+    },
+}
+
+/// A regular expression matched against player names, parsed from a string.
+#[derive(Debug, Clone)]
+pub struct NamePattern(Regex);
+
+impl NamePattern {
+    fn is_match(&self, name: &str) -> bool {
+        self.0.is_match(name)
+    }
+}
+
+impl FromStr for NamePattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Regex::new(s)
+            .map(Self)
+            .with_context(|| format!("invalid name pattern: {s}"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sanction {
+    Warn,
+    Kick,
+    Ban { period: Duration },
+}
+
+impl fmt::Display for Sanction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sanction::Warn => write!(f, "warn"),
+            Sanction::Kick => write!(f, "kick"),
+            Sanction::Ban { period } => write!(f, "ban for {}s", period.as_secs()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub instance_id: Uuid,
+    pub player: Player,
+    pub reason: String,
+    /// Bypasses the escalation ladder, e.g. for whitelist violations.
+    pub sanction: Option<Sanction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModerationAction {
+    pub instance_id: Uuid,
+    pub ucid: String,
+    pub player_name: String,
+    pub sanction: Sanction,
+    pub reason: String,
+    pub dry_run: bool,
+}
+
+#[derive(Debug)]
+pub struct AutoModerator {
+    policies: Vec<ModerationPolicy>,
+    escalation: Vec<Sanction>,
+    exempt: HashSet<String>,
+    dry_run: bool,
+    offences: HashMap<String, usize>,
+    ping_samples: HashMap<(Uuid, String), u32>,
+    team_kills: HashMap<(Uuid, String), u32>,
+    last_chat_ids: HashMap<Uuid, i32>,
+}
+
+impl Default for AutoModerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AutoModerator {
+    pub fn new() -> Self {
+        Self {
+            policies: Vec::new(),
+            escalation: vec![
+                Sanction::Warn,
+                Sanction::Kick,
+                Sanction::Ban {
+                    period: Duration::from_secs(24 * 60 * 60),
+                },
+            ],
+            exempt: HashSet::new(),
+            dry_run: false,
+            offences: HashMap::new(),
+            ping_samples: HashMap::new(),
+            team_kills: HashMap::new(),
+            last_chat_ids: HashMap::new(),
+        }
+    }
+
+    pub fn policy(mut self, policy: ModerationPolicy) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    pub fn escalation(mut self, steps: Vec<Sanction>) -> Self {
+        self.escalation = steps;
+        self
+    }
+
+    pub fn exempt(mut self, ucid: impl Into<String>) -> Self {
+        self.exempt.insert(ucid.into());
+        self
+    }
+
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Records a team kill reported by a webconsole hook. Reports are evaluated
+    /// on the next runtime inspection.
+    pub fn report_team_kill(&mut self, id: &Uuid, ucid: impl Into<String>) {
+        *self.team_kills.entry((*id, ucid.into())).or_default() += 1;
+    }
+
+    pub fn offences(&self, ucid: &str) -> usize {
+        self.offences.get(ucid).copied().unwrap_or_default()
+    }
+
+    pub fn forgive(&mut self, ucid: &str) {
+        self.offences.remove(ucid);
+    }
+
+    pub fn inspect_runtime(&mut self, id: &Uuid, runtime: &DcsRuntime) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let players: Vec<_> = self.moderated_players(runtime).cloned().collect();
+
+        self.ping_samples.retain(|(instance, ucid), _| {
+            instance != id || players.iter().any(|p| &p.ucid == ucid)
+        });
+
+        for policy in &self.policies {
+            for player in &players {
+                let reason = match policy {
+                    ModerationPolicy::MaxPing { limit, samples } => {
+                        let limit = limit.unwrap_or(runtime.settings.settings.advanced.max_ping);
+                        let key = (*id, player.ucid.clone());
+
+                        if limit <= 0 || player.ping <= limit {
+                            self.ping_samples.remove(&key);
+                            None
+                        } else {
+                            let count = self.ping_samples.entry(key.clone()).or_default();
+                            *count += 1;
+
+                            if *count >= *samples {
+                                self.ping_samples.remove(&key);
+                                Some(format!("ping above {limit}ms"))
+                            } else {
+                                None
+                            }
+                        }
+                    }
+                    ModerationPolicy::NameBlocklist { patterns } => patterns
+                        .iter()
+                        .find(|pattern| pattern.is_match(&player.name))
+                        .map(|_| "name not allowed".to_string()),
+                    ModerationPolicy::TeamKills { max_reports } => {
+                        let key = (*id, player.ucid.clone());
+                        match self.team_kills.get(&key) {
+                            Some(count) if count >= max_reports => {
+                                self.team_kills.remove(&key);
+                                Some(format!("{max_reports} team kills"))
+                            }
+                            _ => None,
+                        }
+                    }
+                    ModerationPolicy::Whitelist { ucids } => {
+                        if !ucids.contains(&player.ucid) {
+                            violations.push(Violation {
+                                instance_id: *id,
+                                player: player.clone(),
+                                reason: "private session".to_string(),
+                                sanction: Some(Sanction::Kick),
+                            });
+                        }
+                        None
+                    }
+                    ModerationPolicy::BannedWords { .. } => None,
+                };
+
+                if let Some(reason) = reason {
+                    violations.push(Violation {
+                        instance_id: *id,
+                        player: player.clone(),
+                        reason,
+                        sanction: None,
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+
+    pub fn inspect_chat(
+        &mut self,
+        id: &Uuid,
+        chat: &[DcsChat],
+        runtime: &DcsRuntime,
+    ) -> Vec<Violation> {
+        let last_id = self.last_chat_ids.get(id).copied();
+        let newest = chat.iter().map(|message| message.id).max().unwrap_or(0);
+        self.last_chat_ids
+            .insert(*id, newest.max(last_id.unwrap_or(newest)));
+
+        // The first call only establishes where to resume from.
+        let Some(last_id) = last_id else {
+            return Vec::new();
+        };
+
+        let words: Vec<String> = self
+            .policies
+            .iter()
+            .filter_map(|policy| match policy {
+                ModerationPolicy::BannedWords { words } => Some(words),
+                _ => None,
+            })
+            .flatten()
+            .map(|word| word.to_lowercase())
+            .collect();
+
+        if words.is_empty() {
+            return Vec::new();
+        }
+
+        chat.iter()
+            .filter(|message| message.id > last_id && !message.is_historical)
+            .filter_map(|message| {
+                let player = self
+                    .moderated_players(runtime)
+                    .find(|player| player.id == message.player_id)?;
+                let text = message.message.to_lowercase();
+                let word = words.iter().find(|word| {
+                    text.split(|c: char| !c.is_alphanumeric())
+                        .any(|token| token == word.as_str())
+                })?;
+
+                Some(Violation {
+                    instance_id: *id,
+                    player: player.clone(),
+                    reason: format!("banned word \"{word}\""),
+                    sanction: None,
+                })
+            })
+            .collect()
+    }
+
+    /// The sanction the player's next offence earns on the escalation ladder.
+    pub fn next_sanction(&self, ucid: &str) -> Option<Sanction> {
+        let step = self
+            .offences(ucid)
+            .min(self.escalation.len().saturating_sub(1));
+        self.escalation.get(step).cloned()
+    }
+
+    /// Carries out sanctions for `violations`. A player only moves up the
+    /// escalation ladder once a sanction was actually carried out, so dry
+    /// runs and failed kicks or bans do not escalate. Failures are logged and
+    /// left out of the returned actions.
+    pub async fn enforce(
+        &mut self,
+        client: &Client,
+        violations: Vec<Violation>,
+    ) -> Result<Vec<ModerationAction>> {
+        let mut actions = Vec::with_capacity(violations.len());
+
+        for violation in violations {
+            let escalates = violation.sanction.is_none();
+            let Some(sanction) = violation
+                .sanction
+                .or_else(|| self.next_sanction(&violation.player.ucid))
+            else {
+                continue;
+            };
+
+            let action = ModerationAction {
+                instance_id: violation.instance_id,
+                ucid: violation.player.ucid.clone(),
+                player_name: violation.player.name.clone(),
+                sanction,
+                reason: violation.reason,
+                dry_run: self.dry_run,
+            };
+
+            if self.dry_run {
+                log::info!(
+                    "[dry-run] would {} {} ({}) on {}: {}",
+                    action.sanction,
+                    action.player_name,
+                    action.ucid,
+                    action.instance_id,
+                    action.reason
+                );
+            } else if let Err(error) = execute(client, &violation.player, &action).await {
+                log::warn!(
+                    "failed to {} {} ({}) on {}: {error}",
+                    action.sanction,
+                    action.player_name,
+                    action.ucid,
+                    action.instance_id
+                );
+                continue;
+            } else if escalates {
+                *self.offences.entry(action.ucid.clone()).or_default() += 1;
+            }
+
+            actions.push(action);
+        }
+
+        Ok(actions)
+    }
+
+    pub async fn poll(&mut self, client: &Client, id: &Uuid) -> Result<Vec<ModerationAction>> {
+        let runtime = client.get_runtime(id).await?;
+        let mut violations = self.inspect_runtime(id, &runtime);
+
+        if self
+            .policies
+            .iter()
+            .any(|policy| matches!(policy, ModerationPolicy::BannedWords { .. }))
+        {
+            let chat = client.get_chat(id).await?;
+            violations.extend(self.inspect_chat(id, &chat, &runtime));
+        }
+
+        self.enforce(client, violations).await
+    }
+
+    pub async fn run(&mut self, client: &Client, ids: &[Uuid], interval: Duration) -> Result<()> {
+        poll_every!(interval, "auto moderator poll", {
+            for id in ids {
+                if let Err(error) = self.poll(client, id).await {
+                    log::warn!("auto moderator poll failed for {id}: {error}");
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn moderated_players<'a>(
+        &'a self,
+        runtime: &'a DcsRuntime,
+    ) -> impl Iterator<Item = &'a Player> {
+        runtime
+            .players
+            .players
+            .clients()
+            .filter(|player| !self.exempt.contains(&player.ucid))
+    }
+}
+
+async fn execute(client: &Client, player: &Player, action: &ModerationAction) -> Result<()> {
+    match &action.sanction {
+        Sanction::Warn => {
+            client
+                .send_chat(
+                    &action.instance_id,
+                    &SendChatRequest {
+                        all: true,
+                        msg: format!("{}: warning, {}", player.name, action.reason),
+                    },
+                )
+                .await
+        }
+        Sanction::Kick => client
+            .kick_player(
+                &action.instance_id,
+                &KickPlayerRequest {
+                    id: player.id,
+                    reason: action.reason.clone(),
+                },
+            )
+            .await
+            .map(|_| ()),
+        Sanction::Ban { period } => client
+            .ban_player(
+                &action.instance_id,
+                &BanPlayerRequest {
+                    id: player.id,
+                    reason: action.reason.clone(),
+                    ucid: player.ucid.clone(),
+                    period: period.as_secs() as i64,
+                },
+            )
+            .await
+            .map(|_| ()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{chat, player, runtime};
+
+    fn violation(name: &str) -> Violation {
+        Violation {
+            instance_id: Uuid::nil(),
+            player: player(2, name),
+            reason: "test".to_string(),
+            sanction: None,
+        }
+    }
+
+    #[test]
+    fn default_has_the_escalation_ladder() {
+        let moderator = AutoModerator::default();
+
+        assert_eq!(moderator.next_sanction("ucid"), Some(Sanction::Warn));
+    }
+
+    #[test]
+    fn escalation_stops_at_the_last_step() {
+        let mut moderator = AutoModerator::new().escalation(vec![Sanction::Warn, Sanction::Kick]);
+
+        for expected in [Sanction::Warn, Sanction::Kick, Sanction::Kick] {
+            assert_eq!(moderator.next_sanction("ucid"), Some(expected));
+            *moderator.offences.entry("ucid".to_string()).or_default() += 1;
+        }
+
+        moderator.forgive("ucid");
+        assert_eq!(moderator.next_sanction("ucid"), Some(Sanction::Warn));
+        assert_eq!(
+            AutoModerator::new()
+                .escalation(Vec::new())
+                .next_sanction("ucid"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn dry_run_does_not_escalate() {
+        let mut moderator = AutoModerator::new().dry_run(true);
+        let client = Client::new("key");
+
+        for _ in 0..2 {
+            let actions = moderator
+                .enforce(&client, vec![violation("Viper")])
+                .await
+                .unwrap();
+            assert_eq!(actions.len(), 1);
+            assert_eq!(actions[0].sanction, Sanction::Warn);
+            assert!(actions[0].dry_run);
+        }
+        assert_eq!(moderator.offences("ucid-Viper"), 0);
+    }
+
+    #[tokio::test]
+    async fn explicit_sanctions_bypass_the_ladder() {
+        let mut moderator = AutoModerator::new().dry_run(true);
+        let mut violation = violation("Viper");
+        violation.sanction = Some(Sanction::Kick);
+
+        let actions = moderator
+            .enforce(&Client::new("key"), vec![violation])
+            .await
+            .unwrap();
+
+        assert_eq!(actions[0].sanction, Sanction::Kick);
+    }
+
+    #[test]
+    fn max_ping_needs_consecutive_samples() {
+        let id = Uuid::nil();
+        let mut moderator = AutoModerator::new().policy(ModerationPolicy::MaxPing {
+            limit: Some(200),
+            samples: 2,
+        });
+        let mut laggy = player(2, "Laggy");
+        laggy.ping = 400;
+
+        assert!(
+            moderator
+                .inspect_runtime(&id, &runtime([laggy.clone()]))
+                .is_empty()
+        );
+        assert!(
+            moderator
+                .inspect_runtime(&id, &runtime([player(2, "Laggy")]))
+                .is_empty()
+        );
+        assert!(
+            moderator
+                .inspect_runtime(&id, &runtime([laggy.clone()]))
+                .is_empty()
+        );
+
+        let violations = moderator.inspect_runtime(&id, &runtime([laggy]));
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].reason, "ping above 200ms");
+    }
+
+    #[test]
+    fn runtime_policies_skip_exempt_players_and_the_server() {
+        let id = Uuid::nil();
+        let mut moderator = AutoModerator::new()
+            .policy(ModerationPolicy::NameBlocklist {
+                patterns: vec!["(?i)admin|server".parse().unwrap()],
+            })
+            .policy(ModerationPolicy::Whitelist {
+                ucids: HashSet::from(["ucid-Friend".to_string()]),
+            })
+            .exempt("ucid-Admin");
+        let runtime = runtime([
+            player(2, "Admin"),
+            player(3, "Friend"),
+            player(4, "Stranger"),
+        ]);
+
+        let violations = moderator.inspect_runtime(&id, &runtime);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].player.name, "Stranger");
+        assert_eq!(violations[0].sanction, Some(Sanction::Kick));
+    }
+
+    #[test]
+    fn team_kill_reports_are_counted_per_player() {
+        let id = Uuid::nil();
+        let mut moderator =
+            AutoModerator::new().policy(ModerationPolicy::TeamKills { max_reports: 2 });
+        let runtime = runtime([player(2, "Viper")]);
+
+        moderator.report_team_kill(&id, "ucid-Viper");
+        assert!(moderator.inspect_runtime(&id, &runtime).is_empty());
+
+        moderator.report_team_kill(&id, "ucid-Viper");
+        assert_eq!(moderator.inspect_runtime(&id, &runtime).len(), 1);
+        assert!(moderator.inspect_runtime(&id, &runtime).is_empty());
+    }
+
+    #[test]
+    fn banned_words_match_whole_words_after_the_first_poll() {
+        let id = Uuid::nil();
+        let mut moderator = AutoModerator::new().policy(ModerationPolicy::BannedWords {
+            words: vec!["Noob".to_string()],
+        });
+        let runtime = runtime([player(2, "Viper")]);
+
+        let backlog = [chat(1, 2, "noob")];
+        assert!(moderator.inspect_chat(&id, &backlog, &runtime).is_empty());
+
+        let chat = [
+            chat(1, 2, "noob"),
+            chat(2, 2, "snoobish"),
+            chat(3, 2, "what a NOOB!"),
+        ];
+        let violations = moderator.inspect_chat(&id, &chat, &runtime);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].reason, "banned word \"noob\"");
+        assert!(moderator.inspect_chat(&id, &chat, &runtime).is_empty());
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{BillingType, Client, InstanceResource, InstanceStatusKind, unix_now};
+
+/// Average hours in a month (8760 / 12).
+pub const HOURS_PER_MONTH: f64 = 730.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct PlanPrice {
+    pub name: Option<String>,
+    pub hourly: Option<f64>,
+    pub monthly: Option<f64>,
+}
+
+/// Prices per plan, keyed by the plan (product) id used in `create_server`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PriceTable {
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[serde(default)]
+    pub plans: HashMap<Uuid, PlanPrice>,
+}
+
+fn default_currency() -> String {
+    "EUR".to_string()
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        Self {
+            currency: default_currency(),
+            plans: HashMap::new(),
+        }
+    }
+}
+
+impl PriceTable {
+    pub fn from_toml_str(source: &str) -> Result<Self> {
+        Ok(toml::from_str(source)?)
+    }
+
+    pub fn from_json_str(source: &str) -> Result<Self> {
+        Ok(serde_json::from_str(source)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml_str(&source),
+            Some("json") => Self::from_json_str(&source),
+            _ => bail!("unsupported price table format: {}", path.display()),
+        }
+    }
+
+    pub fn plan(&self, plan: &Uuid) -> Option<&PlanPrice> {
+        self.plans.get(plan)
+    }
+
+    /// `usage` is the fraction of time the server runs, between 0 and 1.
+    pub fn estimate(&self, server: &InstanceResource, usage: f64) -> CostEstimate {
+        let instance = &server.instance;
+        let usage = usage.clamp(0.0, 1.0);
+        let price = self.plan(&instance.product_id);
+
+        let hourly = price
+            .and_then(|price| price.hourly)
+            .map(|hourly| hourly * HOURS_PER_MONTH * usage);
+        let monthly = price.and_then(|price| price.monthly);
+
+        let current = match instance.billing_type {
+            BillingType::Hourly => hourly,
+            BillingType::Monthly => monthly,
+        };
+        let recommendation = match (hourly, monthly) {
+            (Some(hourly), Some(monthly)) if hourly < monthly => Some(BillingType::Hourly),
+            (Some(_), Some(_)) => Some(BillingType::Monthly),
+            _ => None,
+        };
+
+        CostEstimate {
+            id: instance.id,
+            name: server.name().map(str::to_string),
+            plan: instance.product_id,
+            billing_type: instance.billing_type.clone(),
+            usage,
+            hourly,
+            monthly,
+            current,
+            recommendation,
+        }
+    }
+
+    pub fn estimate_fleet(
+        &self,
+        servers: &[InstanceResource],
+        uptime: &UptimeTracker,
+    ) -> FleetCost {
+        let now = unix_now();
+
+        FleetCost {
+            currency: self.currency.clone(),
+            servers: servers
+                .iter()
+                .filter(|server| server.instance.status.kind() != InstanceStatusKind::ServerDeleted)
+                .map(|server| self.estimate(server, uptime.usage(server, now)))
+                .collect(),
+        }
+    }
+}
+
+/// Monthly cost of one instance under both billing types.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CostEstimate {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub plan: Uuid,
+    pub billing_type: BillingType,
+    pub usage: f64,
+    /// Hourly price times the observed usage over a month.
+    pub hourly: Option<f64>,
+    pub monthly: Option<f64>,
+    /// Cost under the current billing type.
+    pub current: Option<f64>,
+    pub recommendation: Option<BillingType>,
+}
+
+impl CostEstimate {
+    /// How much switching to the recommended billing type would save a month.
+    pub fn savings(&self) -> f64 {
+        let recommended = match self.recommendation {
+            Some(BillingType::Hourly) => self.hourly,
+            Some(BillingType::Monthly) => self.monthly,
+            None => None,
+        };
+
+        match (self.current, recommended) {
+            (Some(current), Some(recommended)) => (current - recommended).max(0.0),
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FleetCost {
+    pub currency: String,
+    pub servers: Vec<CostEstimate>,
+}
+
+impl FleetCost {
+    pub fn total(&self) -> f64 {
+        self.servers
+            .iter()
+            .filter_map(|server| server.current)
+            .sum()
+    }
+
+    pub fn savings(&self) -> f64 {
+        self.servers.iter().map(CostEstimate::savings).sum()
+    }
+
+    /// Servers whose plan is missing from the price table.
+    pub fn unpriced(&self) -> impl Iterator<Item = &CostEstimate> {
+        self.servers
+            .iter()
+            .filter(|server| server.current.is_none())
+    }
+}
+
+impl fmt::Display for FleetCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let currency = &self.currency;
+
+        for server in &self.servers {
+            let name = server.name.as_deref().unwrap_or("<unnamed>");
+            match server.current {
+                Some(cost) => write!(
+                    f,
+                    "{name} ({}, {:.0}% usage): {cost:.2} {currency}",
+                    server.billing_type,
+                    server.usage * 100.0
+                )?,
+                None => write!(f, "{name}: no price for plan {}", server.plan)?,
+            }
+
+            if let Some(recommendation) = &server.recommendation
+                && *recommendation != server.billing_type
+            {
+                write!(
+                    f,
+                    ", switch to {recommendation} to save {:.2} {currency}",
+                    server.savings()
+                )?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "total: {:.2} {currency} / month", self.total())?;
+        if self.savings() > 0.0 {
+            writeln!(
+                f,
+                "potential savings: {:.2} {currency} / month",
+                self.savings()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+struct UptimeRecord {
+    last_seen: i64,
+    running: bool,
+    observed_secs: u64,
+    running_secs: u64,
+}
+
+/// Accumulates how long each instance runs from periodic status observations.
+/// Serializable so the history survives restarts of the tracking process.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct UptimeTracker {
+    records: HashMap<Uuid, UptimeRecord>,
+}
+
+impl UptimeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&mut self, servers: &[InstanceResource], now: i64) {
+        for server in servers {
+            let running = is_billed(server.instance.status.kind());
+            let record = self
+                .records
+                .entry(server.instance.id)
+                .or_insert(UptimeRecord {
+                    last_seen: now,
+                    running,
+                    observed_secs: 0,
+                    running_secs: 0,
+                });
+
+            let elapsed = (now - record.last_seen).max(0) as u64;
+            record.observed_secs += elapsed;
+            if record.running {
+                record.running_secs += elapsed;
+            }
+            record.last_seen = now;
+            record.running = running;
+        }
+    }
+
+    pub async fn poll(&mut self, client: &Client) -> Result<()> {
+        let servers = client.get_servers().await?;
+        self.observe(&servers, unix_now());
+        Ok(())
+    }
+
+    /// Fraction of observed time the instance was running. Without any
+    /// history the current status is assumed to have held since `rented_at`.
+    pub fn usage(&self, server: &InstanceResource, now: i64) -> f64 {
+        match self.records.get(&server.instance.id) {
+            Some(record) if record.observed_secs > 0 => {
+                record.running_secs as f64 / record.observed_secs as f64
+            }
+            _ if server.instance.rented_at >= now => 0.0,
+            _ if is_billed(server.instance.status.kind()) => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+fn is_billed(status: InstanceStatusKind) -> bool {
+    !matches!(
+        status,
+        InstanceStatusKind::ServerStopped
+            | InstanceStatusKind::ServerExpired
+            | InstanceStatusKind::ServerDeleted
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::server;
+    use crate::{InstanceStatus, InstanceStoppedReason};
+
+    fn plan() -> Uuid {
+        Uuid::from_u128(42)
+    }
+
+    fn prices() -> PriceTable {
+        PriceTable::from_toml_str(&format!(
+            r#"
+            currency = "USD"
+
+            [plans.{}]
+            name = "Standard"
+            hourly = 0.1
+            monthly = 50.0
+            "#,
+            plan()
+        ))
+        .unwrap()
+    }
+
+    fn priced_server(id: u128, billing_type: BillingType) -> InstanceResource {
+        let mut server = server(id, "Training");
+        server.instance.product_id = plan();
+        server.instance.billing_type = billing_type;
+        server
+    }
+
+    fn stop(server: &mut InstanceResource) {
+        server.instance.status = InstanceStatus::ServerStopped {
+            was_error: false,
+            reason: InstanceStoppedReason::StoppedNormally,
+        };
+    }
+
+    #[test]
+    fn parses_price_tables() {
+        let prices = prices();
+        assert_eq!(prices.currency, "USD");
+        assert_eq!(prices.plan(&plan()).unwrap().monthly, Some(50.0));
+
+        let json = PriceTable::from_json_str("{}").unwrap();
+        assert_eq!(json, PriceTable::default());
+    }
+
+    #[test]
+    fn recommends_the_cheaper_billing_type() {
+        let prices = prices();
+
+        // 0.1 * 730 = 73 a month when always on.
+        let busy = prices.estimate(&priced_server(1, BillingType::Hourly), 1.0);
+        assert_eq!(busy.recommendation, Some(BillingType::Monthly));
+        assert!((busy.savings() - 23.0).abs() < 1e-9);
+
+        let quiet = prices.estimate(&priced_server(1, BillingType::Hourly), 0.5);
+        assert_eq!(quiet.recommendation, Some(BillingType::Hourly));
+        assert_eq!(quiet.savings(), 0.0);
+
+        let monthly = prices.estimate(&priced_server(1, BillingType::Monthly), 0.5);
+        assert_eq!(monthly.current, Some(50.0));
+        assert!((monthly.savings() - 13.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unknown_plans_are_unpriced() {
+        let prices = prices();
+        let servers = [priced_server(1, BillingType::Monthly), server(2, "Other")];
+
+        let cost = FleetCost {
+            currency: prices.currency.clone(),
+            servers: servers
+                .iter()
+                .map(|server| prices.estimate(server, 1.0))
+                .collect(),
+        };
+
+        assert_eq!(cost.total(), 50.0);
+        let unpriced: Vec<_> = cost.unpriced().map(|server| server.id).collect();
+        assert_eq!(unpriced, [Uuid::from_u128(2)]);
+        assert!(cost.to_string().contains("Other: no price for plan"));
+    }
+
+    #[test]
+    fn tracks_running_time() {
+        let mut tracker = UptimeTracker::new();
+        let mut server = priced_server(1, BillingType::Hourly);
+
+        tracker.observe(std::slice::from_ref(&server), 0);
+        tracker.observe(std::slice::from_ref(&server), 300);
+        stop(&mut server);
+        tracker.observe(std::slice::from_ref(&server), 600);
+        tracker.observe(std::slice::from_ref(&server), 1200);
+
+        // Running until the stop was observed at 600.
+        assert_eq!(tracker.usage(&server, 1200), 0.5);
+    }
+
+    #[test]
+    fn usage_without_history_follows_the_status() {
+        let tracker = UptimeTracker::new();
+        let mut server = priced_server(1, BillingType::Hourly);
+
+        assert_eq!(tracker.usage(&server, 100), 1.0);
+        assert_eq!(tracker.usage(&server, 0), 0.0);
+        stop(&mut server);
+        assert_eq!(tracker.usage(&server, 100), 0.0);
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    BillingType, Client, Fleet, InstanceResource, InstanceStatusKind, InstancesResponse, Region,
+    Terrain,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstanceQuery {
+    regions: Vec<Region>,
+    statuses: Vec<InstanceStatusKind>,
+    billing_types: Vec<BillingType>,
+    terrains: Vec<Terrain>,
+    mods: Vec<String>,
+    min_players: Option<usize>,
+    max_players: Option<usize>,
+    rented_until_before: Option<i64>,
+    name_contains: Option<String>,
+}
+
+impl InstanceQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Matches any of the given regions when called more than once.
+    pub fn region(mut self, region: Region) -> Self {
+        self.regions.push(region);
+        self
+    }
+
+    /// Matches any of the given status kinds when called more than once.
+    pub fn status(mut self, status: InstanceStatusKind) -> Self {
+        self.statuses.push(status);
+        self
+    }
+
+    pub fn billing_type(mut self, billing_type: BillingType) -> Self {
+        self.billing_types.push(billing_type);
+        self
+    }
+
+    /// Requires every given terrain to be installed.
+    pub fn terrain(mut self, terrain: Terrain) -> Self {
+        self.terrains.push(terrain);
+        self
+    }
+
+    /// Requires every given mod to be active.
+    pub fn active_mod(mut self, name: impl Into<String>) -> Self {
+        self.mods.push(name.into());
+        self
+    }
+
+    pub fn min_players(mut self, players: usize) -> Self {
+        self.min_players = Some(players);
+        self
+    }
+
+    pub fn max_players(mut self, players: usize) -> Self {
+        self.max_players = Some(players);
+        self
+    }
+
+    pub fn rented_until_before(mut self, timestamp: i64) -> Self {
+        self.rented_until_before = Some(timestamp);
+        self
+    }
+
+    /// Case-insensitive match against the server name.
+    pub fn name_contains(mut self, name: impl Into<String>) -> Self {
+        self.name_contains = Some(name.into().to_lowercase());
+        self
+    }
+
+    pub fn matches(&self, server: &InstanceResource) -> bool {
+        let instance = &server.instance;
+
+        (self.regions.is_empty() || self.regions.contains(&server.node.region))
+            && (self.statuses.is_empty() || self.statuses.contains(&instance.status.kind()))
+            && (self.billing_types.is_empty()
+                || self.billing_types.contains(&instance.billing_type))
+            && self
+                .terrains
+                .iter()
+                .all(|terrain| instance.wanted_terrains.contains(terrain))
+            && self
+                .mods
+                .iter()
+                .all(|name| instance.active_mods.contains(name))
+            && self
+                .min_players
+                .is_none_or(|min| server.player_count() >= min)
+            && self
+                .max_players
+                .is_none_or(|max| server.player_count() <= max)
+            && self.rented_until_before.is_none_or(|timestamp| {
+                instance
+                    .rented_until
+                    .is_some_and(|rented_until| rented_until < timestamp)
+            })
+            && self.name_contains.as_ref().is_none_or(|needle| {
+                server
+                    .name()
+                    .is_some_and(|name| name.to_lowercase().contains(needle))
+            })
+    }
+
+    pub fn apply(&self, servers: InstancesResponse) -> Instances {
+        Instances(
+            servers
+                .into_iter()
+                .filter(|server| self.matches(server))
+                .collect(),
+        )
+    }
+
+    pub async fn fetch(&self, client: &Client) -> Result<Instances> {
+        Ok(self.apply(client.get_servers().await?))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Instances(Vec<InstanceResource>);
+
+impl Instances {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, InstanceResource> {
+        self.0.iter()
+    }
+
+    pub fn ids(&self) -> Vec<Uuid> {
+        self.0.iter().map(|server| server.instance.id).collect()
+    }
+
+    pub fn into_inner(self) -> Vec<InstanceResource> {
+        self.0
+    }
+
+    pub fn filter(self, predicate: impl Fn(&InstanceResource) -> bool) -> Self {
+        Self(
+            self.0
+                .into_iter()
+                .filter(|server| predicate(server))
+                .collect(),
+        )
+    }
+
+    pub fn sort_by_key<K: Ord>(mut self, key: impl FnMut(&InstanceResource) -> K) -> Self {
+        self.0.sort_by_key(key);
+        self
+    }
+
+    pub fn sort_by_name(self) -> Self {
+        self.sort_by_key(|server| server.name().map(str::to_lowercase))
+    }
+
+    /// Busiest servers first.
+    pub fn sort_by_players(self) -> Self {
+        self.sort_by_key(|server| std::cmp::Reverse(server.player_count()))
+    }
+
+    /// Servers expiring first come first; servers without an end date last.
+    pub fn sort_by_rented_until(self) -> Self {
+        self.sort_by_key(|server| server.instance.rented_until.unwrap_or(i64::MAX))
+    }
+
+    pub fn group_by<K: Eq + Hash>(
+        self,
+        mut key: impl FnMut(&InstanceResource) -> K,
+    ) -> HashMap<K, Instances> {
+        let mut groups: HashMap<K, Instances> = HashMap::new();
+        for server in self.0 {
+            groups.entry(key(&server)).or_default().0.push(server);
+        }
+        groups
+    }
+
+    pub fn group_by_region(self) -> HashMap<Region, Instances> {
+        self.group_by(|server| server.node.region.clone())
+    }
+
+    pub fn group_by_status(self) -> HashMap<InstanceStatusKind, Instances> {
+        self.group_by(|server| server.instance.status.kind())
+    }
+
+    pub fn group_by_billing_type(self) -> HashMap<BillingType, Instances> {
+        self.group_by(|server| server.instance.billing_type.clone())
+    }
+
+    pub fn into_fleet(self, client: Client) -> Fleet {
+        Fleet::new(client, self.ids())
+    }
+}
+
+impl IntoIterator for Instances {
+    type Item = InstanceResource;
+    type IntoIter = std::vec::IntoIter<InstanceResource>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Instances {
+    type Item = &'a InstanceResource;
+    type IntoIter = std::slice::Iter<'a, InstanceResource>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl FromIterator<InstanceResource> for Instances {
+    fn from_iter<I: IntoIterator<Item = InstanceResource>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InstanceStatus;
+    use crate::test_support::{player, running_server, server};
+
+    fn servers() -> InstancesResponse {
+        let mut alpha = running_server(1, "Alpha Training", [player(2, "a"), player(3, "b")]);
+        alpha.instance.wanted_terrains = vec![Terrain::Caucasus, Terrain::Syria];
+        alpha.instance.active_mods = vec!["srs".to_string()];
+        alpha.instance.rented_until = Some(200);
+
+        let mut bravo = running_server(2, "Bravo", [player(2, "c")]);
+        bravo.node.region = Region::USA;
+        bravo.instance.billing_type = BillingType::Monthly;
+        bravo.instance.rented_until = Some(100);
+
+        let mut charlie = server(3, "charlie training");
+        charlie.instance.status = InstanceStatus::ServerExpired;
+
+        vec![alpha, bravo, charlie]
+    }
+
+    fn names(instances: &Instances) -> Vec<&str> {
+        instances
+            .iter()
+            .map(|server| server.name().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert_eq!(InstanceQuery::new().apply(servers()).len(), 3);
+    }
+
+    #[test]
+    fn filters_combine() {
+        let apply = |query: InstanceQuery| names(&query.apply(servers())).join(", ");
+
+        assert_eq!(
+            apply(InstanceQuery::new().name_contains("TRAINING")),
+            "Alpha Training, charlie training"
+        );
+        assert_eq!(
+            apply(
+                InstanceQuery::new()
+                    .region(Region::Germany)
+                    .status(InstanceStatusKind::ServerStarted)
+            ),
+            "Alpha Training"
+        );
+        assert_eq!(
+            apply(
+                InstanceQuery::new()
+                    .terrain(Terrain::Caucasus)
+                    .terrain(Terrain::Syria)
+                    .active_mod("srs")
+            ),
+            "Alpha Training"
+        );
+        assert_eq!(apply(InstanceQuery::new().terrain(Terrain::Kola)), "");
+        assert_eq!(
+            apply(InstanceQuery::new().min_players(1).max_players(1)),
+            "Bravo"
+        );
+        assert_eq!(
+            apply(InstanceQuery::new().rented_until_before(150)),
+            "Bravo"
+        );
+        assert_eq!(
+            apply(
+                InstanceQuery::new()
+                    .billing_type(BillingType::Hourly)
+                    .billing_type(BillingType::Monthly)
+                    .region(Region::USA)
+            ),
+            "Bravo"
+        );
+    }
+
+    #[test]
+    fn sorts_and_groups() {
+        let instances = InstanceQuery::new().apply(servers());
+
+        assert_eq!(
+            names(&instances.clone().sort_by_players()),
+            ["Alpha Training", "Bravo", "charlie training"]
+        );
+        assert_eq!(
+            names(&instances.clone().sort_by_rented_until()),
+            ["Bravo", "Alpha Training", "charlie training"]
+        );
+        assert_eq!(
+            names(&instances.clone().sort_by_name()),
+            ["Alpha Training", "Bravo", "charlie training"]
+        );
+
+        let by_region = instances.clone().group_by_region();
+        assert_eq!(by_region[&Region::Germany].len(), 2);
+        assert_eq!(by_region[&Region::USA].ids(), [Uuid::from_u128(2)]);
+
+        let by_status = instances.group_by_status();
+        assert_eq!(by_status[&InstanceStatusKind::ServerExpired].len(), 1);
+    }
+}
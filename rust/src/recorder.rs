@@ -0,0 +1,555 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Datelike, Utc};
+use rusqlite::{Connection, params};
+use uuid::Uuid;
+
+use crate::{
+    Client, InstanceResource, InstanceStatusKind, PrometheusSeries, SeriesStats,
+    ServerResourcesResponse, SystemResourcesPeriod, poll_every,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS instances (
+    instance_id TEXT PRIMARY KEY,
+    plan TEXT NOT NULL,
+    name TEXT,
+    max_ram INTEGER NOT NULL,
+    last_recorded INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS resource_samples (
+    instance_id TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    series INTEGER NOT NULL,
+    timestamp_ms INTEGER NOT NULL,
+    value REAL NOT NULL,
+    PRIMARY KEY (instance_id, metric, series, timestamp_ms)
+);
+CREATE INDEX IF NOT EXISTS resource_samples_time ON resource_samples (timestamp_ms);
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceMetric {
+    Cpu,
+    Ram,
+    Players,
+    ServerFps,
+}
+
+impl ResourceMetric {
+    pub const ALL: [ResourceMetric; 4] = [
+        ResourceMetric::Cpu,
+        ResourceMetric::Ram,
+        ResourceMetric::Players,
+        ResourceMetric::ServerFps,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResourceMetric::Cpu => "cpu",
+            ResourceMetric::Ram => "ram",
+            ResourceMetric::Players => "players",
+            ResourceMetric::ServerFps => "server_fps",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|metric| metric.as_str() == value)
+    }
+
+    fn series<'a>(&self, resources: &'a ServerResourcesResponse) -> &'a [PrometheusSeries] {
+        match self {
+            ResourceMetric::Cpu => &resources.cpus,
+            ResourceMetric::Ram => &resources.ram,
+            ResourceMetric::Players => &resources.players,
+            ResourceMetric::ServerFps => &resources.server_fps,
+        }
+    }
+}
+
+impl fmt::Display for ResourceMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedSample {
+    pub instance_id: Uuid,
+    pub metric: ResourceMetric,
+    /// Index of the series within the metric, e.g. the CPU.
+    pub series: i64,
+    pub time: SystemTime,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonthlyStats {
+    pub year: i32,
+    pub month: u32,
+    pub stats: SeriesStats,
+}
+
+/// Which samples to read back from the recorder.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SampleQuery {
+    pub instance_id: Option<Uuid>,
+    /// Only instances created with this plan.
+    pub plan: Option<Uuid>,
+    pub metric: Option<ResourceMetric>,
+    pub from: Option<SystemTime>,
+    pub to: Option<SystemTime>,
+}
+
+/// Persists `get_server_resources` samples so history outlives the week the
+/// API keeps. Polling more often than the fetched period leaves no gaps;
+/// overlapping samples are stored once.
+#[derive(Debug)]
+pub struct ResourceRecorder {
+    connection: Connection,
+    period: SystemResourcesPeriod,
+}
+
+impl ResourceRecorder {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection,
+            period: SystemResourcesPeriod::Hour,
+        })
+    }
+
+    /// Period fetched on every poll, `Hour` by default.
+    pub fn period(mut self, period: SystemResourcesPeriod) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// Stores the samples of `server`, returning how many were new.
+    pub fn record(
+        &mut self,
+        server: &InstanceResource,
+        resources: &ServerResourcesResponse,
+    ) -> Result<usize> {
+        let instance = server.instance.id.to_string();
+        let tx = self.connection.transaction()?;
+        let mut inserted = 0;
+
+        tx.execute(
+            "INSERT INTO instances (instance_id, plan, name, max_ram, last_recorded)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (instance_id) DO UPDATE SET
+                plan = excluded.plan, name = excluded.name,
+                max_ram = excluded.max_ram, last_recorded = excluded.last_recorded",
+            params![
+                instance,
+                server.instance.product_id.to_string(),
+                server.name(),
+                resources.max_ram as i64,
+                unix_millis(SystemTime::now()),
+            ],
+        )?;
+
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR IGNORE INTO resource_samples
+                 (instance_id, metric, series, timestamp_ms, value) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+
+            for metric in ResourceMetric::ALL {
+                for (series, samples) in metric.series(resources).iter().enumerate() {
+                    for (time, value) in samples.samples() {
+                        inserted += insert.execute(params![
+                            instance,
+                            metric.as_str(),
+                            series as i64,
+                            unix_millis(time),
+                            value,
+                        ])?;
+                    }
+                }
+            }
+        }
+
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    pub async fn poll(&mut self, client: &Client) -> Result<usize> {
+        let servers = client.get_servers().await?;
+        let mut inserted = 0;
+
+        for server in servers {
+            if server.instance.status.kind() != InstanceStatusKind::ServerStarted {
+                continue;
+            }
+
+            let id = server.instance.id;
+            match client.get_server_resources(&id, self.period.clone()).await {
+                Ok(resources) => inserted += self.record(&server, &resources)?,
+                Err(error) => log::warn!("failed to read resources of {id}: {error}"),
+            }
+        }
+
+        Ok(inserted)
+    }
+
+    pub async fn run(&mut self, client: &Client, interval: Duration) -> Result<()> {
+        poll_every!(interval, "resource recording", self.poll(client).await)
+    }
+
+    /// Samples matching `query`, ordered by instance, metric, series and time.
+    pub fn samples(&self, query: &SampleQuery) -> Result<Vec<RecordedSample>> {
+        let mut statement = self.connection.prepare(
+            "SELECT s.instance_id, s.metric, s.series, s.timestamp_ms, s.value
+             FROM resource_samples s JOIN instances i ON i.instance_id = s.instance_id
+             WHERE (?1 IS NULL OR s.instance_id = ?1)
+               AND (?2 IS NULL OR i.plan = ?2)
+               AND (?3 IS NULL OR s.metric = ?3)
+               AND (?4 IS NULL OR s.timestamp_ms >= ?4)
+               AND (?5 IS NULL OR s.timestamp_ms <= ?5)
+             ORDER BY s.instance_id, s.metric, s.series, s.timestamp_ms",
+        )?;
+
+        let rows = statement.query_map(
+            params![
+                query.instance_id.map(|id| id.to_string()),
+                query.plan.map(|plan| plan.to_string()),
+                query.metric.map(|metric| metric.as_str()),
+                query.from.map(unix_millis),
+                query.to.map(unix_millis),
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, f64>(4)?,
+                ))
+            },
+        )?;
+
+        let mut samples = Vec::new();
+        for row in rows {
+            let (instance_id, metric, series, timestamp_ms, value) = row?;
+            let Some(metric) = ResourceMetric::from_str(&metric) else {
+                bail!("unknown metric {metric} in recorder database");
+            };
+
+            samples.push(RecordedSample {
+                instance_id: instance_id.parse()?,
+                metric,
+                series,
+                time: UNIX_EPOCH + Duration::from_millis(timestamp_ms.max(0) as u64),
+                value,
+            });
+        }
+
+        Ok(samples)
+    }
+
+    /// Statistics of `metric` per calendar month (UTC) across every instance
+    /// created with `plan`, for month-over-month capacity planning. Series of an
+    /// instance are merged the same way as [`ServerResourcesResponse`]:
+    /// CPU and FPS averaged, RAM and players summed.
+    pub fn monthly_stats(&self, plan: &Uuid, metric: ResourceMetric) -> Result<Vec<MonthlyStats>> {
+        let samples = self.samples(&SampleQuery {
+            plan: Some(*plan),
+            metric: Some(metric),
+            ..Default::default()
+        })?;
+
+        let mut merged: BTreeMap<(Uuid, SystemTime), (f64, usize)> = BTreeMap::new();
+        for sample in samples {
+            let entry = merged.entry((sample.instance_id, sample.time)).or_default();
+            entry.0 += sample.value;
+            entry.1 += 1;
+        }
+
+        let mut months: BTreeMap<(i32, u32), Vec<f64>> = BTreeMap::new();
+        for ((_, time), (sum, count)) in merged {
+            let value = match metric {
+                ResourceMetric::Cpu | ResourceMetric::ServerFps => sum / count as f64,
+                ResourceMetric::Ram | ResourceMetric::Players => sum,
+            };
+            let time = DateTime::<Utc>::from(time);
+            months
+                .entry((time.year(), time.month()))
+                .or_default()
+                .push(value);
+        }
+
+        Ok(months
+            .into_iter()
+            .filter_map(|((year, month), values)| {
+                Some(MonthlyStats {
+                    year,
+                    month,
+                    stats: SeriesStats::from_values(values)?,
+                })
+            })
+            .collect())
+    }
+
+    /// Writes the samples matching `query` as CSV, returning the row count.
+    pub fn export_csv(&self, query: &SampleQuery, mut writer: impl Write) -> Result<usize> {
+        let samples = self.samples(query)?;
+
+        writeln!(writer, "instance_id,metric,series,timestamp,value")?;
+        for sample in &samples {
+            let timestamp = sample
+                .time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            writeln!(
+                writer,
+                "{},{},{},{timestamp},{}",
+                sample.instance_id, sample.metric, sample.series, sample.value
+            )?;
+        }
+
+        Ok(samples.len())
+    }
+
+    /// Writes the samples matching `query` as a Parquet file, returning the
+    /// row count.
+    #[cfg(feature = "parquet")]
+    pub fn export_parquet(&self, query: &SampleQuery, writer: impl Write + Send) -> Result<usize> {
+        use std::sync::Arc;
+
+        use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+
+        const PARQUET_SCHEMA: &str = "
+            message resource_sample {
+                REQUIRED BYTE_ARRAY instance_id (UTF8);
+                REQUIRED BYTE_ARRAY metric (UTF8);
+                REQUIRED INT64 series;
+                REQUIRED INT64 timestamp (TIMESTAMP(MILLIS,true));
+                REQUIRED DOUBLE value;
+            }
+        ";
+
+        let samples = self.samples(query)?;
+        let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+        let mut file = SerializedFileWriter::new(
+            writer,
+            schema,
+            Arc::new(WriterProperties::builder().build()),
+        )?;
+
+        let strings = |value: fn(&RecordedSample) -> String| -> Vec<ByteArray> {
+            samples
+                .iter()
+                .map(|sample| ByteArray::from(value(sample).into_bytes()))
+                .collect()
+        };
+        let instance_ids = strings(|sample| sample.instance_id.to_string());
+        let metrics = strings(|sample| sample.metric.to_string());
+        let series: Vec<i64> = samples.iter().map(|sample| sample.series).collect();
+        let timestamps: Vec<i64> = samples
+            .iter()
+            .map(|sample| unix_millis(sample.time))
+            .collect();
+        let values: Vec<f64> = samples.iter().map(|sample| sample.value).collect();
+
+        let mut row_group = file.next_row_group()?;
+        let mut index = 0;
+        while let Some(mut column) = row_group.next_column()? {
+            match index {
+                0 => {
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&instance_ids, None, None)?;
+                }
+                1 => {
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&metrics, None, None)?;
+                }
+                2 => {
+                    column
+                        .typed::<Int64Type>()
+                        .write_batch(&series, None, None)?;
+                }
+                3 => {
+                    column
+                        .typed::<Int64Type>()
+                        .write_batch(&timestamps, None, None)?;
+                }
+                _ => {
+                    column
+                        .typed::<DoubleType>()
+                        .write_batch(&values, None, None)?;
+                }
+            }
+            column.close()?;
+            index += 1;
+        }
+        row_group.close()?;
+        file.close()?;
+
+        Ok(samples.len())
+    }
+}
+
+fn unix_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::server;
+
+    // 2026-01-31T23:59:00Z and the minute after.
+    const JANUARY: f64 = 1_769_903_940.0;
+    const FEBRUARY: f64 = JANUARY + 60.0;
+
+    fn series(values: &[(f64, &str)]) -> PrometheusSeries {
+        PrometheusSeries {
+            values: values
+                .iter()
+                .map(|(time, value)| (*time, value.to_string()))
+                .collect(),
+        }
+    }
+
+    fn resources() -> ServerResourcesResponse {
+        ServerResourcesResponse {
+            cpus: vec![
+                series(&[(JANUARY, "10"), (FEBRUARY, "20")]),
+                series(&[(JANUARY, "30"), (FEBRUARY, "40")]),
+            ],
+            ram: vec![series(&[(JANUARY, "1024")])],
+            max_ram: 4096,
+            players: vec![series(&[(JANUARY, "4"), (FEBRUARY, "6")])],
+            server_fps: Vec::new(),
+        }
+    }
+
+    fn recorder() -> ResourceRecorder {
+        let mut recorder = ResourceRecorder::open_in_memory().unwrap();
+        let mut server = server(1, "Training");
+        server.instance.product_id = Uuid::from_u128(42);
+        recorder.record(&server, &resources()).unwrap();
+        recorder
+    }
+
+    #[test]
+    fn stores_overlapping_samples_once() {
+        let mut recorder = recorder();
+        assert_eq!(
+            recorder
+                .record(&server(1, "Training"), &resources())
+                .unwrap(),
+            0
+        );
+
+        let all = recorder.samples(&SampleQuery::default()).unwrap();
+        assert_eq!(all.len(), 7);
+    }
+
+    #[test]
+    fn filters_samples() {
+        let recorder = recorder();
+
+        let cpu = recorder
+            .samples(&SampleQuery {
+                metric: Some(ResourceMetric::Cpu),
+                from: Some(UNIX_EPOCH + Duration::from_secs_f64(FEBRUARY)),
+                ..Default::default()
+            })
+            .unwrap();
+        let values: Vec<_> = cpu
+            .iter()
+            .map(|sample| (sample.series, sample.value))
+            .collect();
+        assert_eq!(values, [(0, 20.0), (1, 40.0)]);
+
+        let other_plan = SampleQuery {
+            plan: Some(Uuid::from_u128(7)),
+            ..Default::default()
+        };
+        assert!(recorder.samples(&other_plan).unwrap().is_empty());
+    }
+
+    #[test]
+    fn merges_series_per_month() {
+        let recorder = recorder();
+        let plan = Uuid::from_u128(42);
+
+        let cpu = recorder.monthly_stats(&plan, ResourceMetric::Cpu).unwrap();
+        let months: Vec<_> = cpu
+            .iter()
+            .map(|month| (month.year, month.month, month.stats.mean))
+            .collect();
+        assert_eq!(months, [(2026, 1, 20.0), (2026, 2, 30.0)]);
+
+        let players = recorder
+            .monthly_stats(&plan, ResourceMetric::Players)
+            .unwrap();
+        assert_eq!(players[1].stats.max, 6.0);
+    }
+
+    #[test]
+    fn exports_csv() {
+        let recorder = recorder();
+        let mut csv = Vec::new();
+
+        let rows = recorder
+            .export_csv(
+                &SampleQuery {
+                    metric: Some(ResourceMetric::Ram),
+                    ..Default::default()
+                },
+                &mut csv,
+            )
+            .unwrap();
+
+        assert_eq!(rows, 1);
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            format!(
+                "instance_id,metric,series,timestamp,value\n{},ram,0,{JANUARY},1024\n",
+                Uuid::from_u128(1)
+            )
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn exports_parquet() {
+        let recorder = recorder();
+        let mut file = Vec::new();
+
+        let rows = recorder
+            .export_parquet(&SampleQuery::default(), &mut file)
+            .unwrap();
+
+        assert_eq!(rows, 7);
+        assert!(file.starts_with(b"PAR1") && file.ends_with(b"PAR1"));
+    }
+}
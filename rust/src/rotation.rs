@@ -0,0 +1,467 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use uuid::Uuid;
+
+use crate::types::dcs_runtime::mission_file_name;
+use crate::{Client, Coalition, CurrentRuntimeAction, DcsRuntime, parse_timezone, poll_every};
+
+/// A daily time range, e.g. `18:00-23:00`. Ranges may wrap past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    pub fn new(start: NaiveTime, end: NaiveTime) -> Self {
+        Self { start, end }
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// The date the window containing `time` on `date` opened on.
+    fn opened_on(&self, date: NaiveDate, time: NaiveTime) -> NaiveDate {
+        if self.start > self.end && time < self.end {
+            date.pred_opt().unwrap_or(date)
+        } else {
+            date
+        }
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| anyhow!("expected HH:MM-HH:MM, got {s}"))?;
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .with_context(|| format!("invalid time in window {s}"))
+        };
+
+        Ok(Self::new(parse(start)?, parse(end)?))
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotationEntry {
+    mission: String,
+    window: Option<TimeWindow>,
+    max_mission_time: Option<Duration>,
+    advance_on_result: bool,
+}
+
+impl RotationEntry {
+    /// `mission` is a file name from the server's mission list.
+    pub fn new(mission: impl Into<String>) -> Self {
+        Self {
+            mission: mission.into(),
+            window: None,
+            max_mission_time: None,
+            advance_on_result: false,
+        }
+    }
+
+    /// Only runs the mission inside `window`, and switches to it when the
+    /// window opens.
+    pub fn window(mut self, window: TimeWindow) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Advances once `mission_time` exceeds `limit`.
+    pub fn max_mission_time(mut self, limit: Duration) -> Self {
+        self.max_mission_time = Some(limit);
+        self
+    }
+
+    /// Advances once either coalition reaches the rotation's win threshold.
+    pub fn advance_on_result(mut self) -> Self {
+        self.advance_on_result = true;
+        self
+    }
+
+    pub fn mission(&self) -> &str {
+        &self.mission
+    }
+
+    fn is_current(&self, filename: &str) -> bool {
+        mission_file_name(&self.mission).eq_ignore_ascii_case(mission_file_name(filename))
+    }
+
+    fn is_open(&self, time: NaiveTime) -> bool {
+        self.window.is_none_or(|window| window.contains(time))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotationReason {
+    WindowOpened(TimeWindow),
+    WindowClosed(TimeWindow),
+    MissionTime(Duration),
+    Winner(Coalition),
+}
+
+impl fmt::Display for RotationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RotationReason::WindowOpened(window) => write!(f, "window {window} opened"),
+            RotationReason::WindowClosed(window) => write!(f, "window {window} closed"),
+            RotationReason::MissionTime(limit) => {
+                write!(f, "mission time exceeded {}s", limit.as_secs())
+            }
+            RotationReason::Winner(coalition) => write!(f, "{coalition} won"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotationDecision {
+    pub mission: String,
+    pub reason: RotationReason,
+}
+
+impl fmt::Display for RotationDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "switching to {} ({})", self.mission, self.reason)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MissionRotation {
+    server: Uuid,
+    entries: Vec<RotationEntry>,
+    timezone: Tz,
+    blackouts: Vec<TimeWindow>,
+    only_when_empty: bool,
+    win_threshold: i32,
+    /// Window openings the windowed mission was seen running in, so advancing
+    /// away from it does not switch straight back.
+    served: HashSet<(usize, NaiveDate)>,
+}
+
+impl MissionRotation {
+    pub fn new(server: Uuid) -> Self {
+        Self {
+            server,
+            entries: Vec::new(),
+            timezone: Tz::UTC,
+            blackouts: Vec::new(),
+            only_when_empty: false,
+            win_threshold: 100,
+            served: HashSet::new(),
+        }
+    }
+
+    pub fn entry(mut self, entry: RotationEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Time zone the entry and blackout windows are in.
+    pub fn timezone(mut self, name: &str) -> Result<Self> {
+        self.timezone = parse_timezone(name)?;
+        Ok(self)
+    }
+
+    /// Never switches missions inside `window`.
+    pub fn blackout(mut self, window: TimeWindow) -> Self {
+        self.blackouts.push(window);
+        self
+    }
+
+    /// Defers switches while players are connected.
+    pub fn only_when_empty(mut self, only_when_empty: bool) -> Self {
+        self.only_when_empty = only_when_empty;
+        self
+    }
+
+    /// Mission result (`result_red` / `result_blue`) that counts as a win.
+    pub fn win_threshold(mut self, threshold: i32) -> Self {
+        self.win_threshold = threshold;
+        self
+    }
+
+    /// Decides whether the running mission should be replaced at `now`.
+    pub fn decide(&mut self, runtime: &DcsRuntime, now: DateTime<Utc>) -> Option<RotationDecision> {
+        if runtime.current_action == Some(CurrentRuntimeAction::StartingMission) {
+            return None;
+        }
+
+        let local = now.with_timezone(&self.timezone);
+        let (date, time) = (local.date_naive(), local.time());
+        let info = &runtime.mission_info;
+
+        self.served
+            .retain(|(_, opened)| date.signed_duration_since(*opened).num_days() <= 1);
+        for (index, entry) in self.entries.iter().enumerate() {
+            if let Some(window) = entry.window
+                && window.contains(time)
+                && entry.is_current(&info.mission_filename)
+            {
+                self.served.insert((index, window.opened_on(date, time)));
+            }
+        }
+
+        if self.blackouts.iter().any(|window| window.contains(time)) {
+            return None;
+        }
+
+        if self.only_when_empty && runtime.players.players.clients().next().is_some() {
+            return None;
+        }
+
+        let current = self
+            .entries
+            .iter()
+            .position(|entry| entry.is_current(&info.mission_filename));
+
+        // A window that opened and has not been served yet takes priority.
+        for (index, entry) in self.entries.iter().enumerate() {
+            if let Some(window) = entry.window
+                && window.contains(time)
+                && !self.served.contains(&(index, window.opened_on(date, time)))
+            {
+                return Some(RotationDecision {
+                    mission: entry.mission.clone(),
+                    reason: RotationReason::WindowOpened(window),
+                });
+            }
+        }
+
+        let index = current?;
+        let entry = &self.entries[index];
+
+        let reason = if let Some(window) = entry.window
+            && !window.contains(time)
+        {
+            RotationReason::WindowClosed(window)
+        } else if let Some(limit) = entry.max_mission_time
+            && f64::from(info.mission_time) >= limit.as_secs_f64()
+        {
+            RotationReason::MissionTime(limit)
+        } else if entry.advance_on_result
+            && let Some(coalition) = self.winner(runtime)
+        {
+            RotationReason::Winner(coalition)
+        } else {
+            return None;
+        };
+
+        let next = (1..=self.entries.len())
+            .map(|offset| &self.entries[(index + offset) % self.entries.len()])
+            .find(|entry| entry.is_open(time))?;
+
+        Some(RotationDecision {
+            mission: next.mission.clone(),
+            reason,
+        })
+    }
+
+    fn winner(&self, runtime: &DcsRuntime) -> Option<Coalition> {
+        let info = &runtime.mission_info;
+
+        [
+            (Coalition::Red, info.result_red),
+            (Coalition::Blue, info.result_blue),
+        ]
+        .into_iter()
+        .filter(|(_, result)| result.is_some_and(|result| result >= self.win_threshold))
+        .max_by_key(|(_, result)| *result)
+        .map(|(coalition, _)| coalition)
+    }
+
+    pub async fn poll(&mut self, client: &Client) -> Result<Option<RotationDecision>> {
+        let runtime = client.get_runtime(&self.server).await?;
+        let Some(decision) = self.decide(&runtime, Utc::now()) else {
+            return Ok(None);
+        };
+
+        let Some(index) = runtime.mission_list.index_of(&decision.mission) else {
+            bail!(
+                "{} is not in the mission list of {}",
+                decision.mission,
+                self.server
+            );
+        };
+
+        client.start_mission(&self.server, index).await?;
+        Ok(Some(decision))
+    }
+
+    pub async fn run(&mut self, client: &Client, interval: Duration) -> Result<()> {
+        let what = format!("mission rotation on {}", self.server);
+        poll_every!(
+            interval,
+            what,
+            self.poll(client).await.map(|decision| {
+                if let Some(decision) = decision {
+                    log::info!("{}: {decision}", self.server);
+                }
+            })
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{player, runtime};
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&format!("2026-03-10T{time}:00Z"))
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn running(mission: &str, mission_time: f32) -> DcsRuntime {
+        let mut runtime = runtime([]);
+        runtime.mission_info.mission_filename = mission.to_string();
+        runtime.mission_info.mission_time = mission_time;
+        runtime
+    }
+
+    fn window(window: &str) -> TimeWindow {
+        window.parse().unwrap()
+    }
+
+    #[test]
+    fn windows_wrap_past_midnight() {
+        let evening = window("22:00-02:00");
+        let time = |time| NaiveTime::parse_from_str(time, "%H:%M").unwrap();
+
+        assert!(evening.contains(time("23:30")));
+        assert!(evening.contains(time("01:00")));
+        assert!(!evening.contains(time("02:00")));
+        assert!(!evening.contains(time("12:00")));
+        assert_eq!(evening.to_string(), "22:00-02:00");
+        assert!("22:00".parse::<TimeWindow>().is_err());
+    }
+
+    #[test]
+    fn advances_after_the_mission_time() {
+        let mut rotation = MissionRotation::new(Uuid::nil())
+            .entry(RotationEntry::new("A.miz").max_mission_time(Duration::from_secs(3600)))
+            .entry(RotationEntry::new("B.miz"));
+
+        assert_eq!(rotation.decide(&running("A.miz", 600.0), at("12:00")), None);
+        assert_eq!(
+            rotation.decide(&running("C:\\Missions\\a.miz", 3600.0), at("12:00")),
+            Some(RotationDecision {
+                mission: "B.miz".to_string(),
+                reason: RotationReason::MissionTime(Duration::from_secs(3600)),
+            })
+        );
+    }
+
+    #[test]
+    fn advances_on_a_win() {
+        let mut rotation = MissionRotation::new(Uuid::nil())
+            .win_threshold(80)
+            .entry(RotationEntry::new("A.miz").advance_on_result())
+            .entry(RotationEntry::new("B.miz"));
+
+        let mut runtime = running("A.miz", 0.0);
+        runtime.mission_info.result_red = Some(50);
+        assert_eq!(rotation.decide(&runtime, at("12:00")), None);
+
+        runtime.mission_info.result_blue = Some(90);
+        let decision = rotation.decide(&runtime, at("12:00")).unwrap();
+        assert_eq!(decision.reason, RotationReason::Winner(Coalition::Blue));
+    }
+
+    #[test]
+    fn window_opens_once_and_closes() {
+        let evening = window("18:00-23:00");
+        let mut rotation = MissionRotation::new(Uuid::nil())
+            .entry(RotationEntry::new("A.miz"))
+            .entry(RotationEntry::new("Night.miz").window(evening));
+
+        assert_eq!(rotation.decide(&running("A.miz", 0.0), at("12:00")), None);
+        assert_eq!(
+            rotation
+                .decide(&running("A.miz", 0.0), at("18:00"))
+                .map(|decision| decision.reason),
+            Some(RotationReason::WindowOpened(evening))
+        );
+
+        // Once served, the window does not pull the rotation back.
+        assert_eq!(
+            rotation.decide(&running("Night.miz", 0.0), at("19:00")),
+            None
+        );
+        assert_eq!(rotation.decide(&running("A.miz", 0.0), at("20:00")), None);
+
+        assert_eq!(
+            rotation.decide(&running("Night.miz", 0.0), at("23:00")),
+            Some(RotationDecision {
+                mission: "A.miz".to_string(),
+                reason: RotationReason::WindowClosed(evening),
+            })
+        );
+    }
+
+    #[test]
+    fn respects_blackouts_and_players() {
+        let entries = |rotation: MissionRotation| {
+            rotation
+                .entry(RotationEntry::new("A.miz").max_mission_time(Duration::from_secs(60)))
+                .entry(RotationEntry::new("B.miz"))
+        };
+
+        let mut blackout =
+            entries(MissionRotation::new(Uuid::nil()).blackout(window("11:00-13:00")));
+        assert_eq!(blackout.decide(&running("A.miz", 120.0), at("12:00")), None);
+        assert!(
+            blackout
+                .decide(&running("A.miz", 120.0), at("13:00"))
+                .is_some()
+        );
+
+        let mut empty = entries(MissionRotation::new(Uuid::nil()).only_when_empty(true));
+        let mut busy = running("A.miz", 120.0);
+        busy.players = runtime([player(2, "Viper")]).players;
+        assert_eq!(empty.decide(&busy, at("12:00")), None);
+        assert!(
+            empty
+                .decide(&running("A.miz", 120.0), at("12:00"))
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn waits_while_a_mission_starts() {
+        let mut rotation = MissionRotation::new(Uuid::nil())
+            .entry(RotationEntry::new("A.miz").max_mission_time(Duration::from_secs(60)))
+            .entry(RotationEntry::new("B.miz"));
+
+        let mut runtime = running("A.miz", 120.0);
+        runtime.current_action = Some(CurrentRuntimeAction::StartingMission);
+        assert_eq!(rotation.decide(&runtime, at("12:00")), None);
+    }
+}
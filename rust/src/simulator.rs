@@ -0,0 +1,500 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
+
+use crate::{
+    CreateTriggerRequest, DcsRuntime, Trigger, TriggerAction, TriggerCondition, parse_timezone,
+};
+
+/// Upper bound of schedule firings per trigger, so a per-second cron over a
+/// long timeline fails instead of exhausting memory.
+const MAX_SCHEDULE_FIRINGS: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TimelineEntry {
+    Players(u32),
+    Event(String),
+}
+
+/// Player counts and events over a span of virtual time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeline {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    entries: Vec<(DateTime<Utc>, TimelineEntry)>,
+}
+
+impl Timeline {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            end,
+            entries: Vec::new(),
+        }
+    }
+
+    /// The player count from `at` until the next count. Without a count at or
+    /// before the start, the server starts out empty.
+    pub fn players(mut self, at: DateTime<Utc>, count: u32) -> Self {
+        self.entries.push((at, TimelineEntry::Players(count)));
+        self
+    }
+
+    /// Player count of a recorded runtime snapshot.
+    pub fn snapshot(self, at: DateTime<Utc>, runtime: &DcsRuntime) -> Self {
+        let count = runtime.players.players.clients().count() as u32;
+        self.players(at, count)
+    }
+
+    pub fn event(mut self, at: DateTime<Utc>, event_type: impl Into<String>) -> Self {
+        self.entries
+            .push((at, TimelineEntry::Event(event_type.into())));
+        self
+    }
+
+    /// The last player count at or before the start, 0 without one.
+    fn players_at_start(&self) -> u32 {
+        self.entries
+            .iter()
+            .filter_map(|(at, entry)| match entry {
+                TimelineEntry::Players(count) if *at <= self.start => Some((at, *count)),
+                _ => None,
+            })
+            .max_by_key(|(at, _)| *at)
+            .map(|(_, count)| count)
+            .unwrap_or(0)
+    }
+
+    fn sorted_entries(&self) -> Vec<&(DateTime<Utc>, TimelineEntry)> {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(at, _)| *at >= self.start && *at <= self.end)
+            .collect();
+        entries.sort_by_key(|(at, _)| *at);
+        entries
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Firing {
+    pub at: DateTime<Utc>,
+    pub trigger: String,
+    pub action: TriggerAction,
+}
+
+impl Firing {
+    /// Whether the action restarts or stops the server.
+    pub fn is_disruptive(&self) -> bool {
+        matches!(
+            self.action,
+            TriggerAction::RestartInstance | TriggerAction::StopInstance
+        )
+    }
+}
+
+/// Two firings of different triggers closer together than the overlap window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlap {
+    pub firings: Vec<Firing>,
+}
+
+impl Overlap {
+    /// Both firings restart or stop the server.
+    pub fn is_conflict(&self) -> bool {
+        self.firings
+            .iter()
+            .filter(|firing| firing.is_disruptive())
+            .count()
+            > 1
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimulationReport {
+    pub firings: Vec<Firing>,
+    pub overlaps: Vec<Overlap>,
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for firing in &self.firings {
+            writeln!(
+                f,
+                "{} {}: {}",
+                firing.at.format("%Y-%m-%d %H:%M:%S"),
+                firing.trigger,
+                action_label(&firing.action)
+            )?;
+        }
+
+        for overlap in &self.overlaps {
+            let names: Vec<_> = overlap
+                .firings
+                .iter()
+                .map(|firing| firing.trigger.as_str())
+                .collect();
+            writeln!(
+                f,
+                "{} at {}: {}",
+                if overlap.is_conflict() {
+                    "conflict"
+                } else {
+                    "overlap"
+                },
+                overlap.firings[0].at.format("%Y-%m-%d %H:%M:%S"),
+                names.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn action_label(action: &TriggerAction) -> String {
+    match action {
+        TriggerAction::RestartInstance => "restart".to_string(),
+        TriggerAction::StopInstance => "stop".to_string(),
+        TriggerAction::ExecuteLuaScript { .. } => "run lua script".to_string(),
+        TriggerAction::SendChatMessage { message } => format!("chat \"{message}\""),
+    }
+}
+
+/// Evaluates triggers against a [`Timeline`] locally, as a dry run before
+/// installing them.
+///
+/// Player count conditions fire when they become met, and again only after
+/// they were missed in between. Event conditions fire on every matching
+/// event, schedules on every cron occurrence in the simulator's time zone.
+/// The simulation does not model the effect of actions, e.g. players leaving
+/// after a stop.
+#[derive(Debug, Clone)]
+pub struct TriggerSimulator {
+    triggers: Vec<CreateTriggerRequest>,
+    timezone: Tz,
+    overlap_window: Duration,
+}
+
+impl TriggerSimulator {
+    /// Disabled triggers are left out.
+    pub fn new(triggers: impl IntoIterator<Item = Trigger>) -> Self {
+        Self {
+            triggers: triggers
+                .into_iter()
+                .filter(|trigger| trigger.enabled)
+                .map(|trigger| trigger.to_request())
+                .collect(),
+            timezone: Tz::UTC,
+            overlap_window: Duration::from_secs(60),
+        }
+    }
+
+    pub fn from_requests(requests: impl IntoIterator<Item = CreateTriggerRequest>) -> Self {
+        Self {
+            triggers: requests.into_iter().collect(),
+            ..Self::new([])
+        }
+    }
+
+    /// Time zone cron schedules are evaluated in, UTC by default.
+    pub fn timezone(mut self, name: &str) -> Result<Self> {
+        self.timezone = parse_timezone(name)?;
+        Ok(self)
+    }
+
+    /// Firings of different triggers closer than `window` are reported as
+    /// overlapping. One minute by default.
+    pub fn overlap_window(mut self, window: Duration) -> Self {
+        self.overlap_window = window;
+        self
+    }
+
+    pub fn simulate(&self, timeline: &Timeline) -> Result<SimulationReport> {
+        let entries = timeline.sorted_entries();
+        let mut firings = Vec::new();
+
+        for trigger in &self.triggers {
+            let fire = |at: DateTime<Utc>| Firing {
+                at,
+                trigger: trigger.name.clone(),
+                action: trigger.action.clone(),
+            };
+
+            match &trigger.condition {
+                TriggerCondition::PlayerCount {
+                    operator,
+                    threshold,
+                } => {
+                    let mut met = operator.compare(timeline.players_at_start(), *threshold);
+                    if met {
+                        firings.push(fire(timeline.start));
+                    }
+
+                    for (at, entry) in &entries {
+                        let TimelineEntry::Players(count) = entry else {
+                            continue;
+                        };
+                        if *at == timeline.start {
+                            continue;
+                        }
+                        let now_met = operator.compare(*count, *threshold);
+                        if now_met && !met {
+                            firings.push(fire(*at));
+                        }
+                        met = now_met;
+                    }
+                }
+                TriggerCondition::OnEvent { event_type } => {
+                    firings.extend(
+                        entries
+                            .iter()
+                            .filter(|(_, entry)| {
+                                matches!(entry, TimelineEntry::Event(event) if event == event_type)
+                            })
+                            .map(|(at, _)| fire(*at)),
+                    );
+                }
+                TriggerCondition::Schedule { cron_expression } => {
+                    let schedule = Cron::from_str(cron_expression).with_context(|| {
+                        format!(
+                            "trigger {}: invalid cron expression: {cron_expression}",
+                            trigger.name
+                        )
+                    })?;
+
+                    let mut cursor = timeline.start.with_timezone(&self.timezone);
+                    let mut inclusive = true;
+                    let mut count = 0;
+                    loop {
+                        let next = schedule.find_next_occurrence(&cursor, inclusive)?;
+                        if next.with_timezone(&Utc) > timeline.end {
+                            break;
+                        }
+
+                        count += 1;
+                        if count > MAX_SCHEDULE_FIRINGS {
+                            bail!(
+                                "trigger {} fires more than {MAX_SCHEDULE_FIRINGS} times",
+                                trigger.name
+                            );
+                        }
+                        firings.push(fire(next.with_timezone(&Utc)));
+                        cursor = next;
+                        inclusive = false;
+                    }
+                }
+            }
+        }
+
+        firings.sort_by(|a, b| a.at.cmp(&b.at).then_with(|| a.trigger.cmp(&b.trigger)));
+        let overlaps = self.overlaps(&firings);
+
+        Ok(SimulationReport { firings, overlaps })
+    }
+
+    /// Every pair of firings of different triggers closer than the overlap
+    /// window, in the order of the earlier firing.
+    fn overlaps(&self, firings: &[Firing]) -> Vec<Overlap> {
+        let window =
+            chrono::Duration::from_std(self.overlap_window).unwrap_or(chrono::Duration::MAX);
+        let mut overlaps = Vec::new();
+
+        for (index, first) in firings.iter().enumerate() {
+            for second in firings[index + 1..]
+                .iter()
+                .take_while(|second| second.at - first.at < window)
+            {
+                if second.trigger != first.trigger {
+                    overlaps.push(Overlap {
+                        firings: vec![first.clone(), second.clone()],
+                    });
+                }
+            }
+        }
+
+        overlaps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::ComparisonOperator;
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 28, hour, minute, second)
+            .unwrap()
+    }
+
+    fn trigger(
+        name: &str,
+        condition: TriggerCondition,
+        action: TriggerAction,
+    ) -> CreateTriggerRequest {
+        CreateTriggerRequest {
+            name: name.to_string(),
+            description: None,
+            condition,
+            action,
+        }
+    }
+
+    fn on_event(name: &str, event_type: &str, action: TriggerAction) -> CreateTriggerRequest {
+        let condition = TriggerCondition::OnEvent {
+            event_type: event_type.to_string(),
+        };
+        trigger(name, condition, action)
+    }
+
+    fn firing_times(report: &SimulationReport) -> Vec<DateTime<Utc>> {
+        report.firings.iter().map(|firing| firing.at).collect()
+    }
+
+    #[test]
+    fn player_counts_fire_on_edges() {
+        let condition = TriggerCondition::PlayerCount {
+            operator: ComparisonOperator::GreaterThanOrEqual,
+            threshold: 2,
+        };
+        let simulator = TriggerSimulator::from_requests([trigger(
+            "busy",
+            condition,
+            TriggerAction::SendChatMessage {
+                message: "welcome".to_string(),
+            },
+        )]);
+
+        let timeline = Timeline::new(at(10, 0, 0), at(12, 0, 0))
+            .players(at(9, 0, 0), 3)
+            .players(at(10, 10, 0), 4)
+            .players(at(10, 20, 0), 1)
+            .players(at(10, 30, 0), 2)
+            .players(at(10, 40, 0), 5)
+            .players(at(12, 30, 0), 0)
+            .players(at(12, 40, 0), 2);
+
+        // Met at the start, missed at 10:20, met again at 10:30. Counts that
+        // keep it met or fall outside the timeline don't fire.
+        let report = simulator.simulate(&timeline).unwrap();
+        assert_eq!(firing_times(&report), [at(10, 0, 0), at(10, 30, 0)]);
+
+        let empty = Timeline::new(at(10, 0, 0), at(12, 0, 0)).players(at(11, 0, 0), 2);
+        let report = simulator.simulate(&empty).unwrap();
+        assert_eq!(firing_times(&report), [at(11, 0, 0)]);
+    }
+
+    #[test]
+    fn schedules_follow_daylight_saving_time() {
+        let nightly = trigger(
+            "nightly",
+            TriggerCondition::Schedule {
+                cron_expression: "0 4 * * *".to_string(),
+            },
+            TriggerAction::RestartInstance,
+        );
+        let simulator = TriggerSimulator::from_requests([nightly])
+            .timezone("Europe/Berlin")
+            .unwrap();
+
+        // Berlin switches from UTC+1 to UTC+2 on the night of March 29th.
+        let start = Utc.with_ymd_and_hms(2026, 3, 27, 12, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2026, 3, 30, 12, 0, 0).unwrap();
+        let report = simulator.simulate(&Timeline::new(start, end)).unwrap();
+
+        assert_eq!(
+            firing_times(&report),
+            [
+                Utc.with_ymd_and_hms(2026, 3, 28, 3, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2026, 3, 29, 2, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2026, 3, 30, 2, 0, 0).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_two_restarts_in_the_same_minute() {
+        let simulator = TriggerSimulator::from_requests([
+            on_event("crash restart", "crash", TriggerAction::RestartInstance),
+            on_event("update restart", "update", TriggerAction::RestartInstance),
+            on_event(
+                "update notice",
+                "update",
+                TriggerAction::SendChatMessage {
+                    message: "updating".to_string(),
+                },
+            ),
+        ]);
+        let timeline = Timeline::new(at(10, 0, 0), at(12, 0, 0))
+            .event(at(10, 0, 10), "crash")
+            .event(at(10, 0, 40), "update")
+            .event(at(11, 0, 0), "update");
+
+        let report = simulator.simulate(&timeline).unwrap();
+        assert_eq!(report.overlaps.len(), 4);
+        // A restart with its own notice is an overlap, not a conflict.
+        let conflicts: Vec<_> = report
+            .overlaps
+            .iter()
+            .filter(|overlap| overlap.is_conflict())
+            .collect();
+        assert_eq!(conflicts.len(), 1);
+        assert!(
+            report
+                .to_string()
+                .contains("conflict at 2026-03-28 10:00:10: crash restart, update restart")
+        );
+    }
+
+    #[test]
+    fn reports_every_close_pair() {
+        let simulator = TriggerSimulator::from_requests([
+            on_event("a", "a", TriggerAction::RestartInstance),
+            on_event("b", "b", TriggerAction::RestartInstance),
+            on_event("c", "c", TriggerAction::RestartInstance),
+        ]);
+        // Each firing is within a minute of the previous one, but the last is
+        // more than a minute after the first.
+        let timeline = Timeline::new(at(10, 0, 0), at(12, 0, 0))
+            .event(at(10, 0, 0), "a")
+            .event(at(10, 0, 50), "b")
+            .event(at(10, 1, 40), "c");
+
+        let report = simulator.simulate(&timeline).unwrap();
+        let groups: Vec<Vec<_>> = report
+            .overlaps
+            .iter()
+            .map(|overlap| {
+                overlap
+                    .firings
+                    .iter()
+                    .map(|firing| firing.trigger.as_str())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(groups, [vec!["a", "b"], vec!["b", "c"]]);
+    }
+
+    #[test]
+    fn rejects_invalid_and_runaway_schedules() {
+        let schedule = |cron_expression: &str| {
+            TriggerSimulator::from_requests([trigger(
+                "schedule",
+                TriggerCondition::Schedule {
+                    cron_expression: cron_expression.to_string(),
+                },
+                TriggerAction::RestartInstance,
+            )])
+        };
+        let timeline = Timeline::new(at(0, 0, 0), at(0, 0, 0) + chrono::Duration::days(7));
+
+        assert!(schedule("not cron").simulate(&timeline).is_err());
+        assert!(schedule("* * * * * *").simulate(&timeline).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! Fixtures shared by the unit tests.
+
+use std::collections::HashMap;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::{
+    AdvancedSettings, BillingType, DcsChat, DcsRuntime, DcsSettings, GameRuntime, GameType,
+    GetMissionInfoResponse, GetMissionListResponse, GetPlayersResponse, GetServerSettingsResponse,
+    Instance, InstanceNodeResource, InstanceResource, InstanceStatus, Player, Players, Region,
+    ResumeMode, ServerMode, Settings, Trigger, TriggerAction, TriggerCondition,
+};
+
+pub fn player(id: i32, name: &str) -> Player {
+    Player {
+        ping: 50,
+        side: 2,
+        slot: "1".to_string(),
+        id,
+        name: name.to_string(),
+        score: 0,
+        ucid: format!("ucid-{name}"),
+        started: true,
+        lang: "en".to_string(),
+        ipaddr: "127.0.0.1".to_string(),
+    }
+}
+
+pub fn chat(id: i32, player_id: i32, message: &str) -> DcsChat {
+    DcsChat {
+        id,
+        player_id,
+        player_name: String::new(),
+        message: message.to_string(),
+        is_historical: false,
+        unix_time: 0,
+    }
+}
+
+/// A restart on `cron_expression`, stored on the nil server.
+pub fn trigger(id: u128, name: &str, cron_expression: &str) -> Trigger {
+    Trigger {
+        id: Some(Uuid::from_u128(id)),
+        instance_id: Uuid::nil(),
+        name: name.to_string(),
+        description: None,
+        condition: TriggerCondition::Schedule {
+            cron_expression: cron_expression.to_string(),
+        },
+        action: TriggerAction::RestartInstance,
+        last_executed_at: None,
+        created_at: "2026-01-01T00:00:00Z".to_string(),
+        updated_at: "2026-01-01T00:00:00Z".to_string(),
+        enabled: true,
+    }
+}
+
+/// A running server with the dedicated server player and `players`.
+pub fn runtime(players: impl IntoIterator<Item = Player>) -> DcsRuntime {
+    let mission_list = GetMissionListResponse {
+        mission_list: vec!["C:\\Missions\\Training.miz".to_string()],
+        mission_theatres: vec!["Caucasus".to_string()],
+        list_start_index: 1,
+        list_shuffle: false,
+        list_loop: false,
+    };
+
+    DcsRuntime {
+        current_action: None,
+        last_full_update: 0,
+        paused: false,
+        mission_info: GetMissionInfoResponse {
+            result_red: None,
+            result_blue: None,
+            mission_filename: "Training.miz".to_string(),
+            mission_time: 0.0,
+            mission_name: "Training".to_string(),
+            mission_description: String::new(),
+        },
+        mission_list: mission_list.clone(),
+        players: GetPlayersResponse {
+            players: Players {
+                banned: Vec::new(),
+                all: std::iter::once(player(Players::SERVER_PLAYER_ID, "Server"))
+                    .chain(players)
+                    .map(|player| (player.id.to_string(), player))
+                    .collect::<HashMap<_, _>>(),
+            },
+            server_id: 1,
+        },
+        settings: GetServerSettingsResponse {
+            mission_list,
+            settings: settings(),
+            ip: "127.0.0.1".to_string(),
+        },
+    }
+}
+
+pub fn settings() -> Settings {
+    Settings {
+        description: String::new(),
+        require_pure_textures: false,
+        list_start_index: 1,
+        advanced: AdvancedSettings {
+            allow_change_tailno: true,
+            disable_events: false,
+            allow_ownship_export: true,
+            allow_object_export: false,
+            pause_on_load: false,
+            allow_sensor_export: false,
+            event_takeoff: true,
+            pause_without_clients: false,
+            client_outbound_limit: 0,
+            client_inbound_limit: 0,
+            server_can_screenshot: false,
+            allow_players_pool: true,
+            voice_chat_server: true,
+            allow_change_skin: true,
+            event_connect: true,
+            event_ejecting: true,
+            event_kill: true,
+            event_crash: true,
+            event_role: true,
+            resume_mode: ResumeMode::RunWhenPlayers,
+            max_ping: 0,
+            allow_trial_only_clients: false,
+            allow_dynamic_radio: true,
+            red_password_hash: None,
+            blue_password_hash: None,
+            red_password: None,
+            blue_password: None,
+        },
+        port: 10308,
+        mode: ServerMode::Standard,
+        bind_address: String::new(),
+        is_public: true,
+        list_shuffle: false,
+        password: String::new(),
+        list_loop: false,
+        name: "Training Server".to_string(),
+        require_pure_scripts: false,
+        mission_list: vec!["C:\\Missions\\Training.miz".to_string()],
+        require_pure_clients: false,
+        require_pure_models: false,
+        max_players: 16,
+    }
+}
+
+/// A started hourly server in Germany without a runtime.
+pub fn server(id: u128, name: &str) -> InstanceResource {
+    InstanceResource {
+        instance: Instance {
+            id: Uuid::from_u128(id),
+            node_id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            product_id: Uuid::nil(),
+            game_type: GameType::Dcs,
+            billing_type: BillingType::Hourly,
+            port: 10308,
+            webgui_port: 8088,
+            ftp_port: 21,
+            ftp_username: String::new(),
+            ftp_password: String::new(),
+            pid: None,
+            status: InstanceStatus::ServerStarted,
+            want_delete: false,
+            wanted_terrains: Vec::new(),
+            rented_at: 0,
+            rented_until: None,
+            active_mods: Vec::new(),
+            created_at: String::new(),
+            dcs_settings: Some(DcsSettings {
+                initial_server_name: name.to_string(),
+                initial_server_password: String::new(),
+                initial_max_players: 16,
+                enable_io: false,
+                enable_os: false,
+                enable_lfs: false,
+                initial_use_voice_chat: false,
+            }),
+        },
+        node: InstanceNodeResource {
+            region: Region::Germany,
+            ip: "127.0.0.1".to_string(),
+            domain: "localhost".to_string(),
+        },
+        runtime: None,
+    }
+}
+
+/// [`server`] with a runtime holding `players`.
+pub fn running_server(
+    id: u128,
+    name: &str,
+    players: impl IntoIterator<Item = Player>,
+) -> InstanceResource {
+    let mut runtime = runtime(players);
+    runtime.settings.settings.name = name.to_string();
+
+    InstanceResource {
+        runtime: Some(GameRuntime::Dcs(runtime)),
+        ..server(id, name)
+    }
+}
+
+/// A request captured by [`http_receiver`], with lowercase header names.
+#[derive(Debug)]
+pub struct Received {
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+/// A local HTTP server answering with `statuses` in turn, then 200. Returns
+/// its base URL and the requests it received.
+pub async fn http_receiver(statuses: Vec<u16>) -> (String, mpsc::UnboundedReceiver<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut statuses = statuses.into_iter();
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            let header_end = loop {
+                if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+                    break end + 4;
+                }
+                let read = stream.read(&mut buffer).await.unwrap();
+                assert!(read > 0, "connection closed before the headers ended");
+                request.extend_from_slice(&buffer[..read]);
+            };
+
+            let head = String::from_utf8_lossy(&request[..header_end]).to_string();
+            let mut lines = head.lines();
+            let path = lines
+                .next()
+                .and_then(|line| line.split_whitespace().nth(1))
+                .unwrap_or_default()
+                .to_string();
+            let headers: HashMap<String, String> = lines
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+                .collect();
+
+            let length: usize = headers
+                .get("content-length")
+                .and_then(|length| length.parse().ok())
+                .unwrap_or(0);
+            while request.len() < header_end + length {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+
+            // Recorded before answering, so the request is visible once the
+            // client has its response.
+            let body = request[header_end..header_end + length].to_vec();
+            let _ = sender.send(Received {
+                path,
+                headers,
+                body,
+            });
+
+            let status = statuses.next().unwrap_or(200);
+            let response =
+                format!("HTTP/1.1 {status} Test\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        }
+    });
+
+    (url, receiver)
+}
@@ -0,0 +1,417 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{Client, CreateTriggerRequest, Trigger};
+
+/// Triggers declared in a file, e.g. kept in a repository.
+///
+/// ```toml
+/// [[triggers]]
+/// name = "nightly restart"
+/// condition = { type = "Schedule", config = { cron_expression = "0 4 * * *" } }
+/// action = { type = "RestartInstance" }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TriggerSet {
+    #[serde(default)]
+    pub triggers: Vec<CreateTriggerRequest>,
+}
+
+impl TriggerSet {
+    pub fn from_toml_str(source: &str) -> Result<Self> {
+        Ok(toml::from_str(source)?)
+    }
+
+    pub fn from_json_str(source: &str) -> Result<Self> {
+        Ok(serde_json::from_str(source)?)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(source: &str) -> Result<Self> {
+        Ok(serde_yaml::from_str(source)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml_str(&source),
+            Some("json") => Self::from_json_str(&source),
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Self::from_yaml_str(&source),
+            _ => bail!("unsupported trigger file format: {}", path.display()),
+        }
+    }
+
+    /// Validates every trigger and rejects duplicate names, which sync
+    /// matches on.
+    pub fn validate(&self) -> Result<()> {
+        validate(&self.triggers)
+    }
+}
+
+pub(crate) fn validate(triggers: &[CreateTriggerRequest]) -> Result<()> {
+    let mut names = HashSet::new();
+    for trigger in triggers {
+        if !names.insert(trigger.name.as_str()) {
+            bail!("trigger {} is declared more than once", trigger.name);
+        }
+        trigger.validate(None)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TriggerSyncOptions {
+    /// Delete triggers that exist on the server but are not declared.
+    pub prune: bool,
+    /// Only compute the diff.
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriggerChange {
+    Create {
+        trigger: CreateTriggerRequest,
+    },
+    /// Updated in place, which keeps the trigger's execution history.
+    Update {
+        trigger_id: Uuid,
+        trigger: CreateTriggerRequest,
+        fields: Vec<&'static str>,
+    },
+    Delete {
+        trigger_id: Uuid,
+        name: String,
+    },
+}
+
+impl fmt::Display for TriggerChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TriggerChange::Create { trigger } => write!(f, "+ trigger {}", trigger.name),
+            TriggerChange::Update {
+                trigger, fields, ..
+            } => write!(f, "~ trigger {} ({})", trigger.name, fields.join(", ")),
+            TriggerChange::Delete { name, .. } => write!(f, "- trigger {name}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerDiff {
+    pub instance_id: Uuid,
+    pub changes: Vec<TriggerChange>,
+    /// Names of declared triggers that already match.
+    pub unchanged: Vec<String>,
+    /// Names of declared triggers that exist more than once on the server.
+    /// Only reported without pruning, which deletes the extra copies.
+    pub duplicates: Vec<String>,
+    /// Changes that failed in the last [`Self::apply`], with the error.
+    pub failed: Vec<(TriggerChange, String)>,
+}
+
+impl TriggerDiff {
+    /// Matches `existing` triggers to `desired` ones by name. Of several
+    /// triggers with the same name, one that already matches is kept.
+    /// Declarations have no enabled flag, so a trigger disabled on the server
+    /// counts as unchanged and stays disabled.
+    pub fn between(
+        instance_id: Uuid,
+        existing: &[Trigger],
+        desired: &[CreateTriggerRequest],
+        prune: bool,
+    ) -> Self {
+        let mut changes = Vec::new();
+        let mut unchanged = Vec::new();
+        let mut duplicates = Vec::new();
+
+        for wanted in desired {
+            let copies: Vec<_> = existing
+                .iter()
+                .filter(|trigger| trigger.name == wanted.name)
+                .filter_map(|trigger| Some((trigger.id?, trigger, changed_fields(trigger, wanted))))
+                .collect();
+            let current = copies
+                .iter()
+                .find(|(_, _, fields)| fields.is_empty())
+                .or(copies.first());
+
+            match current {
+                None => changes.push(TriggerChange::Create {
+                    trigger: wanted.clone(),
+                }),
+                Some((trigger_id, _, fields)) if !fields.is_empty() => {
+                    changes.push(TriggerChange::Update {
+                        trigger_id: *trigger_id,
+                        trigger: wanted.clone(),
+                        fields: fields.clone(),
+                    })
+                }
+                Some(_) => unchanged.push(wanted.name.clone()),
+            }
+
+            let Some((kept, _, _)) = current else {
+                continue;
+            };
+            if prune {
+                changes.extend(
+                    copies
+                        .iter()
+                        .filter(|(trigger_id, _, _)| trigger_id != kept)
+                        .map(|(trigger_id, trigger, _)| TriggerChange::Delete {
+                            trigger_id: *trigger_id,
+                            name: trigger.name.clone(),
+                        }),
+                );
+            } else if copies.len() > 1 {
+                duplicates.push(wanted.name.clone());
+            }
+        }
+
+        if prune {
+            changes.extend(
+                existing
+                    .iter()
+                    .filter(|trigger| desired.iter().all(|wanted| wanted.name != trigger.name))
+                    .filter_map(|trigger| {
+                        Some(TriggerChange::Delete {
+                            trigger_id: trigger.id?,
+                            name: trigger.name.clone(),
+                        })
+                    }),
+            );
+        }
+
+        Self {
+            instance_id,
+            changes,
+            unchanged,
+            duplicates,
+            failed: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Applies every change, carrying on past failures so the others still
+    /// land. Failures are kept in [`Self::failed`]; fails if there were any.
+    pub async fn apply(&mut self, client: &Client) -> Result<()> {
+        let id = &self.instance_id;
+        self.failed.clear();
+
+        for change in &self.changes {
+            let result = match change {
+                TriggerChange::Create { trigger } => {
+                    client.create_trigger(id, trigger).await.map(|_| ())
+                }
+                TriggerChange::Update {
+                    trigger_id,
+                    trigger,
+                    ..
+                } => client
+                    .update_trigger(id, trigger_id, trigger)
+                    .await
+                    .map(|_| ()),
+                TriggerChange::Delete { trigger_id, .. } => {
+                    client.delete_trigger(id, trigger_id).await
+                }
+            };
+
+            if let Err(error) = result {
+                self.failed.push((change.clone(), format!("{error:#}")));
+            }
+        }
+
+        if !self.failed.is_empty() {
+            bail!(
+                "{id}: {} of {} trigger changes failed",
+                self.failed.len(),
+                self.changes.len()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn changed_fields(current: &Trigger, wanted: &CreateTriggerRequest) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if current.description != wanted.description {
+        fields.push("description");
+    }
+    if current.condition != wanted.condition {
+        fields.push("condition");
+    }
+    if current.action != wanted.action {
+        fields.push("action");
+    }
+    fields
+}
+
+impl fmt::Display for TriggerDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() && self.duplicates.is_empty() && self.failed.is_empty() {
+            return writeln!(f, "{}: triggers up to date", self.instance_id);
+        }
+
+        writeln!(f, "{}:", self.instance_id)?;
+        for change in &self.changes {
+            writeln!(f, "  {change}")?;
+        }
+        for name in &self.duplicates {
+            writeln!(f, "  ! trigger {name} exists more than once")?;
+        }
+        for (change, error) in &self.failed {
+            writeln!(f, "  ! {change} failed: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::trigger;
+
+    fn declared(name: &str, cron_expression: &str) -> CreateTriggerRequest {
+        trigger(0, name, cron_expression).to_request()
+    }
+
+    #[test]
+    fn diffs_by_name() {
+        let existing = [
+            trigger(1, "nightly", "0 4 * * *"),
+            trigger(2, "weekly", "0 5 * * 0"),
+            trigger(3, "manual", "0 6 * * *"),
+        ];
+        let desired = [
+            declared("nightly", "0 4 * * *"),
+            declared("weekly", "0 6 * * 0"),
+            declared("hourly", "0 * * * *"),
+        ];
+
+        let diff = TriggerDiff::between(Uuid::nil(), &existing, &desired, false);
+        assert_eq!(diff.unchanged, ["nightly"]);
+        assert_eq!(
+            diff.changes,
+            [
+                TriggerChange::Update {
+                    trigger_id: Uuid::from_u128(2),
+                    trigger: desired[1].clone(),
+                    fields: vec!["condition"],
+                },
+                TriggerChange::Create {
+                    trigger: desired[2].clone(),
+                },
+            ]
+        );
+
+        let diff = TriggerDiff::between(Uuid::nil(), &existing, &desired, true);
+        assert_eq!(
+            diff.changes.last(),
+            Some(&TriggerChange::Delete {
+                trigger_id: Uuid::from_u128(3),
+                name: "manual".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn reports_duplicates_without_pruning() {
+        let existing = [
+            trigger(1, "nightly", "0 3 * * *"),
+            trigger(2, "nightly", "0 4 * * *"),
+        ];
+        let desired = [declared("nightly", "0 4 * * *")];
+
+        // The copy that already matches is kept, so nothing is updated.
+        let diff = TriggerDiff::between(Uuid::nil(), &existing, &desired, false);
+        assert!(diff.is_empty());
+        assert_eq!(diff.unchanged, ["nightly"]);
+        assert_eq!(diff.duplicates, ["nightly"]);
+        assert!(
+            diff.to_string()
+                .contains("! trigger nightly exists more than once")
+        );
+    }
+
+    #[test]
+    fn leaves_disabled_triggers_alone_and_lists_failures() {
+        let mut disabled = trigger(1, "nightly", "0 4 * * *");
+        disabled.enabled = false;
+        let desired = [
+            declared("nightly", "0 4 * * *"),
+            declared("hourly", "0 * * * *"),
+        ];
+
+        let mut diff = TriggerDiff::between(Uuid::nil(), &[disabled], &desired, false);
+        assert_eq!(diff.unchanged, ["nightly"]);
+
+        diff.failed.push((
+            diff.changes[0].clone(),
+            "500 Internal Server Error".to_string(),
+        ));
+        assert!(
+            diff.to_string()
+                .contains("! + trigger hourly failed: 500 Internal Server Error")
+        );
+    }
+
+    #[test]
+    fn prunes_duplicates() {
+        let existing = [
+            trigger(1, "nightly", "0 3 * * *"),
+            trigger(2, "nightly", "0 2 * * *"),
+        ];
+        let desired = [declared("nightly", "0 4 * * *")];
+
+        let diff = TriggerDiff::between(Uuid::nil(), &existing, &desired, true);
+        assert!(diff.duplicates.is_empty());
+        assert_eq!(
+            diff.changes,
+            [
+                TriggerChange::Update {
+                    trigger_id: Uuid::from_u128(1),
+                    trigger: desired[0].clone(),
+                    fields: vec!["condition"],
+                },
+                TriggerChange::Delete {
+                    trigger_id: Uuid::from_u128(2),
+                    name: "nightly".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_declarations() {
+        let set = TriggerSet {
+            triggers: vec![
+                declared("nightly", "0 4 * * *"),
+                declared("nightly", "0 5 * * *"),
+            ],
+        };
+        assert!(set.validate().is_err());
+
+        let set = TriggerSet::from_toml_str(
+            r#"
+[[triggers]]
+name = "nightly"
+condition = { type = "Schedule", config = { cron_expression = "0 4 * * *" } }
+action = { type = "RestartInstance" }
+"#,
+        )
+        .unwrap();
+        assert!(set.validate().is_ok());
+    }
+}
@@ -0,0 +1,239 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use blake2::Blake2bMac;
+use blake2::digest::consts::U32;
+use blake2::digest::{KeyInit, Mac};
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use serde::{Deserialize, Serialize};
+
+use super::dcs_runtime::AdvancedSettings;
+use super::settings_patch::AdvancedSettingsPatch;
+
+const SALT_LENGTH: usize = 11;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Coalition {
+    Red,
+    Blue,
+}
+
+impl Coalition {
+    /// Maps a DCS player side (`0` spectators, `1` red, `2` blue).
+    pub fn from_side(side: i32) -> Option<Self> {
+        match side {
+            1 => Some(Coalition::Red),
+            2 => Some(Coalition::Blue),
+            _ => None,
+        }
+    }
+
+    pub fn side(&self) -> i32 {
+        match self {
+            Coalition::Red => 1,
+            Coalition::Blue => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for Coalition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Coalition::Red => write!(f, "red"),
+            Coalition::Blue => write!(f, "blue"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum CoalitionPassword {
+    Plain(String),
+    Hash(String),
+    #[default]
+    None,
+}
+
+impl CoalitionPassword {
+    /// Hashes `password` the way DCS stores `redPasswordHash` /
+    /// `bluePasswordHash`: an 11 character alphanumeric salt used as the key of
+    /// a BLAKE2b-256 MAC, written as `<salt>:<unpadded base64 digest>`.
+    pub fn hashed(password: &str) -> Self {
+        let salt: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(SALT_LENGTH)
+            .map(char::from)
+            .collect();
+
+        CoalitionPassword::Hash(
+            hash_password(password, &salt).expect("an 11 byte salt is a valid BLAKE2b key"),
+        )
+    }
+
+    /// Checks `password` against a hash produced by DCS or [`Self::hashed`].
+    pub fn verify(&self, password: &str) -> bool {
+        match self {
+            CoalitionPassword::Plain(plain) => plain == password,
+            CoalitionPassword::Hash(hash) => hash
+                .split_once(':')
+                .and_then(|(salt, _)| hash_password(password, salt))
+                .is_some_and(|computed| computed == *hash),
+            CoalitionPassword::None => password.is_empty(),
+        }
+    }
+
+    fn into_fields(self) -> (Option<String>, Option<String>) {
+        match self {
+            CoalitionPassword::Plain(password) => (Some(password), None),
+            CoalitionPassword::Hash(hash) => (None, Some(hash)),
+            CoalitionPassword::None => (None, None),
+        }
+    }
+}
+
+/// `None` if the salt is longer than the 64 byte BLAKE2b key limit. DCS
+/// writes the digest without base64 padding.
+fn hash_password(password: &str, salt: &str) -> Option<String> {
+    let mut mac = <Blake2bMac<U32> as KeyInit>::new_from_slice(salt.as_bytes()).ok()?;
+    mac.update(password.as_bytes());
+
+    Some(format!(
+        "{salt}:{}",
+        STANDARD_NO_PAD.encode(mac.finalize().into_bytes())
+    ))
+}
+
+impl AdvancedSettings {
+    pub fn coalition_password(&self, coalition: Coalition) -> CoalitionPassword {
+        let (password, hash) = match coalition {
+            Coalition::Red => (&self.red_password, &self.red_password_hash),
+            Coalition::Blue => (&self.blue_password, &self.blue_password_hash),
+        };
+
+        match (password, hash) {
+            (Some(password), _) => CoalitionPassword::Plain(password.clone()),
+            (None, Some(hash)) => CoalitionPassword::Hash(hash.clone()),
+            (None, None) => CoalitionPassword::None,
+        }
+    }
+
+    pub fn set_coalition_password(&mut self, coalition: Coalition, password: CoalitionPassword) {
+        let (plain, hash) = password.into_fields();
+
+        match coalition {
+            Coalition::Red => {
+                self.red_password = plain;
+                self.red_password_hash = hash;
+            }
+            Coalition::Blue => {
+                self.blue_password = plain;
+                self.blue_password_hash = hash;
+            }
+        }
+    }
+}
+
+impl AdvancedSettingsPatch {
+    pub fn set_coalition_password(&mut self, coalition: Coalition, password: CoalitionPassword) {
+        let (plain, hash) = password.into_fields();
+
+        match coalition {
+            Coalition::Red => {
+                self.red_password = Some(plain);
+                self.red_password_hash = Some(hash);
+            }
+            Coalition::Blue => {
+                self.blue_password = Some(plain);
+                self.blue_password_hash = Some(hash);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference hashes of the DCS scheme, computed independently with Python's
+    // `hashlib.blake2b(password, key=salt, digest_size=32)` and unpadded base64.
+    const BLUE_HASH: &str = "x3NqcWvzsBM:W90Xw+lrA/noe38UbM/pL0JGn2/urbZviwIURl/rvtc";
+    const RED_HASH: &str = "aB3dE5gH7jK:WS6zl3D7jcueLktIqtoKyF2ey3bpCjrWYTxy0DNenTQ";
+
+    #[test]
+    fn hashes_match_dcs() {
+        assert_eq!(
+            hash_password("blue team", "x3NqcWvzsBM").as_deref(),
+            Some(BLUE_HASH)
+        );
+        assert_eq!(
+            hash_password("red", "aB3dE5gH7jK").as_deref(),
+            Some(RED_HASH)
+        );
+    }
+
+    #[test]
+    fn verify_accepts_dcs_hashes() {
+        let hash = CoalitionPassword::Hash(BLUE_HASH.to_string());
+
+        assert!(hash.verify("blue team"));
+        assert!(!hash.verify("red team"));
+        assert!(!CoalitionPassword::Hash("no separator".to_string()).verify("blue team"));
+    }
+
+    #[test]
+    fn hashed_passwords_verify() {
+        let CoalitionPassword::Hash(hash) = CoalitionPassword::hashed("secret") else {
+            panic!("expected a hash");
+        };
+        let (salt, digest) = hash.split_once(':').unwrap();
+
+        assert_eq!(salt.len(), SALT_LENGTH);
+        assert!(!digest.ends_with('='));
+        assert!(CoalitionPassword::Hash(hash.clone()).verify("secret"));
+    }
+
+    #[test]
+    fn verify_rejects_oversized_salts() {
+        let hash = format!("{}:digest", "s".repeat(65));
+
+        assert!(!CoalitionPassword::Hash(hash).verify("secret"));
+    }
+
+    #[test]
+    fn plain_and_missing_passwords() {
+        assert!(CoalitionPassword::Plain("red".to_string()).verify("red"));
+        assert!(!CoalitionPassword::Plain("red".to_string()).verify("blue"));
+        assert!(CoalitionPassword::None.verify(""));
+        assert!(!CoalitionPassword::None.verify("red"));
+    }
+
+    #[test]
+    fn coalition_passwords_map_to_settings_fields() {
+        let mut advanced = crate::test_support::settings().advanced;
+
+        advanced.set_coalition_password(Coalition::Red, CoalitionPassword::Plain("red".into()));
+        advanced.set_coalition_password(Coalition::Blue, CoalitionPassword::Hash(BLUE_HASH.into()));
+
+        assert_eq!(advanced.red_password.as_deref(), Some("red"));
+        assert_eq!(advanced.red_password_hash, None);
+        assert_eq!(advanced.blue_password, None);
+        assert_eq!(
+            advanced.coalition_password(Coalition::Blue),
+            CoalitionPassword::Hash(BLUE_HASH.to_string())
+        );
+
+        let mut patch = AdvancedSettingsPatch::default();
+        patch.set_coalition_password(Coalition::Red, CoalitionPassword::None);
+        assert_eq!(patch.red_password, Some(None));
+        assert_eq!(patch.red_password_hash, Some(None));
+    }
+
+    #[test]
+    fn sides_round_trip() {
+        for coalition in [Coalition::Red, Coalition::Blue] {
+            assert_eq!(Coalition::from_side(coalition.side()), Some(coalition));
+        }
+        assert_eq!(Coalition::from_side(0), None);
+    }
+}
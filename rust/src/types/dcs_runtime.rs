@@ -52,6 +52,23 @@ pub struct GetMissionListResponse {
     pub list_loop: bool,
 }
 
+impl GetMissionListResponse {
+    /// The 1-based index `start_mission` expects for `mission`, matched by file
+    /// name so both bare names and full paths work.
+    pub fn index_of(&self, mission: &str) -> Option<i32> {
+        let name = mission_file_name(mission);
+
+        self.mission_list
+            .iter()
+            .position(|path| mission_file_name(path).eq_ignore_ascii_case(name))
+            .map(|position| position as i32 + 1)
+    }
+}
+
+pub(crate) fn mission_file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct GetPlayersResponse {
     pub players: Players,
@@ -65,6 +82,17 @@ pub struct Players {
     pub all: HashMap<String, Player>,
 }
 
+impl Players {
+    // DCS lists the dedicated server itself as player 1.
+    pub const SERVER_PLAYER_ID: i32 = 1;
+
+    pub fn clients(&self) -> impl Iterator<Item = &Player> {
+        self.all
+            .values()
+            .filter(|player| player.id != Self::SERVER_PLAYER_ID)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Player {
     pub ping: i32,
@@ -103,8 +131,9 @@ pub struct Settings {
     #[serde(rename = "listStartIndex")]
     pub list_start_index: i32,
     pub advanced: AdvancedSettings,
+    #[serde(deserialize_with = "super::deserialize_string_or_int")]
     pub port: i32,
-    pub mode: i32,
+    pub mode: ServerMode,
     pub bind_address: String,
     #[serde(rename = "isPublic")]
     pub is_public: bool,
@@ -122,10 +151,89 @@ pub struct Settings {
     pub mission_list: Vec<String>,
     pub require_pure_clients: bool,
     pub require_pure_models: bool,
-    #[serde(rename = "maxPlayers")]
+    #[serde(
+        rename = "maxPlayers",
+        deserialize_with = "super::deserialize_string_or_int"
+    )]
     pub max_players: i32,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(from = "i32", into = "i32")]
+pub enum ServerMode {
+    Standard,
+    Other(i32),
+}
+
+impl From<i32> for ServerMode {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => ServerMode::Standard,
+            other => ServerMode::Other(other),
+        }
+    }
+}
+
+impl From<ServerMode> for i32 {
+    fn from(mode: ServerMode) -> Self {
+        match mode {
+            ServerMode::Standard => 0,
+            ServerMode::Other(other) => other,
+        }
+    }
+}
+
+impl std::fmt::Display for ServerMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerMode::Standard => write!(f, "standard"),
+            ServerMode::Other(other) => write!(f, "unknown ({other})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(from = "i32", into = "i32")]
+pub enum ResumeMode {
+    PauseOnLoad,
+    RunWhenPlayers,
+    AlwaysRun,
+    Other(i32),
+}
+
+impl From<i32> for ResumeMode {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => ResumeMode::PauseOnLoad,
+            1 => ResumeMode::RunWhenPlayers,
+            2 => ResumeMode::AlwaysRun,
+            other => ResumeMode::Other(other),
+        }
+    }
+}
+
+impl From<ResumeMode> for i32 {
+    fn from(mode: ResumeMode) -> Self {
+        match mode {
+            ResumeMode::PauseOnLoad => 0,
+            ResumeMode::RunWhenPlayers => 1,
+            ResumeMode::AlwaysRun => 2,
+            ResumeMode::Other(other) => other,
+        }
+    }
+}
+
+impl std::fmt::Display for ResumeMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResumeMode::PauseOnLoad => write!(f, "pause on load"),
+            ResumeMode::RunWhenPlayers => write!(f, "run when players"),
+            ResumeMode::AlwaysRun => write!(f, "always run"),
+            ResumeMode::Other(other) => write!(f, "unknown ({other})"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AdvancedSettings {
     pub allow_change_tailno: bool,
@@ -153,7 +261,7 @@ pub struct AdvancedSettings {
     pub event_crash: bool,
     #[serde(rename = "event_Role")]
     pub event_role: bool,
-    pub resume_mode: i32,
+    pub resume_mode: ResumeMode,
     #[serde(rename = "maxPing")]
     pub max_ping: i32,
     pub allow_trial_only_clients: bool,
@@ -52,6 +52,27 @@ pub struct InstanceResource {
     pub runtime: Option<GameRuntime>,
 }
 
+impl InstanceResource {
+    /// The live server name, falling back to the name used at creation.
+    pub fn name(&self) -> Option<&str> {
+        match &self.runtime {
+            Some(GameRuntime::Dcs(runtime)) => Some(runtime.settings.settings.name.as_str()),
+            None => self
+                .instance
+                .dcs_settings
+                .as_ref()
+                .map(|settings| settings.initial_server_name.as_str()),
+        }
+    }
+
+    pub fn player_count(&self) -> usize {
+        match &self.runtime {
+            Some(GameRuntime::Dcs(runtime)) => runtime.players.players.clients().count(),
+            None => 0,
+        }
+    }
+}
+
 pub type InstancesResponse = Vec<InstanceResource>;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -114,6 +135,55 @@ pub enum InstanceStatus {
     },
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum InstanceStatusKind {
+    AwaitingContainer,
+    InstallingBaseGame,
+    InstallingTerrains,
+    InstallingMods,
+    InstallingPost,
+    UninstallingTerrains,
+    ServerStarted,
+    ServerStopped,
+    ServerExpired,
+    ServerDeleted,
+    WantServerStarted,
+    WantServerStopped,
+    WantUpdateServer,
+}
+
+impl InstanceStatus {
+    pub fn kind(&self) -> InstanceStatusKind {
+        match self {
+            InstanceStatus::AwaitingContainer => InstanceStatusKind::AwaitingContainer,
+            InstanceStatus::InstallingBaseGame { .. } => InstanceStatusKind::InstallingBaseGame,
+            InstanceStatus::InstallingTerrains { .. } => InstanceStatusKind::InstallingTerrains,
+            InstanceStatus::InstallingMods => InstanceStatusKind::InstallingMods,
+            InstanceStatus::InstallingPost => InstanceStatusKind::InstallingPost,
+            InstanceStatus::UninstallingTerrains { .. } => InstanceStatusKind::UninstallingTerrains,
+            InstanceStatus::ServerStarted => InstanceStatusKind::ServerStarted,
+            InstanceStatus::ServerStopped { .. } => InstanceStatusKind::ServerStopped,
+            InstanceStatus::ServerExpired => InstanceStatusKind::ServerExpired,
+            InstanceStatus::ServerDeleted => InstanceStatusKind::ServerDeleted,
+            InstanceStatus::WantServerStarted { .. } => InstanceStatusKind::WantServerStarted,
+            InstanceStatus::WantServerStopped { .. } => InstanceStatusKind::WantServerStopped,
+            InstanceStatus::WantUpdateServer { .. } => InstanceStatusKind::WantUpdateServer,
+        }
+    }
+
+    /// Whether the instance is on its way to another state, e.g. installing or
+    /// starting, and should not be acted upon.
+    pub fn is_transitional(&self) -> bool {
+        !matches!(
+            self.kind(),
+            InstanceStatusKind::ServerStarted
+                | InstanceStatusKind::ServerStopped
+                | InstanceStatusKind::ServerExpired
+                | InstanceStatusKind::ServerDeleted
+        )
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum InstanceStoppedReason {
     StoppedNormally,
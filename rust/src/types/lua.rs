@@ -0,0 +1,541 @@
+use std::fmt::Write;
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Number, Value};
+
+use super::dcs_runtime::Settings;
+
+const SERVER_SETTINGS_TABLE: &str = "cfg";
+
+impl Settings {
+    pub fn from_server_settings_lua(source: &str) -> Result<Self> {
+        let value = Parser::new(source)
+            .assignment(SERVER_SETTINGS_TABLE)?
+            .with_context(|| format!("no `{SERVER_SETTINGS_TABLE}` table found"))?;
+
+        serde_json::from_value(value).context("invalid serverSettings.lua")
+    }
+
+    pub fn to_server_settings_lua(&self) -> Result<String> {
+        let value = serde_json::to_value(self)?;
+        let mut output = format!("{SERVER_SETTINGS_TABLE} = \n");
+        write_value(&mut output, &value, 0);
+        writeln!(output, " -- end of {SERVER_SETTINGS_TABLE}")?;
+
+        Ok(output)
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            position: 0,
+        }
+    }
+
+    fn assignment(&mut self, name: &str) -> Result<Option<Value>> {
+        loop {
+            self.skip_trivia();
+            if self.is_eof() {
+                return Ok(None);
+            }
+
+            let identifier = self.identifier()?;
+            self.skip_trivia();
+            self.expect('=')?;
+            let value = self.value()?;
+
+            if identifier == name {
+                return Ok(Some(value));
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_trivia();
+
+        match self.peek() {
+            Some('{') => self.table(),
+            Some('"') | Some('\'') => Ok(Value::String(self.string()?)),
+            Some(c) if c == '-' || c == '.' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_alphabetic() || c == '_' => match self.identifier()?.as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                "nil" => Ok(Value::Null),
+                other => bail!("unsupported expression `{other}` at byte {}", self.position),
+            },
+            Some(c) => bail!("unexpected `{c}` at byte {}", self.position),
+            None => bail!("unexpected end of input"),
+        }
+    }
+
+    fn table(&mut self) -> Result<Value> {
+        self.expect('{')?;
+        let mut entries: Vec<(Value, Value)> = Vec::new();
+        let mut next_index = 1;
+
+        loop {
+            self.skip_trivia();
+            if self.peek() == Some('}') {
+                self.position += 1;
+                break;
+            }
+
+            let key = match self.peek() {
+                Some('[') => {
+                    self.position += 1;
+                    let key = self.value()?;
+                    self.skip_trivia();
+                    self.expect(']')?;
+                    self.skip_trivia();
+                    self.expect('=')?;
+                    key
+                }
+                Some(c) if (c.is_alphabetic() || c == '_') && self.is_named_field() => {
+                    let key = self.identifier()?;
+                    self.skip_trivia();
+                    self.expect('=')?;
+                    Value::String(key)
+                }
+                _ => {
+                    let key = Value::from(next_index);
+                    next_index += 1;
+                    key
+                }
+            };
+
+            let value = self.value()?;
+            if !value.is_null() {
+                entries.push((key, value));
+            }
+
+            self.skip_trivia();
+            match self.peek() {
+                Some(',') | Some(';') => self.position += 1,
+                Some('}') => {}
+                Some(c) => bail!(
+                    "expected `,` or `}}` but found `{c}` at byte {}",
+                    self.position
+                ),
+                None => bail!("unterminated table"),
+            }
+        }
+
+        let is_array = entries
+            .iter()
+            .enumerate()
+            .all(|(index, (key, _))| key.as_u64() == Some(index as u64 + 1));
+
+        if is_array && !entries.is_empty() {
+            return Ok(Value::Array(
+                entries.into_iter().map(|(_, value)| value).collect(),
+            ));
+        }
+
+        let mut map = Map::new();
+        for (key, value) in entries {
+            let key = match key {
+                Value::String(key) => key,
+                other => other.to_string(),
+            };
+            map.insert(key, value);
+        }
+
+        Ok(Value::Object(map))
+    }
+
+    /// Decimal and hex escapes are bytes, so UTF-8 written as `\195\169` is
+    /// decoded as one character.
+    fn string(&mut self) -> Result<String> {
+        let quote = self.next().context("unexpected end of input")?;
+        let mut bytes = Vec::new();
+
+        loop {
+            match self.next() {
+                Some(c) if c == quote => {
+                    return String::from_utf8(bytes).context("string is not valid UTF-8");
+                }
+                Some('\\') => match self.next() {
+                    Some('n') => bytes.push(b'\n'),
+                    Some('t') => bytes.push(b'\t'),
+                    Some('r') => bytes.push(b'\r'),
+                    Some('a') => bytes.push(0x07),
+                    Some('b') => bytes.push(0x08),
+                    Some('f') => bytes.push(0x0c),
+                    Some('v') => bytes.push(0x0b),
+                    Some('x') => {
+                        let digits = self.source.get(self.position..self.position + 2);
+                        let byte = digits
+                            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                            .with_context(|| format!("invalid escape at byte {}", self.position))?;
+                        self.position += 2;
+                        bytes.push(byte);
+                    }
+                    Some(c) if c.is_ascii_digit() => {
+                        let mut code = c.to_digit(10).unwrap_or_default();
+                        for _ in 0..2 {
+                            match self.peek().and_then(|c| c.to_digit(10)) {
+                                Some(digit) => {
+                                    code = code * 10 + digit;
+                                    self.position += 1;
+                                }
+                                None => break,
+                            }
+                        }
+                        bytes.push(u8::try_from(code).with_context(|| {
+                            format!("escape \\{code} is out of range at byte {}", self.position)
+                        })?);
+                    }
+                    Some(c) => push_char(&mut bytes, c),
+                    None => bail!("unterminated string"),
+                },
+                Some(c) => push_char(&mut bytes, c),
+                None => bail!("unterminated string"),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value> {
+        let start = self.position;
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+
+        while let Some(c) = self.peek() {
+            let exponent_sign = matches!(c, '-' | '+')
+                && matches!(self.source[..self.position].chars().last(), Some('e' | 'E'));
+
+            if c.is_ascii_alphanumeric() || c == '.' || exponent_sign {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+
+        let literal = &self.source[start..self.position];
+        if let Ok(integer) = literal.parse::<i64>() {
+            return Ok(Value::from(integer));
+        }
+
+        literal
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .with_context(|| format!("invalid number `{literal}`"))
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' {
+                self.position += c.len_utf8();
+            } else {
+                break;
+            }
+        }
+
+        if start == self.position {
+            bail!("expected identifier at byte {}", self.position);
+        }
+
+        Ok(self.source[start..self.position].to_string())
+    }
+
+    fn is_named_field(&self) -> bool {
+        let rest = &self.source[self.position..];
+        let end = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let after = rest[end..].trim_start();
+
+        after.starts_with('=') && !after.starts_with("==")
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = &self.source[self.position..];
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+
+            if let Some(comment) = trimmed.strip_prefix("--") {
+                let length = if comment.starts_with("[[") {
+                    comment
+                        .find("]]")
+                        .map(|end| end + 2)
+                        .unwrap_or(comment.len())
+                } else {
+                    comment.find('\n').unwrap_or(comment.len())
+                };
+                self.position += 2 + length;
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => bail!(
+                "expected `{expected}` but found `{c}` at byte {}",
+                self.position - c.len_utf8()
+            ),
+            None => bail!("expected `{expected}` but reached end of input"),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn is_eof(&self) -> bool {
+        self.position >= self.source.len()
+    }
+}
+
+fn push_char(bytes: &mut Vec<u8>, c: char) {
+    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+fn write_value(output: &mut String, value: &Value, depth: usize) {
+    let indent = "    ".repeat(depth);
+
+    match value {
+        Value::Null => output.push_str("nil"),
+        Value::Bool(value) => output.push_str(if *value { "true" } else { "false" }),
+        Value::Number(number) => output.push_str(&number.to_string()),
+        Value::String(value) => write_string(output, value),
+        Value::Array(items) => {
+            let _ = writeln!(output, "{indent}{{");
+            for (index, item) in items.iter().enumerate() {
+                let _ = write!(output, "{indent}    [{}] = ", index + 1);
+                write_nested(output, item, depth + 1);
+                output.push_str(",\n");
+            }
+            let _ = write!(output, "{indent}}}");
+        }
+        Value::Object(map) => {
+            let _ = writeln!(output, "{indent}{{");
+            for (key, item) in map.iter().filter(|(_, item)| !item.is_null()) {
+                let _ = write!(output, "{indent}    [");
+                write_string(output, key);
+                output.push_str("] = ");
+                write_nested(output, item, depth + 1);
+                if item.is_object() || item.is_array() {
+                    output.push_str(", -- end of [");
+                    write_string(output, key);
+                    output.push_str("]\n");
+                } else {
+                    output.push_str(",\n");
+                }
+            }
+            let _ = write!(output, "{indent}}}");
+        }
+    }
+}
+
+fn write_nested(output: &mut String, value: &Value, depth: usize) {
+    if value.is_object() || value.is_array() {
+        output.push('\n');
+        write_value(output, value, depth);
+    } else {
+        write_value(output, value, depth);
+    }
+}
+
+fn write_string(output: &mut String, value: &str) {
+    output.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ResumeMode;
+
+    // In the layout DCS writes to Saved Games\DCS.server\Config\serverSettings.lua.
+    const SERVER_SETTINGS: &str = r#"cfg = 
+{
+    ["description"] = "Caf\195\169 training \"open\"\nline two",
+    ["require_pure_textures"] = true,
+    ["listStartIndex"] = 2,
+    ["advanced"] = 
+    {
+        ["allow_change_tailno"] = true,
+        ["disable_events"] = false,
+        ["allow_ownship_export"] = true,
+        ["allow_object_export"] = false,
+        ["pause_on_load"] = false,
+        ["allow_sensor_export"] = false,
+        ["event_Takeoff"] = true,
+        ["pause_without_clients"] = false,
+        ["client_outbound_limit"] = 0,
+        ["client_inbound_limit"] = 0,
+        ["server_can_screenshot"] = false,
+        ["allow_players_pool"] = true,
+        ["voice_chat_server"] = true,
+        ["allow_change_skin"] = true,
+        ["event_Connect"] = true,
+        ["event_Ejecting"] = true,
+        ["event_Kill"] = true,
+        ["event_Crash"] = true,
+        ["event_Role"] = true,
+        ["resume_mode"] = 1,
+        ["maxPing"] = 300,
+        ["allow_trial_only_clients"] = false,
+        ["allow_dynamic_radio"] = true,
+        ["bluePasswordHash"] = "x3NqcWvzsBM:W90Xw+lrA/noe38UbM/pL0JGn2/urbZviwIURl/rvtc",
+    }, -- end of ["advanced"]
+    ["port"] = "10308",
+    ["mode"] = 0,
+    ["bind_address"] = "",
+    ["isPublic"] = true,
+    ["listShuffle"] = false,
+    ["password"] = "",
+    ["listLoop"] = false,
+    ["name"] = "\208\161\208\181\209\128\208\178\208\181\209\128 | Training",
+    ["require_pure_scripts"] = false,
+    ["missionList"] = 
+    {
+        [1] = "C:\\Users\\dcs\\Saved Games\\DCS.server\\Missions\\Training.miz",
+        [2] = "C:\\Users\\dcs\\Saved Games\\DCS.server\\Missions\\Caucasus Op.miz",
+    }, -- end of ["missionList"]
+    ["require_pure_clients"] = false,
+    ["require_pure_models"] = true,
+    ["maxPlayers"] = "16",
+} -- end of cfg
+"#;
+
+    #[test]
+    fn parses_server_settings() {
+        let settings = Settings::from_server_settings_lua(SERVER_SETTINGS).unwrap();
+
+        assert_eq!(settings.name, "Сервер | Training");
+        assert_eq!(settings.description, "Café training \"open\"\nline two");
+        assert_eq!(settings.list_start_index, 2);
+        assert_eq!(settings.port, 10308);
+        assert_eq!(settings.max_players, 16);
+        assert_eq!(settings.advanced.max_ping, 300);
+        assert_eq!(settings.advanced.resume_mode, ResumeMode::RunWhenPlayers);
+        assert_eq!(settings.advanced.red_password_hash, None);
+        assert_eq!(
+            settings.advanced.blue_password_hash.as_deref(),
+            Some("x3NqcWvzsBM:W90Xw+lrA/noe38UbM/pL0JGn2/urbZviwIURl/rvtc")
+        );
+        assert_eq!(
+            settings.mission_list,
+            [
+                r"C:\Users\dcs\Saved Games\DCS.server\Missions\Training.miz",
+                r"C:\Users\dcs\Saved Games\DCS.server\Missions\Caucasus Op.miz",
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_bare_and_quoted_numbers() {
+        let bare = SERVER_SETTINGS
+            .replace(r#"["port"] = "10308""#, r#"["port"] = 10308"#)
+            .replace(r#"["maxPlayers"] = "16""#, r#"["maxPlayers"] = 16"#);
+        assert_eq!(
+            Settings::from_server_settings_lua(&bare).unwrap(),
+            Settings::from_server_settings_lua(SERVER_SETTINGS).unwrap()
+        );
+
+        let invalid = SERVER_SETTINGS.replace(r#"["port"] = "10308""#, r#"["port"] = "any""#);
+        assert!(Settings::from_server_settings_lua(&invalid).is_err());
+    }
+
+    #[test]
+    fn round_trips_server_settings() {
+        let settings = Settings::from_server_settings_lua(SERVER_SETTINGS).unwrap();
+        let written = settings.to_server_settings_lua().unwrap();
+
+        assert!(written.starts_with("cfg = \n{\n"));
+        assert!(written.ends_with("} -- end of cfg\n"));
+        assert!(written.contains("    [\"missionList\"] = \n    {\n        [1] = "));
+        assert!(!written.contains("nil"));
+        assert_eq!(
+            Settings::from_server_settings_lua(&written).unwrap(),
+            settings
+        );
+    }
+
+    #[test]
+    fn round_trips_escapes_and_unicode() {
+        let mut settings = crate::test_support::settings();
+        settings.name = "Tab\there, \"quoted\" \\ back – ünïcödé 日本".to_string();
+        settings.description = "line\r\nbreak".to_string();
+        settings.mission_list = Vec::new();
+
+        let written = settings.to_server_settings_lua().unwrap();
+
+        assert_eq!(
+            Settings::from_server_settings_lua(&written).unwrap(),
+            settings
+        );
+    }
+
+    #[test]
+    fn parses_lua_syntax_variants() {
+        let mut parser = Parser::new(
+            "-- header\n--[[ block\ncomment ]]\nother = { 1, 2 }\n\
+             cfg = { name = 'single', list = { 'a'; 'b', }, [\"n\"] = -1.5e2, \
+             hex = \"\\x41\\65\", skipped = nil, empty = {} }",
+        );
+
+        let value = parser.assignment("cfg").unwrap().unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "name": "single",
+                "list": ["a", "b"],
+                "n": -150.0,
+                "hex": "AA",
+                "empty": {},
+            })
+        );
+        assert_eq!(Parser::new("x = 1").assignment("cfg").unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert!(
+            Parser::new("cfg = { \"unterminated }")
+                .assignment("cfg")
+                .is_err()
+        );
+        assert!(
+            Parser::new("cfg = { a = 1 b = 2 }")
+                .assignment("cfg")
+                .is_err()
+        );
+        assert!(Parser::new("cfg = \"\\300\"").assignment("cfg").is_err());
+        assert!(Parser::new("cfg = \"\\255\"").assignment("cfg").is_err());
+        assert!(Parser::new("cfg = os.getenv").assignment("cfg").is_err());
+        assert!(Settings::from_server_settings_lua("cfg = { name = 1 }").is_err());
+    }
+}
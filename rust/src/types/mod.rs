@@ -2,13 +2,18 @@ use serde::{Deserialize, Deserializer};
 use serde_json::Value;
 
 pub mod billing;
+pub mod coalition;
 pub mod dcs_api;
 pub mod dcs_chat;
 pub mod dcs_runtime;
 pub mod dcs_settings;
 pub mod files;
 pub mod instance;
+pub mod lua;
 pub mod region;
+pub mod resource_stats;
+pub mod settings_patch;
+pub mod settings_validation;
 pub mod srs;
 pub mod system_resources;
 pub mod system_resources_periode;
@@ -42,6 +47,26 @@ where
     }
 }
 
+/// serverSettings.lua written by DCS stores some numbers as strings, e.g.
+/// `["port"] = "10308"`.
+fn deserialize_string_or_int<'de, D>(deserializer: D) -> Result<i32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Value = Deserialize::deserialize(deserializer)?;
+
+    match value {
+        Value::Number(number) => number
+            .as_i64()
+            .and_then(|number| i32::try_from(number).ok())
+            .ok_or_else(|| serde::de::Error::custom(format!("{number} is out of range"))),
+        Value::String(string) => string.trim().parse().map_err(serde::de::Error::custom),
+        _ => Err(serde::de::Error::custom(
+            "Expected a number or a numeric string",
+        )),
+    }
+}
+
 pub fn deserialize_mission_field<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
 where
     D: Deserializer<'de>,
@@ -0,0 +1,341 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::system_resources::{PrometheusSeries, ServerResourcesResponse};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SeriesStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub p95: f64,
+}
+
+impl SeriesStats {
+    /// Returns `None` when there are no values.
+    pub fn from_values(values: impl IntoIterator<Item = f64>) -> Option<Self> {
+        let mut values: Vec<f64> = values.into_iter().collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+
+        let count = values.len();
+        // Nearest-rank percentile.
+        let p95_rank = ((count as f64 * 0.95).ceil() as usize).clamp(1, count);
+
+        Some(Self {
+            count,
+            min: values[0],
+            max: values[count - 1],
+            mean: values.iter().sum::<f64>() / count as f64,
+            p95: values[p95_rank - 1],
+        })
+    }
+}
+
+impl PrometheusSeries {
+    /// Samples with parsed values. Values that are not finite numbers are
+    /// skipped.
+    pub fn samples(&self) -> Vec<(SystemTime, f64)> {
+        self.values
+            .iter()
+            .filter_map(|(timestamp, value)| {
+                let value = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|value| value.is_finite())?;
+                Some((to_system_time(*timestamp)?, value))
+            })
+            .collect()
+    }
+
+    pub fn stats(&self) -> Option<SeriesStats> {
+        SeriesStats::from_values(self.samples().into_iter().map(|(_, value)| value))
+    }
+
+    /// Averages the samples into buckets of `step`, aligned to the Unix epoch
+    /// so buckets of different series line up.
+    pub fn resample(&self, step: Duration) -> Vec<(SystemTime, f64)> {
+        resample(self.samples(), step)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ResourceSummary {
+    pub cpu: Option<SeriesStats>,
+    /// RAM usage in percent of `max_ram`.
+    pub ram_percent: Option<SeriesStats>,
+    pub server_fps: Option<SeriesStats>,
+    pub players: Option<SeriesStats>,
+}
+
+/// One bucket of [`ServerResourcesResponse::aligned`]. Fields are `None` when
+/// the series has no sample in the bucket.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct AlignedSample {
+    pub time: SystemTime,
+    pub cpu: Option<f64>,
+    pub ram_percent: Option<f64>,
+    pub server_fps: Option<f64>,
+    pub players: Option<f64>,
+}
+
+impl ServerResourcesResponse {
+    /// CPU usage, averaged across the per-CPU series.
+    pub fn cpu_samples(&self) -> Vec<(SystemTime, f64)> {
+        merge(&self.cpus, Merge::Mean)
+    }
+
+    /// RAM usage in bytes, summed across series.
+    pub fn ram_samples(&self) -> Vec<(SystemTime, f64)> {
+        merge(&self.ram, Merge::Sum)
+    }
+
+    pub fn ram_percent_samples(&self) -> Vec<(SystemTime, f64)> {
+        if self.max_ram == 0 {
+            return Vec::new();
+        }
+
+        self.ram_samples()
+            .into_iter()
+            .map(|(time, ram)| (time, ram / self.max_ram as f64 * 100.0))
+            .collect()
+    }
+
+    pub fn server_fps_samples(&self) -> Vec<(SystemTime, f64)> {
+        merge(&self.server_fps, Merge::Mean)
+    }
+
+    pub fn player_samples(&self) -> Vec<(SystemTime, f64)> {
+        merge(&self.players, Merge::Sum)
+    }
+
+    pub fn summary(&self) -> ResourceSummary {
+        let stats = |samples: Vec<(SystemTime, f64)>| {
+            SeriesStats::from_values(samples.into_iter().map(|(_, value)| value))
+        };
+
+        ResourceSummary {
+            cpu: stats(self.cpu_samples()),
+            ram_percent: stats(self.ram_percent_samples()),
+            server_fps: stats(self.server_fps_samples()),
+            players: stats(self.player_samples()),
+        }
+    }
+
+    /// Resamples every series onto a shared grid of `step`.
+    pub fn aligned(&self, step: Duration) -> Vec<AlignedSample> {
+        let grid = |samples| -> BTreeMap<SystemTime, f64> {
+            resample(samples, step).into_iter().collect()
+        };
+        let cpu = grid(self.cpu_samples());
+        let ram_percent = grid(self.ram_percent_samples());
+        let server_fps = grid(self.server_fps_samples());
+        let players = grid(self.player_samples());
+
+        let mut times: Vec<SystemTime> = cpu
+            .keys()
+            .chain(ram_percent.keys())
+            .chain(server_fps.keys())
+            .chain(players.keys())
+            .copied()
+            .collect();
+        times.sort_unstable();
+        times.dedup();
+
+        times
+            .into_iter()
+            .map(|time| AlignedSample {
+                time,
+                cpu: cpu.get(&time).copied(),
+                ram_percent: ram_percent.get(&time).copied(),
+                server_fps: server_fps.get(&time).copied(),
+                players: players.get(&time).copied(),
+            })
+            .collect()
+    }
+
+    /// Pearson correlation between server FPS and player count over buckets
+    /// of `step`. Strongly negative values mean FPS drops as players join.
+    pub fn fps_player_correlation(&self, step: Duration) -> Option<f64> {
+        let pairs: Vec<(f64, f64)> = self
+            .aligned(step)
+            .into_iter()
+            .filter_map(|sample| Some((sample.server_fps?, sample.players?)))
+            .collect();
+
+        correlation(&pairs)
+    }
+}
+
+enum Merge {
+    Sum,
+    Mean,
+}
+
+fn merge(series: &[PrometheusSeries], merge: Merge) -> Vec<(SystemTime, f64)> {
+    let mut merged: BTreeMap<SystemTime, (f64, usize)> = BTreeMap::new();
+    for (time, value) in series.iter().flat_map(PrometheusSeries::samples) {
+        let entry = merged.entry(time).or_default();
+        entry.0 += value;
+        entry.1 += 1;
+    }
+
+    merged
+        .into_iter()
+        .map(|(time, (sum, count))| match merge {
+            Merge::Sum => (time, sum),
+            Merge::Mean => (time, sum / count as f64),
+        })
+        .collect()
+}
+
+fn resample(samples: Vec<(SystemTime, f64)>, step: Duration) -> Vec<(SystemTime, f64)> {
+    let step = step.as_secs_f64();
+    if step <= 0.0 {
+        return samples;
+    }
+
+    let mut buckets: BTreeMap<u64, (f64, usize)> = BTreeMap::new();
+    for (time, value) in samples {
+        let Ok(since_epoch) = time.duration_since(UNIX_EPOCH) else {
+            continue;
+        };
+        let bucket = (since_epoch.as_secs_f64() / step).floor() as u64;
+        let entry = buckets.entry(bucket).or_default();
+        entry.0 += value;
+        entry.1 += 1;
+    }
+
+    buckets
+        .into_iter()
+        .filter_map(|(bucket, (sum, count))| {
+            Some((to_system_time(bucket as f64 * step)?, sum / count as f64))
+        })
+        .collect()
+}
+
+fn correlation(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 2 {
+        return None;
+    }
+
+    let count = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / count;
+
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+
+    let denominator = (variance_x * variance_y).sqrt();
+    (denominator > 0.0).then(|| covariance / denominator)
+}
+
+fn to_system_time(timestamp: f64) -> Option<SystemTime> {
+    UNIX_EPOCH.checked_add(Duration::try_from_secs_f64(timestamp).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(values: &[(f64, &str)]) -> PrometheusSeries {
+        PrometheusSeries {
+            values: values
+                .iter()
+                .map(|(time, value)| (*time, value.to_string()))
+                .collect(),
+        }
+    }
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn resources() -> ServerResourcesResponse {
+        ServerResourcesResponse {
+            cpus: vec![
+                series(&[(0.0, "10"), (60.0, "30")]),
+                series(&[(0.0, "30"), (60.0, "50")]),
+            ],
+            ram: vec![series(&[(0.0, "512"), (60.0, "1024")])],
+            max_ram: 2048,
+            players: vec![series(&[(0.0, "0"), (60.0, "10"), (120.0, "20")])],
+            server_fps: vec![series(&[(0.0, "60"), (60.0, "40"), (120.0, "20")])],
+        }
+    }
+
+    #[test]
+    fn parses_samples_and_skips_invalid_values() {
+        let series = series(&[(0.0, "1.5"), (30.0, "NaN"), (-1.0, "2"), (60.0, "oops")]);
+        assert_eq!(series.samples(), [(at(0), 1.5)]);
+    }
+
+    #[test]
+    fn computes_series_stats() {
+        assert_eq!(SeriesStats::from_values([]), None);
+
+        let stats = SeriesStats::from_values((1..=20).map(f64::from)).unwrap();
+        assert_eq!(stats.count, 20);
+        assert_eq!((stats.min, stats.max), (1.0, 20.0));
+        assert_eq!(stats.mean, 10.5);
+        assert_eq!(stats.p95, 19.0);
+
+        let single = SeriesStats::from_values([7.0]).unwrap();
+        assert_eq!(single.p95, 7.0);
+    }
+
+    #[test]
+    fn merges_series() {
+        let resources = resources();
+
+        assert_eq!(resources.cpu_samples(), [(at(0), 20.0), (at(60), 40.0)]);
+        assert_eq!(
+            resources.ram_percent_samples(),
+            [(at(0), 25.0), (at(60), 50.0)]
+        );
+
+        let summary = resources.summary();
+        assert_eq!(summary.players.unwrap().max, 20.0);
+        assert_eq!(summary.ram_percent.unwrap().mean, 37.5);
+
+        let no_ram = ServerResourcesResponse {
+            max_ram: 0,
+            ..resources
+        };
+        assert!(no_ram.ram_percent_samples().is_empty());
+    }
+
+    #[test]
+    fn resamples_onto_aligned_buckets() {
+        let series = series(&[(0.0, "1"), (30.0, "3"), (90.0, "5")]);
+        assert_eq!(
+            series.resample(Duration::from_secs(60)),
+            [(at(0), 2.0), (at(60), 5.0)]
+        );
+
+        let aligned = resources().aligned(Duration::from_secs(60));
+        assert_eq!(aligned.len(), 3);
+        assert_eq!(aligned[2].cpu, None);
+        assert_eq!(aligned[2].players, Some(20.0));
+    }
+
+    #[test]
+    fn correlates_fps_with_players() {
+        let fps_players = resources()
+            .fps_player_correlation(Duration::from_secs(60))
+            .unwrap();
+        assert!((fps_players + 1.0).abs() < 1e-9);
+
+        assert_eq!(correlation(&[(1.0, 1.0)]), None);
+        assert_eq!(correlation(&[(1.0, 1.0), (1.0, 2.0)]), None);
+    }
+}
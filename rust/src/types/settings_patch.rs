@@ -0,0 +1,315 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::dcs_runtime::{ResumeMode, ServerMode, Settings};
+
+macro_rules! apply_fields {
+    ($patch:expr, $target:expr, $changed:expr, $prefix:literal, [$($field:ident),* $(,)?]) => {
+        $(
+            if let Some(value) = &$patch.$field {
+                if $target.$field != *value {
+                    $target.$field = value.clone();
+                    $changed.push(concat!($prefix, stringify!($field)));
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! mismatched_fields {
+    ($patch:expr, $target:expr, $mismatched:expr, $prefix:literal, [$($field:ident),* $(,)?]) => {
+        $(
+            if let Some(value) = &$patch.$field {
+                if $target.$field != *value {
+                    $mismatched.push(concat!($prefix, stringify!($field)));
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! settings_fields {
+    ($callback:ident, $patch:expr, $target:expr, $out:expr) => {
+        $callback!(
+            $patch,
+            $target,
+            $out,
+            "",
+            [
+                description,
+                require_pure_textures,
+                list_start_index,
+                port,
+                mode,
+                bind_address,
+                is_public,
+                list_shuffle,
+                password,
+                list_loop,
+                name,
+                require_pure_scripts,
+                mission_list,
+                require_pure_clients,
+                require_pure_models,
+                max_players,
+            ]
+        )
+    };
+}
+
+macro_rules! advanced_fields {
+    ($callback:ident, $patch:expr, $target:expr, $out:expr) => {
+        $callback!(
+            $patch,
+            $target,
+            $out,
+            "advanced.",
+            [
+                allow_change_tailno,
+                disable_events,
+                allow_ownship_export,
+                allow_object_export,
+                pause_on_load,
+                allow_sensor_export,
+                event_takeoff,
+                pause_without_clients,
+                client_outbound_limit,
+                client_inbound_limit,
+                server_can_screenshot,
+                allow_players_pool,
+                voice_chat_server,
+                allow_change_skin,
+                event_connect,
+                event_ejecting,
+                event_kill,
+                event_crash,
+                event_role,
+                resume_mode,
+                max_ping,
+                allow_trial_only_clients,
+                allow_dynamic_radio,
+                red_password_hash,
+                blue_password_hash,
+                red_password,
+                blue_password,
+            ]
+        )
+    };
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SettingsPatch {
+    pub description: Option<String>,
+    pub require_pure_textures: Option<bool>,
+    #[serde(rename = "listStartIndex", alias = "list_start_index")]
+    pub list_start_index: Option<i32>,
+    pub advanced: AdvancedSettingsPatch,
+    pub port: Option<i32>,
+    pub mode: Option<ServerMode>,
+    pub bind_address: Option<String>,
+    #[serde(rename = "isPublic", alias = "is_public")]
+    pub is_public: Option<bool>,
+    #[serde(rename = "listShuffle", alias = "list_shuffle")]
+    pub list_shuffle: Option<bool>,
+    pub password: Option<String>,
+    #[serde(rename = "listLoop", alias = "list_loop")]
+    pub list_loop: Option<bool>,
+    pub name: Option<String>,
+    pub require_pure_scripts: Option<bool>,
+    #[serde(rename = "missionList", alias = "mission_list")]
+    pub mission_list: Option<Vec<String>>,
+    pub require_pure_clients: Option<bool>,
+    pub require_pure_models: Option<bool>,
+    #[serde(rename = "maxPlayers", alias = "max_players")]
+    pub max_players: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AdvancedSettingsPatch {
+    pub allow_change_tailno: Option<bool>,
+    pub disable_events: Option<bool>,
+    pub allow_ownship_export: Option<bool>,
+    pub allow_object_export: Option<bool>,
+    pub pause_on_load: Option<bool>,
+    pub allow_sensor_export: Option<bool>,
+    #[serde(rename = "event_Takeoff", alias = "event_takeoff")]
+    pub event_takeoff: Option<bool>,
+    pub pause_without_clients: Option<bool>,
+    pub client_outbound_limit: Option<i32>,
+    pub client_inbound_limit: Option<i32>,
+    pub server_can_screenshot: Option<bool>,
+    pub allow_players_pool: Option<bool>,
+    pub voice_chat_server: Option<bool>,
+    pub allow_change_skin: Option<bool>,
+    #[serde(rename = "event_Connect", alias = "event_connect")]
+    pub event_connect: Option<bool>,
+    #[serde(rename = "event_Ejecting", alias = "event_ejecting")]
+    pub event_ejecting: Option<bool>,
+    #[serde(rename = "event_Kill", alias = "event_kill")]
+    pub event_kill: Option<bool>,
+    #[serde(rename = "event_Crash", alias = "event_crash")]
+    pub event_crash: Option<bool>,
+    #[serde(rename = "event_Role", alias = "event_role")]
+    pub event_role: Option<bool>,
+    pub resume_mode: Option<ResumeMode>,
+    #[serde(rename = "maxPing", alias = "max_ping")]
+    pub max_ping: Option<i32>,
+    pub allow_trial_only_clients: Option<bool>,
+    pub allow_dynamic_radio: Option<bool>,
+    #[serde(
+        rename = "redPasswordHash",
+        alias = "red_password_hash",
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub red_password_hash: Option<Option<String>>,
+    #[serde(
+        rename = "bluePasswordHash",
+        alias = "blue_password_hash",
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub blue_password_hash: Option<Option<String>>,
+    #[serde(
+        rename = "redPassword",
+        alias = "red_password",
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub red_password: Option<Option<String>>,
+    #[serde(
+        rename = "bluePassword",
+        alias = "blue_password",
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub blue_password: Option<Option<String>>,
+}
+
+impl SettingsPatch {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Applies every set field to `settings` and returns the names of the
+    /// fields whose value actually changed.
+    pub fn apply(&self, settings: &mut Settings) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        settings_fields!(apply_fields, self, settings, changed);
+        advanced_fields!(apply_fields, self.advanced, settings.advanced, changed);
+        changed
+    }
+
+    /// Names of the set fields that do not match `settings`. Plaintext
+    /// passwords are left out, since servers may report them hashed.
+    pub fn mismatches(&self, settings: &Settings) -> Vec<&'static str> {
+        let mut mismatched = Vec::new();
+        settings_fields!(mismatched_fields, self, settings, mismatched);
+        advanced_fields!(
+            mismatched_fields,
+            self.advanced,
+            settings.advanced,
+            mismatched
+        );
+        mismatched.retain(|field| !PASSWORD_FIELDS.contains(field));
+        mismatched
+    }
+}
+
+const PASSWORD_FIELDS: [&str; 3] = [
+    "password",
+    "advanced.red_password",
+    "advanced.blue_password",
+];
+
+/// Tells an explicit `null`, which clears the value, apart from a missing key.
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+pub(super) use {advanced_fields, settings_fields};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::settings;
+
+    #[test]
+    fn null_clears_and_missing_keeps() {
+        let patch: SettingsPatch =
+            serde_json::from_str(r#"{"advanced": {"redPassword": null, "bluePassword": "blue"}}"#)
+                .unwrap();
+
+        assert_eq!(patch.advanced.red_password, Some(None));
+        assert_eq!(patch.advanced.blue_password, Some(Some("blue".to_string())));
+        assert_eq!(patch.advanced.red_password_hash, None);
+
+        let json = serde_json::to_value(&patch).unwrap();
+        let advanced = json["advanced"].as_object().unwrap();
+        assert_eq!(advanced["redPassword"], serde_json::Value::Null);
+        assert_eq!(advanced["bluePassword"], "blue");
+        assert!(!advanced.contains_key("redPasswordHash"));
+    }
+
+    #[test]
+    fn field_names_match_settings() {
+        let patch: SettingsPatch = serde_json::from_str(
+            r#"{"maxPlayers": 32, "isPublic": false, "advanced": {"maxPing": 300, "event_Kill": false}}"#,
+        )
+        .unwrap();
+        assert_eq!(patch.max_players, Some(32));
+        assert_eq!(patch.is_public, Some(false));
+        assert_eq!(patch.advanced.max_ping, Some(300));
+        assert_eq!(patch.advanced.event_kill, Some(false));
+
+        let snake_case: SettingsPatch = toml::from_str("max_players = 32").unwrap();
+        assert_eq!(snake_case.max_players, Some(32));
+
+        let json = serde_json::to_value(&patch).unwrap();
+        assert_eq!(json["maxPlayers"], 32);
+        assert_eq!(json["advanced"]["maxPing"], 300);
+    }
+
+    #[test]
+    fn apply_reports_changed_fields() {
+        let mut settings = settings();
+        let patch = SettingsPatch {
+            name: Some(settings.name.clone()),
+            max_players: Some(32),
+            advanced: AdvancedSettingsPatch {
+                red_password: Some(Some("red".to_string())),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            patch.apply(&mut settings),
+            ["max_players", "advanced.red_password"]
+        );
+        assert_eq!(settings.max_players, 32);
+        assert_eq!(settings.advanced.red_password.as_deref(), Some("red"));
+        assert!(patch.apply(&mut settings).is_empty());
+    }
+
+    #[test]
+    fn mismatches_skip_plaintext_passwords() {
+        let settings = settings();
+        let patch = SettingsPatch {
+            password: Some("secret".to_string()),
+            max_players: Some(32),
+            advanced: AdvancedSettingsPatch {
+                blue_password: Some(Some("blue".to_string())),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(patch.mismatches(&settings), ["max_players"]);
+    }
+}
@@ -0,0 +1,334 @@
+use std::fmt;
+
+use super::dcs_runtime::{ResumeMode, ServerMode, Settings};
+use super::settings_patch::{advanced_fields, settings_fields};
+
+const MAX_PLAYERS_LIMIT: i32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IssueSeverity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingsIssue {
+    pub field: &'static str,
+    pub severity: IssueSeverity,
+    pub message: String,
+}
+
+impl SettingsIssue {
+    pub fn error(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            severity: IssueSeverity::Error,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            severity: IssueSeverity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SettingsIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            IssueSeverity::Warning => "warning",
+            IssueSeverity::Error => "error",
+        };
+        write!(f, "{severity}: {}: {}", self.field, self.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingChange {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+impl fmt::Display for SettingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.old, self.new)
+    }
+}
+
+trait DiffValue {
+    fn render(&self) -> String;
+}
+
+impl DiffValue for bool {
+    fn render(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl DiffValue for i32 {
+    fn render(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl DiffValue for String {
+    fn render(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+impl DiffValue for ServerMode {
+    fn render(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl DiffValue for ResumeMode {
+    fn render(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl<T: DiffValue> DiffValue for Option<T> {
+    fn render(&self) -> String {
+        match self {
+            Some(value) => value.render(),
+            None => "unset".to_string(),
+        }
+    }
+}
+
+impl<T: DiffValue> DiffValue for Vec<T> {
+    fn render(&self) -> String {
+        let items: Vec<_> = self.iter().map(DiffValue::render).collect();
+        format!("[{}]", items.join(", "))
+    }
+}
+
+fn mask(value: String) -> String {
+    match value.as_str() {
+        "unset" | "\"\"" => value,
+        _ => "***".to_string(),
+    }
+}
+
+macro_rules! diff_fields {
+    ($old:expr, $new:expr, $changes:expr, $prefix:literal, [$($field:ident),* $(,)?]) => {
+        $(
+            if $old.$field != $new.$field {
+                let field = concat!($prefix, stringify!($field));
+                let (mut old, mut new) = ($old.$field.render(), $new.$field.render());
+                if field.contains("password") {
+                    old = mask(old);
+                    new = mask(new);
+                }
+                $changes.push(SettingChange { field, old, new });
+            }
+        )*
+    };
+}
+
+impl Settings {
+    /// Checks the settings for values DCS rejects or that are likely mistakes.
+    /// Mission files are not checked here, see `Client::validate_settings`.
+    pub fn validate(&self) -> Vec<SettingsIssue> {
+        let mut issues = Vec::new();
+        let advanced = &self.advanced;
+
+        if self.name.trim().is_empty() {
+            issues.push(SettingsIssue::error("name", "server name is empty"));
+        }
+
+        if !(1..=65535).contains(&self.port) {
+            issues.push(SettingsIssue::error(
+                "port",
+                format!("{} is not a valid port", self.port),
+            ));
+        }
+
+        if !(1..=MAX_PLAYERS_LIMIT).contains(&self.max_players) {
+            issues.push(SettingsIssue::error(
+                "max_players",
+                format!("must be between 1 and {MAX_PLAYERS_LIMIT}"),
+            ));
+        }
+
+        if let ServerMode::Other(mode) = self.mode {
+            issues.push(SettingsIssue::warning(
+                "mode",
+                format!("unknown mode {mode}"),
+            ));
+        }
+
+        if !self.mission_list.is_empty()
+            && !(1..=self.mission_list.len() as i32).contains(&self.list_start_index)
+        {
+            issues.push(SettingsIssue::error(
+                "list_start_index",
+                format!(
+                    "{} is outside the mission list (1..={})",
+                    self.list_start_index,
+                    self.mission_list.len()
+                ),
+            ));
+        }
+
+        for (field, limit) in [
+            (
+                "advanced.client_inbound_limit",
+                advanced.client_inbound_limit,
+            ),
+            (
+                "advanced.client_outbound_limit",
+                advanced.client_outbound_limit,
+            ),
+        ] {
+            if limit < 0 {
+                issues.push(SettingsIssue::error(
+                    field,
+                    "must be 0 (unlimited) or a positive byte rate",
+                ));
+            }
+        }
+
+        if let ResumeMode::Other(mode) = advanced.resume_mode {
+            issues.push(SettingsIssue::error(
+                "advanced.resume_mode",
+                format!("unknown resume mode {mode}"),
+            ));
+        }
+
+        if advanced.max_ping < 0 {
+            issues.push(SettingsIssue::error(
+                "advanced.max_ping",
+                "must be 0 (disabled) or positive",
+            ));
+        }
+
+        for (side, field, password, hash) in [
+            (
+                "red",
+                "advanced.red_password",
+                &advanced.red_password,
+                &advanced.red_password_hash,
+            ),
+            (
+                "blue",
+                "advanced.blue_password",
+                &advanced.blue_password,
+                &advanced.blue_password_hash,
+            ),
+        ] {
+            match (password, hash) {
+                (Some(_), Some(_)) => issues.push(SettingsIssue::error(
+                    field,
+                    format!("both a plain {side} password and a {side} password hash are set"),
+                )),
+                (Some(password), None) if password.is_empty() => {
+                    issues.push(SettingsIssue::warning(
+                        field,
+                        format!("empty {side} password leaves the coalition open"),
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        if advanced.allow_object_export {
+            issues.push(SettingsIssue::warning(
+                "advanced.allow_object_export",
+                "clients can export the position of every object",
+            ));
+        }
+
+        if advanced.allow_sensor_export {
+            issues.push(SettingsIssue::warning(
+                "advanced.allow_sensor_export",
+                "clients can export sensor data",
+            ));
+        }
+
+        issues
+    }
+
+    pub fn diff(&self, other: &Settings) -> Vec<SettingChange> {
+        let mut changes = Vec::new();
+        settings_fields!(diff_fields, self, other, changes);
+        advanced_fields!(diff_fields, self.advanced, other.advanced, changes);
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::settings;
+
+    #[test]
+    fn valid_settings_have_no_issues() {
+        assert!(settings().validate().is_empty());
+    }
+
+    #[test]
+    fn validate_reports_errors_and_warnings() {
+        let mut settings = settings();
+        settings.name = "  ".to_string();
+        settings.port = 0;
+        settings.max_players = 300;
+        settings.list_start_index = 2;
+        settings.advanced.client_inbound_limit = -1;
+        settings.advanced.resume_mode = ResumeMode::Other(7);
+        settings.advanced.red_password = Some("red".to_string());
+        settings.advanced.red_password_hash = Some("salt:hash".to_string());
+        settings.advanced.blue_password = Some(String::new());
+        settings.advanced.allow_sensor_export = true;
+
+        let issues: Vec<_> = settings
+            .validate()
+            .into_iter()
+            .map(|issue| (issue.field, issue.severity))
+            .collect();
+
+        assert_eq!(
+            issues,
+            [
+                ("name", IssueSeverity::Error),
+                ("port", IssueSeverity::Error),
+                ("max_players", IssueSeverity::Error),
+                ("list_start_index", IssueSeverity::Error),
+                ("advanced.client_inbound_limit", IssueSeverity::Error),
+                ("advanced.resume_mode", IssueSeverity::Error),
+                ("advanced.red_password", IssueSeverity::Error),
+                ("advanced.blue_password", IssueSeverity::Warning),
+                ("advanced.allow_sensor_export", IssueSeverity::Warning),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_lists_changes_and_masks_passwords() {
+        let old = settings();
+        let mut new = old.clone();
+        new.max_players = 32;
+        new.password = "secret".to_string();
+        new.advanced.blue_password = Some("blue".to_string());
+        new.advanced.resume_mode = ResumeMode::AlwaysRun;
+
+        let changes: Vec<_> = old.diff(&new).iter().map(ToString::to_string).collect();
+
+        assert_eq!(
+            changes,
+            [
+                "password: \"\" -> ***",
+                "max_players: 16 -> 32",
+                "advanced.resume_mode: run when players -> always run",
+                "advanced.blue_password: unset -> ***",
+            ]
+        );
+        assert!(old.diff(&old).is_empty());
+    }
+}
@@ -1,3 +1,7 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use croner::Cron;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -46,6 +50,12 @@ pub struct Trigger {
     pub last_executed_at: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -55,3 +65,234 @@ pub struct CreateTriggerRequest {
     pub condition: TriggerCondition,
     pub action: TriggerAction,
 }
+
+impl Trigger {
+    /// The request that would create this trigger, e.g. to copy it to
+    /// another server.
+    pub fn to_request(&self) -> CreateTriggerRequest {
+        CreateTriggerRequest {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            condition: self.condition.clone(),
+            action: self.action.clone(),
+        }
+    }
+}
+
+/// Partial trigger update; `None` fields are left unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UpdateTriggerRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<TriggerCondition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<TriggerAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+}
+
+impl ComparisonOperator {
+    pub fn compare(&self, value: u32, threshold: u32) -> bool {
+        match self {
+            ComparisonOperator::Equal => value == threshold,
+            ComparisonOperator::NotEqual => value != threshold,
+            ComparisonOperator::GreaterThan => value > threshold,
+            ComparisonOperator::GreaterThanOrEqual => value >= threshold,
+            ComparisonOperator::LessThan => value < threshold,
+            ComparisonOperator::LessThanOrEqual => value <= threshold,
+        }
+    }
+}
+
+impl std::fmt::Display for ComparisonOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            ComparisonOperator::Equal => "==",
+            ComparisonOperator::NotEqual => "!=",
+            ComparisonOperator::GreaterThan => ">",
+            ComparisonOperator::GreaterThanOrEqual => ">=",
+            ComparisonOperator::LessThan => "<",
+            ComparisonOperator::LessThanOrEqual => "<=",
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+impl TriggerCondition {
+    /// Checks cron syntax and that player count conditions can be both met
+    /// and missed with at most `max_players` players.
+    pub fn validate(&self, max_players: Option<u32>) -> Result<()> {
+        match self {
+            TriggerCondition::PlayerCount {
+                operator,
+                threshold,
+            } => {
+                let max = max_players.unwrap_or(u32::MAX);
+                if *threshold > max {
+                    bail!("threshold {threshold} exceeds the {max} player slots");
+                }
+
+                // Comparisons only change around the threshold and the bounds.
+                let counts = [
+                    0,
+                    threshold.saturating_sub(1),
+                    *threshold,
+                    threshold.saturating_add(1).min(max),
+                    max,
+                ];
+                let met = counts
+                    .iter()
+                    .any(|count| operator.compare(*count, *threshold));
+                let missed = counts
+                    .iter()
+                    .any(|count| !operator.compare(*count, *threshold));
+                if !met {
+                    bail!("player count {operator} {threshold} can never be met");
+                }
+                if !missed {
+                    bail!("player count {operator} {threshold} is always met");
+                }
+            }
+            TriggerCondition::OnEvent { event_type } => {
+                if event_type.trim().is_empty() {
+                    bail!("event type is empty");
+                }
+            }
+            TriggerCondition::Schedule { cron_expression } => {
+                Cron::from_str(cron_expression)
+                    .with_context(|| format!("invalid cron expression: {cron_expression}"))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TriggerAction {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            TriggerAction::ExecuteLuaScript { script } if script.trim().is_empty() => {
+                bail!("lua script is empty")
+            }
+            TriggerAction::SendChatMessage { message } if message.trim().is_empty() => {
+                bail!("chat message is empty")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl CreateTriggerRequest {
+    pub fn validate(&self, max_players: Option<u32>) -> Result<()> {
+        if self.name.trim().is_empty() {
+            bail!("trigger name is empty");
+        }
+
+        self.condition
+            .validate(max_players)
+            .with_context(|| format!("trigger {}", self.name))?;
+        self.action
+            .validate()
+            .with_context(|| format!("trigger {}", self.name))
+    }
+}
+
+/// Builds a [`CreateTriggerRequest`], validating it locally on
+/// [`TriggerBuilder::build`].
+#[derive(Debug, Clone, Default)]
+pub struct TriggerBuilder {
+    name: String,
+    description: Option<String>,
+    condition: Option<TriggerCondition>,
+    action: Option<TriggerAction>,
+    max_players: Option<u32>,
+}
+
+impl TriggerBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn player_count(mut self, operator: ComparisonOperator, threshold: u32) -> Self {
+        self.condition = Some(TriggerCondition::PlayerCount {
+            operator,
+            threshold,
+        });
+        self
+    }
+
+    pub fn on_event(mut self, event_type: impl Into<String>) -> Self {
+        self.condition = Some(TriggerCondition::OnEvent {
+            event_type: event_type.into(),
+        });
+        self
+    }
+
+    pub fn schedule(mut self, cron_expression: impl Into<String>) -> Self {
+        self.condition = Some(TriggerCondition::Schedule {
+            cron_expression: cron_expression.into(),
+        });
+        self
+    }
+
+    pub fn action(mut self, action: TriggerAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn restart(self) -> Self {
+        self.action(TriggerAction::RestartInstance)
+    }
+
+    pub fn stop(self) -> Self {
+        self.action(TriggerAction::StopInstance)
+    }
+
+    pub fn lua(self, script: impl Into<String>) -> Self {
+        self.action(TriggerAction::ExecuteLuaScript {
+            script: script.into(),
+        })
+    }
+
+    pub fn chat(self, message: impl Into<String>) -> Self {
+        self.action(TriggerAction::SendChatMessage {
+            message: message.into(),
+        })
+    }
+
+    /// Player slots of the server, to reject player count thresholds it can
+    /// never reach.
+    pub fn max_players(mut self, max_players: u32) -> Self {
+        self.max_players = Some(max_players);
+        self
+    }
+
+    pub fn build(self) -> Result<CreateTriggerRequest> {
+        let Some(condition) = self.condition else {
+            bail!("trigger {} has no condition", self.name);
+        };
+        let Some(action) = self.action else {
+            bail!("trigger {} has no action", self.name);
+        };
+
+        let request = CreateTriggerRequest {
+            name: self.name,
+            description: self.description,
+            condition,
+            action,
+        };
+        request.validate(self.max_players)?;
+        Ok(request)
+    }
+}
@@ -0,0 +1,621 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::{Mutex, Semaphore};
+use uuid::Uuid;
+
+use crate::{BoxFuture, Event, EventKind, EventSink, TimedEvent, unix_now};
+
+pub const SIGNATURE_HEADER: &str = "X-NimbusPulse-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-NimbusPulse-Timestamp";
+pub const EVENT_HEADER: &str = "X-NimbusPulse-Event";
+
+/// An outgoing webhook. Receives every event unless limited with
+/// [`Webhook::events`] or [`Webhook::servers`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub events: Option<Vec<EventKind>>,
+    #[serde(default)]
+    pub servers: Option<Vec<Uuid>>,
+}
+
+impl Webhook {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            secret: None,
+            events: None,
+            servers: None,
+        }
+    }
+
+    /// Signs payloads with HMAC-SHA256, see [`sign_webhook`].
+    pub fn secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    pub fn events(mut self, kinds: impl IntoIterator<Item = EventKind>) -> Self {
+        self.events = Some(kinds.into_iter().collect());
+        self
+    }
+
+    pub fn servers(mut self, ids: impl IntoIterator<Item = Uuid>) -> Self {
+        self.servers = Some(ids.into_iter().collect());
+        self
+    }
+
+    pub fn accepts(&self, event: &Event) -> bool {
+        self.events
+            .as_ref()
+            .is_none_or(|kinds| kinds.contains(&event.kind()))
+            && self
+                .servers
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&event.instance_id()))
+    }
+}
+
+/// A delivery that ran out of attempts, as stored in the dead-letter file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub url: String,
+    pub payload: TimedEvent,
+    pub attempts: u32,
+    pub error: String,
+}
+
+/// Signature of a payload: hex encoded HMAC-SHA256 of `"{timestamp}.{body}"`,
+/// prefixed with `sha256=`. Sent in [`SIGNATURE_HEADER`], with the time of the
+/// delivery attempt in [`TIMESTAMP_HEADER`] so receivers can reject replays.
+pub fn sign_webhook(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("sha256={digest}")
+}
+
+/// Checks a signature produced by [`sign_webhook`] in constant time.
+pub fn verify_webhook(secret: &str, timestamp: i64, body: &[u8], signature: &str) -> bool {
+    let expected = sign_webhook(secret, timestamp, body);
+
+    expected.len() == signature.len()
+        && expected
+            .bytes()
+            .zip(signature.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Delivers events to webhooks with retries and exponential backoff. Plug it
+/// into an [`EventBus`](crate::EventBus) as a sink; as a sink it delivers in
+/// the background, so a slow receiver does not hold up the bus, and events may
+/// arrive out of order. Deliveries that still fail are appended to the
+/// dead-letter file, if one is set.
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcher {
+    webhooks: Vec<Webhook>,
+    http: reqwest::Client,
+    max_attempts: u32,
+    backoff: Duration,
+    dead_letters: Option<PathBuf>,
+    dead_letters_lock: Arc<Mutex<()>>,
+    pending: Arc<Semaphore>,
+}
+
+impl Default for WebhookDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookDispatcher {
+    pub fn new() -> Self {
+        Self {
+            webhooks: Vec::new(),
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            max_attempts: 5,
+            backoff: Duration::from_secs(1),
+            dead_letters: None,
+            dead_letters_lock: Arc::default(),
+            pending: Arc::new(Semaphore::new(100)),
+        }
+    }
+
+    pub fn webhook(mut self, webhook: Webhook) -> Self {
+        self.webhooks.push(webhook);
+        self
+    }
+
+    /// Attempts per delivery, 5 by default.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Delay before the first retry, doubled after every further attempt.
+    /// 1 second by default.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Events delivered in the background at once, 100 by default. Events
+    /// published while the limit is reached go to the dead-letter file.
+    pub fn max_pending(mut self, events: usize) -> Self {
+        self.pending = Arc::new(Semaphore::new(events.max(1)));
+        self
+    }
+
+    /// JSON lines file failed deliveries are appended to.
+    pub fn dead_letters(mut self, path: impl Into<PathBuf>) -> Self {
+        self.dead_letters = Some(path.into());
+        self
+    }
+
+    /// Delivers `event` to every webhook that accepts it. Fails if any
+    /// delivery failed.
+    pub async fn dispatch(&self, event: &TimedEvent) -> Result<()> {
+        let mut failed = 0;
+
+        for webhook in self
+            .webhooks
+            .iter()
+            .filter(|webhook| webhook.accepts(&event.event))
+        {
+            if let Err((error, attempts)) = self.deliver(webhook, event).await {
+                failed += 1;
+                self.dead_letter(webhook, event.clone(), error, attempts)
+                    .await;
+            }
+        }
+
+        if failed > 0 {
+            bail!(
+                "{failed} webhook deliveries of {:?} failed",
+                event.event.kind()
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the last error and the number of attempts on failure.
+    async fn deliver(
+        &self,
+        webhook: &Webhook,
+        payload: &TimedEvent,
+    ) -> std::result::Result<(), (String, u32)> {
+        let body = serde_json::to_vec(payload).map_err(|error| (error.to_string(), 0))?;
+        let mut delay = self.backoff;
+        let mut attempt = 1;
+
+        loop {
+            match self.send(webhook, payload.event.kind(), &body).await {
+                Ok(()) => return Ok(()),
+                Err(Delivery::Rejected(error)) => return Err((error, attempt)),
+                Err(Delivery::Retry(error)) if attempt >= self.max_attempts => {
+                    return Err((error, attempt));
+                }
+                Err(Delivery::Retry(error)) => {
+                    log::debug!("webhook {} failed, retrying: {error}", webhook.url);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Signs every attempt with its own timestamp, so retries and redelivered
+    /// dead letters pass the receiver's replay check.
+    async fn send(
+        &self,
+        webhook: &Webhook,
+        kind: EventKind,
+        body: &[u8],
+    ) -> std::result::Result<(), Delivery> {
+        let timestamp = unix_now();
+        let mut request = self
+            .http
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(EVENT_HEADER, event_name(kind));
+        if let Some(secret) = &webhook.secret {
+            request = request.header(SIGNATURE_HEADER, sign_webhook(secret, timestamp, body));
+        }
+
+        let response = request
+            .body(body.to_vec())
+            .send()
+            .await
+            .map_err(|error| Delivery::Retry(error.to_string()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else if status.is_server_error() || status.as_u16() == 429 {
+            Err(Delivery::Retry(format!("webhook responded with {status}")))
+        } else {
+            Err(Delivery::Rejected(format!(
+                "webhook responded with {status}"
+            )))
+        }
+    }
+
+    async fn dead_letter(
+        &self,
+        webhook: &Webhook,
+        payload: TimedEvent,
+        error: String,
+        attempts: u32,
+    ) {
+        log::warn!(
+            "giving up on webhook {} after {attempts} attempts: {error}",
+            webhook.url
+        );
+
+        let Some(path) = &self.dead_letters else {
+            return;
+        };
+        let letter = DeadLetter {
+            url: webhook.url.clone(),
+            payload,
+            attempts,
+            error,
+        };
+
+        let _guard = self.dead_letters_lock.lock().await;
+        if let Err(error) = append_line(path, &letter) {
+            log::error!("failed to write dead letter to {}: {error}", path.display());
+        }
+    }
+
+    /// Redelivers the dead letters once each, keeping those that fail again
+    /// in the file. Letters the receiver rejects with a 4xx other than 429 are
+    /// dropped. Returns how many were delivered.
+    pub async fn retry_dead_letters(&self) -> Result<usize> {
+        let Some(path) = &self.dead_letters else {
+            return Ok(0);
+        };
+
+        let _guard = self.dead_letters_lock.lock().await;
+        let letters: Vec<DeadLetter> = match std::fs::read_to_string(path) {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<std::result::Result<_, _>>()?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut delivered = 0;
+        let mut remaining = Vec::new();
+        for mut letter in letters {
+            let webhook = self
+                .webhooks
+                .iter()
+                .find(|webhook| webhook.url == letter.url)
+                .cloned()
+                .unwrap_or_else(|| Webhook::new(letter.url.clone()));
+            let body = serde_json::to_vec(&letter.payload)?;
+
+            match self
+                .send(&webhook, letter.payload.event.kind(), &body)
+                .await
+            {
+                Ok(()) => delivered += 1,
+                Err(Delivery::Retry(error)) => {
+                    letter.attempts += 1;
+                    letter.error = error;
+                    remaining.push(letter);
+                }
+                Err(Delivery::Rejected(error)) => {
+                    log::warn!("dropping dead letter for {}: {error}", letter.url);
+                }
+            }
+        }
+
+        let mut content = String::new();
+        for letter in &remaining {
+            content.push_str(&serde_json::to_string(letter)?);
+            content.push('\n');
+        }
+        std::fs::write(path, content)?;
+
+        Ok(delivered)
+    }
+}
+
+impl EventSink for WebhookDispatcher {
+    fn publish<'a>(&'a self, event: &'a TimedEvent) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let Ok(permit) = self.pending.clone().try_acquire_owned() else {
+                for webhook in self
+                    .webhooks
+                    .iter()
+                    .filter(|webhook| webhook.accepts(&event.event))
+                {
+                    let error = "too many pending deliveries".to_string();
+                    self.dead_letter(webhook, event.clone(), error, 0).await;
+                }
+                bail!("too many pending webhook deliveries");
+            };
+
+            let dispatcher = self.clone();
+            let event = event.clone();
+            tokio::spawn(async move {
+                if let Err(error) = dispatcher.dispatch(&event).await {
+                    log::warn!("{error}");
+                }
+                drop(permit);
+            });
+            Ok(())
+        })
+    }
+}
+
+enum Delivery {
+    /// Network errors, 5xx and 429 responses.
+    Retry(String),
+    /// Other responses, which will not succeed on retry.
+    Rejected(String),
+}
+
+fn event_name(kind: EventKind) -> String {
+    serde_json::to_value(kind)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn append_line(path: &Path, letter: &DeadLetter) -> Result<()> {
+    let mut line = serde_json::to_string(letter)?;
+    line.push('\n');
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Received, http_receiver};
+
+    fn paused() -> TimedEvent {
+        TimedEvent {
+            time: 1_700_000_000,
+            event: Event::Paused {
+                instance_id: Uuid::from_u128(1),
+            },
+        }
+    }
+
+    fn dead_letter_path() -> PathBuf {
+        std::env::temp_dir().join(format!("dead-letters-{}.jsonl", Uuid::now_v7()))
+    }
+
+    fn read_dead_letters(path: &Path) -> Vec<DeadLetter> {
+        std::fs::read_to_string(path)
+            .unwrap_or_default()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn dispatcher(url: &str) -> WebhookDispatcher {
+        WebhookDispatcher::new()
+            .webhook(Webhook::new(url))
+            .backoff(Duration::from_millis(1))
+    }
+
+    fn drain(received: &mut tokio::sync::mpsc::UnboundedReceiver<Received>) -> usize {
+        std::iter::from_fn(|| received.try_recv().ok()).count()
+    }
+
+    #[test]
+    fn filters_events() {
+        let event = paused().event;
+
+        assert!(Webhook::new("http://localhost").accepts(&event));
+        assert!(
+            Webhook::new("http://localhost")
+                .events([EventKind::Paused])
+                .servers([Uuid::from_u128(1)])
+                .accepts(&event)
+        );
+        assert!(
+            !Webhook::new("http://localhost")
+                .events([EventKind::Resumed])
+                .accepts(&event)
+        );
+        assert!(
+            !Webhook::new("http://localhost")
+                .servers([Uuid::from_u128(2)])
+                .accepts(&event)
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_signatures() {
+        let signature = sign_webhook("secret", 10, b"{}");
+
+        assert!(signature.starts_with("sha256="));
+        assert!(verify_webhook("secret", 10, b"{}", &signature));
+        assert!(!verify_webhook("secret", 11, b"{}", &signature));
+        assert!(!verify_webhook("other", 10, b"{}", &signature));
+        assert!(!verify_webhook("secret", 10, b"{ }", &signature));
+    }
+
+    #[tokio::test]
+    async fn signed_deliveries_verify() {
+        let (url, mut received) = http_receiver(Vec::new()).await;
+        let dispatcher = WebhookDispatcher::new()
+            .webhook(Webhook::new(format!("{url}/hooks/events")).secret("secret"));
+
+        dispatcher.dispatch(&paused()).await.unwrap();
+
+        let request = received.recv().await.unwrap();
+        assert_eq!(request.path, "/hooks/events");
+        let timestamp: i64 = request.headers["x-nimbuspulse-timestamp"].parse().unwrap();
+        assert!(verify_webhook(
+            "secret",
+            timestamp,
+            &request.body,
+            &request.headers["x-nimbuspulse-signature"]
+        ));
+        assert_eq!(request.headers["x-nimbuspulse-event"], "paused");
+        assert_eq!(
+            serde_json::from_slice::<TimedEvent>(&request.body).unwrap(),
+            paused()
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_and_rate_limits() {
+        let (url, mut received) = http_receiver(vec![500, 429]).await;
+
+        dispatcher(&url).dispatch(&paused()).await.unwrap();
+
+        assert_eq!(drain(&mut received), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let (url, mut received) = http_receiver(vec![400]).await;
+        let path = dead_letter_path();
+
+        let result = dispatcher(&url)
+            .dead_letters(&path)
+            .dispatch(&paused())
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(drain(&mut received), 1);
+        let letters = read_dead_letters(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(letters[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn dead_letters_after_max_attempts() {
+        let (url, mut received) = http_receiver(vec![503, 503, 503]).await;
+        let path = dead_letter_path();
+
+        let result = dispatcher(&url)
+            .max_attempts(3)
+            .dead_letters(&path)
+            .dispatch(&paused())
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(drain(&mut received), 3);
+        let letters = read_dead_letters(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            letters,
+            [DeadLetter {
+                url,
+                payload: paused(),
+                attempts: 3,
+                error: "webhook responded with 503 Service Unavailable".to_string(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn retrying_dead_letters_keeps_only_retryable_failures() {
+        let (delivered, mut received) = http_receiver(Vec::new()).await;
+        let (unavailable, _) = http_receiver(vec![503]).await;
+        let (rejected, _) = http_receiver(vec![410]).await;
+        let path = dead_letter_path();
+
+        for url in [&delivered, &unavailable, &rejected] {
+            let letter = DeadLetter {
+                url: url.clone(),
+                payload: paused(),
+                attempts: 5,
+                error: "timed out".to_string(),
+            };
+            append_line(&path, &letter).unwrap();
+        }
+
+        let dispatcher = WebhookDispatcher::new()
+            .webhook(Webhook::new(&delivered).secret("secret"))
+            .dead_letters(&path);
+        assert_eq!(dispatcher.retry_dead_letters().await.unwrap(), 1);
+
+        let letters = read_dead_letters(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].url, unavailable);
+        assert_eq!(letters[0].attempts, 6);
+
+        // Re-signed for the redelivery, not with the time of the event.
+        let request = received.recv().await.unwrap();
+        let timestamp: i64 = request.headers["x-nimbuspulse-timestamp"].parse().unwrap();
+        assert!(timestamp > paused().time);
+        assert!(verify_webhook(
+            "secret",
+            timestamp,
+            &request.body,
+            &request.headers["x-nimbuspulse-signature"]
+        ));
+    }
+
+    #[tokio::test]
+    async fn publishing_does_not_wait_for_delivery() {
+        // Accepts connections but never answers.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+        let path = dead_letter_path();
+        let dispatcher = dispatcher(&url).max_pending(1).dead_letters(&path);
+
+        let published = tokio::time::timeout(Duration::from_secs(1), async {
+            dispatcher.publish(&paused()).await.unwrap();
+            dispatcher.publish(&paused()).await
+        })
+        .await
+        .unwrap();
+
+        assert!(published.is_err());
+        let letters = read_dead_letters(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].attempts, 0);
+    }
+}